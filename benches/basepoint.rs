@@ -0,0 +1,40 @@
+use criterion::{criterion_group, criterion_main, Criterion};
+use curve25519_dalek::{
+    constants::{RISTRETTO_BASEPOINT_POINT, RISTRETTO_BASEPOINT_TABLE},
+    RistrettoPoint, Scalar,
+};
+use nym::proof::dlog_eq::{prove_noninteractive, Publics, Secrets};
+use rand::thread_rng;
+
+fn basepoint_mul(c: &mut Criterion) {
+    let s = Scalar::random(&mut thread_rng());
+    let mut group = c.benchmark_group("basepoint multiplication");
+    group.bench_function("naive", |b| b.iter(|| s * RISTRETTO_BASEPOINT_POINT));
+    group.bench_function("table", |b| b.iter(|| &s * RISTRETTO_BASEPOINT_TABLE));
+    group.finish();
+}
+
+fn dlog_eq_prove(c: &mut Criterion) {
+    let x = Scalar::random(&mut thread_rng());
+    let g = RistrettoPoint::random(&mut thread_rng());
+    let g2 = RistrettoPoint::random(&mut thread_rng());
+    let mut group = c.benchmark_group("dlog_eq::prove_noninteractive");
+    for (name, g1) in [
+        ("base point", RISTRETTO_BASEPOINT_POINT),
+        ("other point", g),
+    ] {
+        let publics = Publics {
+            g1: &g1,
+            h1: &(x * g1),
+            g2: &g2,
+            h2: &(x * g2),
+        };
+        group.bench_function(name, |b| {
+            b.iter(|| prove_noninteractive(publics, Secrets { x: &x }))
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, basepoint_mul, dlog_eq_prove);
+criterion_main!(benches);
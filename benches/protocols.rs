@@ -0,0 +1,200 @@
+#![allow(mixed_script_confusables)]
+
+use std::future::Future;
+
+use criterion::{
+    criterion_group, criterion_main, measurement::WallTime, BenchmarkGroup, Criterion, Throughput,
+};
+use curve25519_dalek::{RistrettoPoint, Scalar};
+use futures::{executor::block_on, future::try_join, io};
+use nym::{
+    proof::{
+        blind_dlog_eq::{self, VerifierSecrets},
+        dlog_eq::{self, prove_noninteractive, Publics, Secrets},
+    },
+    transport::{
+        codec::{Bincode, Codec as _},
+        memory::DuplexTransport,
+        Transport,
+    },
+    Org, OrgSecretKey, User, UserSecretKey,
+};
+use rand::thread_rng;
+use serde::{Deserialize, Serialize};
+
+/// An in-memory transport that records the serialized size of each message sent
+struct Measured {
+    inner: DuplexTransport<Bincode>,
+    sizes: Vec<(&'static [u8], usize)>,
+}
+
+fn pair() -> (Measured, Measured) {
+    let (t1, t2) = DuplexTransport::pair();
+    let wrap = |inner| Measured {
+        inner,
+        sizes: Vec::new(),
+    };
+    (wrap(t1), wrap(t2))
+}
+
+impl Transport for Measured {
+    async fn receive<V: for<'a> Deserialize<'a>>(
+        &mut self,
+        label: &'static [u8],
+    ) -> Result<V, io::Error> {
+        self.inner.receive(label).await
+    }
+
+    fn send<V: Serialize>(
+        &mut self,
+        label: &'static [u8],
+        value: V,
+    ) -> impl Future<Output = Result<(), io::Error>> + Send {
+        if let Ok(bytes) = Bincode::encode(&value) {
+            self.sizes.push((label, bytes.len()));
+        }
+        self.inner.send(label, value)
+    }
+}
+
+/// Runs a two-party protocol once to report its message sizes, then benchmarks it
+fn bench_protocol<F, G, A, B>(
+    group: &mut BenchmarkGroup<'_, WallTime>,
+    name: &str,
+    first: F,
+    second: G,
+) where
+    F: Fn(Measured) -> A,
+    G: Fn(Measured) -> B,
+    A: Future<Output = nym::Result<Measured>>,
+    B: Future<Output = nym::Result<Measured>>,
+{
+    let (t1, t2) = pair();
+    let (t1, t2) = block_on(try_join(first(t1), second(t2))).unwrap();
+    let mut sizes = t1.sizes;
+    sizes.extend(t2.sizes);
+    for (label, size) in &sizes {
+        println!(
+            "{name}: message {:?} is {size} bytes",
+            String::from_utf8_lossy(label)
+        );
+    }
+    let total: usize = sizes.iter().map(|(_, size)| size).sum();
+    println!("{name}: {total} bytes in total");
+
+    group.throughput(Throughput::Bytes(total as u64));
+    group.bench_function(name, |b| {
+        b.iter(|| {
+            let (t1, t2) = pair();
+            block_on(try_join(first(t1), second(t2))).unwrap()
+        })
+    });
+}
+
+fn dlog_eq(c: &mut Criterion) {
+    let x = Scalar::random(&mut thread_rng());
+    let g1 = RistrettoPoint::random(&mut thread_rng());
+    let g2 = RistrettoPoint::random(&mut thread_rng());
+    let (h1, h2) = (x * g1, x * g2);
+    let publics = Publics {
+        g1: &g1,
+        h1: &h1,
+        g2: &g2,
+        h2: &h2,
+    };
+    let secrets = Secrets { x: &x };
+
+    let mut group = c.benchmark_group("dlog_eq");
+    group.bench_function("prove_noninteractive", |b| {
+        b.iter(|| prove_noninteractive(publics, secrets))
+    });
+    let transcript = prove_noninteractive(publics, secrets);
+    group.bench_function("verify_noninteractive", |b| {
+        b.iter(|| transcript.verify(publics).unwrap())
+    });
+    bench_protocol(
+        &mut group,
+        "interactive",
+        |mut t| async move {
+            dlog_eq::prove(&mut t, publics, secrets).await?;
+            Ok(t)
+        },
+        |mut t| async move {
+            dlog_eq::verify(&mut t, publics).await?;
+            Ok(t)
+        },
+    );
+    group.finish();
+}
+
+fn blind_dlog_eq(c: &mut Criterion) {
+    let x = Scalar::random(&mut thread_rng());
+    let gamma = Scalar::random(&mut thread_rng());
+    let g1 = RistrettoPoint::random(&mut thread_rng());
+    let g2 = RistrettoPoint::random(&mut thread_rng());
+    let (h1, h2) = (x * g1, x * g2);
+    let publics = Publics {
+        g1: &g1,
+        h1: &h1,
+        g2: &g2,
+        h2: &h2,
+    };
+
+    let mut group = c.benchmark_group("blind_dlog_eq");
+    bench_protocol(
+        &mut group,
+        "interactive",
+        |mut t| async move {
+            blind_dlog_eq::prove(&mut t, publics, Secrets { x: &x }).await?;
+            Ok(t)
+        },
+        |mut t| async move {
+            blind_dlog_eq::verify(&mut t, publics, VerifierSecrets { γ: &gamma }).await?;
+            Ok(t)
+        },
+    );
+    group.finish();
+}
+
+fn nym_protocols(c: &mut Criterion) {
+    let user = User::new(UserSecretKey::random(&mut thread_rng()));
+    let org = Org::new(OrgSecretKey::random(&mut thread_rng()));
+    let (mut t1, mut t2) = pair();
+    let (nym, _) = block_on(try_join(
+        user.generate_nym(&mut t1),
+        org.generate_nym(&mut t2),
+    ))
+    .unwrap();
+
+    let (user, org) = (&user, &org);
+    let mut group = c.benchmark_group("nym");
+    bench_protocol(
+        &mut group,
+        "generate_nym",
+        |mut t| async move {
+            user.generate_nym(&mut t).await?;
+            Ok(t)
+        },
+        |mut t| async move {
+            org.generate_nym(&mut t).await?;
+            Ok(t)
+        },
+    );
+    bench_protocol(
+        &mut group,
+        "issue_credential",
+        |mut t| async move {
+            user.issue_credential(&mut t, nym, org.public_key(), 0)
+                .await?;
+            Ok(t)
+        },
+        |mut t| async move {
+            org.issue_credential(&mut t, nym, 0).await?;
+            Ok(t)
+        },
+    );
+    group.finish();
+}
+
+criterion_group!(benches, dlog_eq, blind_dlog_eq, nym_protocols);
+criterion_main!(benches);
@@ -0,0 +1,112 @@
+//! Enrolling a user with an organization over TCP
+//!
+//! The server plays an [`Org`] and the client a [`User`]. For each connection they generate a
+//! nym, the organization issues a credential on it, and the user authenticates as the owner of
+//! the nym.
+//!
+//! Run both sides in one process, over a loopback connection:
+//!
+//! ```sh
+//! cargo run --example tcp_enroll --features tokio
+//! ```
+//!
+//! Or run them separately, with the server serving clients until interrupted:
+//!
+//! ```sh
+//! cargo run --example tcp_enroll --features tokio -- server 127.0.0.1:7878
+//! cargo run --example tcp_enroll --features tokio -- client 127.0.0.1:7878
+//! ```
+//!
+//! Either side stops at the first protocol error, reporting it and exiting with a failure status.
+
+use std::{env, process::ExitCode};
+
+use nym::{
+    transport::{checked, CheckedTransport, LocalTransport as _, TcpTransport},
+    Error, Org, OrgPublicKey, OrgSecretKey, User, UserSecretKey,
+};
+use rand::thread_rng;
+use tokio::net::{TcpListener, TcpStream};
+
+/// Label for the organization's public key
+const KEY: &[u8] = b"key";
+
+/// Epoch for the issued credentials
+const EPOCH: u64 = 0;
+
+type Channel = CheckedTransport<TcpTransport>;
+
+/// Enrolls the user connected on a channel
+async fn serve(org: &Org, user: &mut Channel) -> Result<(), Error> {
+    // Clients should have this key from a trusted source; it is sent here to keep the example
+    // self-contained
+    user.send(KEY, org.public_key()).await?;
+    let nym = org.generate_nym(user).await?;
+    org.issue_credential(user, nym, EPOCH).await?;
+    org.authenticate_nym(user, nym).await?;
+    Ok(())
+}
+
+/// Enrolls with the organization connected on a channel
+async fn enroll(user: &User, org: &mut Channel) -> Result<(), Error> {
+    let key: OrgPublicKey = org.receive(KEY).await?;
+    let nym = user.generate_nym(org).await?;
+    user.issue_credential(org, nym, key, EPOCH).await?;
+    user.authenticate_nym(org, nym).await?;
+    Ok(())
+}
+
+async fn server(addr: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let org = Org::new(OrgSecretKey::random(&mut thread_rng()));
+    let listener = TcpListener::bind(addr).await?;
+    println!("serving on {}", listener.local_addr()?);
+    loop {
+        let (stream, peer) = listener.accept().await?;
+        serve(&org, &mut checked(TcpTransport::new(stream))).await?;
+        println!("enrolled {peer}");
+    }
+}
+
+async fn client(addr: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let user = User::new(UserSecretKey::random(&mut thread_rng()));
+    let stream = TcpStream::connect(addr).await?;
+    enroll(&user, &mut checked(TcpTransport::new(stream))).await?;
+    println!("enrolled with {addr}");
+    Ok(())
+}
+
+async fn both() -> Result<(), Box<dyn std::error::Error>> {
+    let org = Org::new(OrgSecretKey::random(&mut thread_rng()));
+    let user = User::new(UserSecretKey::random(&mut thread_rng()));
+    let listener = TcpListener::bind("127.0.0.1:0").await?;
+    let (client, server) = tokio::join!(
+        TcpStream::connect(listener.local_addr()?),
+        listener.accept()
+    );
+    let mut o_channel = checked(TcpTransport::new(client?));
+    let mut u_channel = checked(TcpTransport::new(server?.0));
+    tokio::try_join!(serve(&org, &mut u_channel), enroll(&user, &mut o_channel))?;
+    println!("enrolled");
+    Ok(())
+}
+
+#[tokio::main(flavor = "current_thread")]
+async fn main() -> ExitCode {
+    let args: Vec<_> = env::args().skip(1).collect();
+    let res = match args.iter().map(String::as_str).collect::<Vec<_>>()[..] {
+        [] => both().await,
+        ["server", addr] => server(addr).await,
+        ["client", addr] => client(addr).await,
+        _ => {
+            eprintln!("usage: tcp_enroll [server ADDR | client ADDR]");
+            return ExitCode::FAILURE;
+        }
+    };
+    match res {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("error: {e}");
+            ExitCode::FAILURE
+        }
+    }
+}
@@ -0,0 +1,29 @@
+//! Decodes and verifies transcripts from arbitrary bytes, which should never panic
+
+#![no_main]
+
+use curve25519_dalek::{ristretto::CompressedRistretto, RistrettoPoint};
+use libfuzzer_sys::fuzz_target;
+use nym::proof::dlog_eq::{Publics, Transcript};
+
+fuzz_target!(|data: &[u8]| {
+    if data.len() < 4 * 32 {
+        return;
+    }
+    let (publics, transcript) = data.split_at(4 * 32);
+    let Some(points) = publics
+        .chunks(32)
+        .map(|chunk| CompressedRistretto::from_slice(chunk).ok()?.decompress())
+        .collect::<Option<Vec<RistrettoPoint>>>()
+    else {
+        return;
+    };
+    if let Ok(t) = Transcript::try_from_bytes(transcript) {
+        let _ = t.verify(Publics {
+            g1: &points[0],
+            h1: &points[1],
+            g2: &points[2],
+            h2: &points[3],
+        });
+    }
+});
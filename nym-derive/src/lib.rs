@@ -0,0 +1,110 @@
+#![warn(missing_docs)]
+
+//! Derive macros for nym
+
+use proc_macro::TokenStream;
+use proc_macro2::{Span, TokenStream as TokenStream2};
+use quote::quote;
+use syn::{
+    ext::IdentExt as _, parse_macro_input, parse_quote, Data, DeriveInput, Error, GenericParam,
+    LitByteStr, LitStr, Result,
+};
+
+/// Derives `Transcribe` for a struct
+///
+/// The number of fields is appended under the given label, followed by each field in declaration
+/// order, under its name as the label. Fields of tuple structs are labelled `$0`, `$1`, and so on.
+///
+/// Fields can be skipped with `#[transcribe(skip)]`, and given a custom label with
+/// `#[transcribe(label = "...")]`.
+#[proc_macro_derive(Transcribe, attributes(transcribe))]
+pub fn derive_transcribe(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand(input)
+        .unwrap_or_else(Error::into_compile_error)
+        .into()
+}
+
+fn expand(mut input: DeriveInput) -> Result<TokenStream2> {
+    let fields = match &input.data {
+        Data::Struct(data) => &data.fields,
+        _ => {
+            return Err(Error::new_spanned(
+                &input.ident,
+                "Transcribe can only be derived for structs",
+            ))
+        }
+    };
+
+    let mut appends = Vec::new();
+    for (i, field) in fields.iter().enumerate() {
+        let options = FieldOptions::parse(&field.attrs)?;
+        if options.skip {
+            continue;
+        }
+        let member = match &field.ident {
+            Some(ident) => quote!(#ident),
+            None => {
+                let index = syn::Index::from(i);
+                quote!(#index)
+            }
+        };
+        let label = match (options.label, &field.ident) {
+            (Some(label), _) => label.value(),
+            (None, Some(ident)) => ident.unraw().to_string(),
+            (None, None) => format!("${i}"),
+        };
+        let label = LitByteStr::new(label.as_bytes(), Span::call_site());
+        appends.push(quote! {
+            ::nym::hash::Transcribe::append_to(&self.#member, t, #label);
+        });
+    }
+    let len = appends.len();
+
+    for param in &mut input.generics.params {
+        if let GenericParam::Type(param) = param {
+            param.bounds.push(parse_quote!(::nym::hash::Transcribe));
+        }
+    }
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    Ok(quote! {
+        impl #impl_generics ::nym::hash::Transcribe for #name #ty_generics #where_clause {
+            fn append_to<__S: ::nym::hash::AppendMessage + ?Sized>(
+                &self,
+                t: &mut __S,
+                label: &'static [u8],
+            ) {
+                ::nym::hash::append_length(t, label, #len);
+                #(#appends)*
+            }
+        }
+    })
+}
+
+/// Options from `#[transcribe(...)]` attributes on a field
+#[derive(Default)]
+struct FieldOptions {
+    skip: bool,
+    label: Option<LitStr>,
+}
+
+impl FieldOptions {
+    fn parse(attrs: &[syn::Attribute]) -> Result<Self> {
+        let mut options = Self::default();
+        for attr in attrs.iter().filter(|a| a.path().is_ident("transcribe")) {
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("skip") {
+                    options.skip = true;
+                    Ok(())
+                } else if meta.path.is_ident("label") {
+                    options.label = Some(meta.value()?.parse()?);
+                    Ok(())
+                } else {
+                    Err(meta.error("unknown transcribe option"))
+                }
+            })?;
+        }
+        Ok(options)
+    }
+}
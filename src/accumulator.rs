@@ -0,0 +1,230 @@
+//! Accumulators of issued nyms
+
+use std::collections::HashMap;
+
+use digest::{generic_array::typenum::U32, Digest as _};
+use merlin::Transcript;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    error::{Error, Result},
+    hash::TranscriptDigest as _,
+    Nym,
+};
+
+/// A set of pseudonyms, committed to by the root of a binary Merkle tree
+///
+/// An organization can publish the [`root`](NymSet::root) of the set of pseudonyms it issued, and
+/// hand out [`MerkleProof`]s of membership, which anyone can check against the root without the
+/// organization being online. This complements [`RevocationList`](crate::RevocationList), which
+/// lists the pseudonyms that are no longer valid.
+///
+/// Leaves are the hashes of the compressed nyms, in the order they were inserted. A node without a
+/// sibling is carried up to the next level unchanged.
+#[derive(Default, Debug, Clone)]
+pub struct NymSet {
+    leaves: Vec<[u8; 32]>,
+    indices: HashMap<[u8; Nym::LENGTH], usize>,
+}
+
+/// A proof that a pseudonym is in a [`NymSet`] with a given root
+#[derive(PartialEq, Eq, Debug, Clone, Serialize, Deserialize)]
+pub struct MerkleProof {
+    path: Vec<Sibling>,
+}
+
+/// The sibling of a node on the path from a leaf to the root
+#[derive(PartialEq, Eq, Debug, Clone, Serialize, Deserialize)]
+enum Sibling {
+    Left([u8; 32]),
+    Right([u8; 32]),
+}
+
+impl NymSet {
+    /// Creates an empty set
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Gets the number of pseudonyms in this set
+    pub fn len(&self) -> usize {
+        self.leaves.len()
+    }
+
+    /// Checks whether this set is empty
+    pub fn is_empty(&self) -> bool {
+        self.leaves.is_empty()
+    }
+
+    /// Inserts a pseudonym, returning whether it was not in the set yet
+    pub fn insert(&mut self, nym: Nym) -> bool {
+        let bytes = nym.to_bytes();
+        if self.indices.contains_key(&bytes) {
+            return false;
+        }
+        self.indices.insert(bytes, self.leaves.len());
+        self.leaves.push(leaf_hash(&bytes));
+        true
+    }
+
+    /// Checks whether a pseudonym is in this set
+    pub fn contains(&self, nym: &Nym) -> bool {
+        self.indices.contains_key(&nym.to_bytes())
+    }
+
+    /// Gets the root of the Merkle tree, which commits to every pseudonym in this set
+    pub fn root(&self) -> [u8; 32] {
+        match self.levels().last() {
+            Some(top) => top[0],
+            None => hash(b"nym/0.1/nym-set/empty", &[]),
+        }
+    }
+
+    /// Proves that a pseudonym is in this set, or returns `None` if it isn't
+    ///
+    /// The proof only verifies against the current [`root`](NymSet::root), so it has to be
+    /// obtained again after inserting other pseudonyms.
+    pub fn prove_membership(&self, nym: &Nym) -> Option<MerkleProof> {
+        let mut index = *self.indices.get(&nym.to_bytes())?;
+        let levels = self.levels();
+        let mut path = Vec::new();
+        for level in &levels[..levels.len() - 1] {
+            let sibling = index ^ 1;
+            if let Some(&hash) = level.get(sibling) {
+                path.push(if sibling < index {
+                    Sibling::Left(hash)
+                } else {
+                    Sibling::Right(hash)
+                });
+            }
+            index /= 2;
+        }
+        Some(MerkleProof { path })
+    }
+
+    /// Verifies that a pseudonym is in the set with a given root
+    ///
+    /// This fails with [`Error::NotMember`] if the proof doesn't lead from the pseudonym to the
+    /// root.
+    pub fn verify_membership(root: &[u8; 32], nym: &Nym, proof: &MerkleProof) -> Result {
+        let computed = proof
+            .path
+            .iter()
+            .fold(leaf_hash(&nym.to_bytes()), |hash, sibling| match sibling {
+                Sibling::Left(left) => node_hash(left, &hash),
+                Sibling::Right(right) => node_hash(&hash, right),
+            });
+        if computed != *root {
+            return Err(Error::NotMember);
+        }
+        Ok(())
+    }
+
+    /// Computes every level of the tree, from the leaves up to the root
+    fn levels(&self) -> Vec<Vec<[u8; 32]>> {
+        if self.leaves.is_empty() {
+            return Vec::new();
+        }
+        let mut levels = vec![self.leaves.clone()];
+        while levels[levels.len() - 1].len() > 1 {
+            let next = levels[levels.len() - 1]
+                .chunks(2)
+                .map(|pair| match pair {
+                    [left, right] => node_hash(left, right),
+                    [single] => *single,
+                    _ => unreachable!(),
+                })
+                .collect();
+            levels.push(next);
+        }
+        levels
+    }
+}
+
+fn leaf_hash(nym: &[u8]) -> [u8; 32] {
+    hash(b"nym/0.1/nym-set/leaf", &[nym])
+}
+
+fn node_hash(left: &[u8; 32], right: &[u8; 32]) -> [u8; 32] {
+    hash(b"nym/0.1/nym-set/node", &[&left[..], &right[..]])
+}
+
+/// Hashes some parts with the transcript digest, domain-separated so that leaves and inner nodes
+/// can't be confused
+fn hash(domain: &'static [u8], parts: &[&[u8]]) -> [u8; 32] {
+    let mut digest = Transcript::new(domain).into_digest::<U32>();
+    for part in parts {
+        digest.update(part);
+    }
+    digest.finalize().into()
+}
+
+#[cfg(test)]
+mod test {
+    use std::assert_matches::assert_matches;
+
+    use curve25519_dalek::RistrettoPoint;
+    use rand::thread_rng;
+
+    use crate::{Error, Nym};
+
+    use super::NymSet;
+
+    fn random_nym() -> Nym {
+        Nym::from_points(
+            RistrettoPoint::random(&mut thread_rng()),
+            RistrettoPoint::random(&mut thread_rng()),
+        )
+    }
+
+    #[test]
+    fn membership() {
+        for size in [1, 2, 3, 7, 8] {
+            let nyms: Vec<_> = (0..size).map(|_| random_nym()).collect();
+            let mut set = NymSet::new();
+            for &nym in &nyms {
+                assert!(set.insert(nym));
+            }
+            assert!(!set.insert(nyms[0]), "nyms should only be inserted once");
+            assert_eq!(set.len(), size);
+
+            let root = set.root();
+            for nym in &nyms {
+                let proof = set.prove_membership(nym).unwrap();
+                assert_matches!(NymSet::verify_membership(&root, nym, &proof), Ok(_));
+            }
+        }
+    }
+
+    #[test]
+    fn non_membership() {
+        let nyms: Vec<_> = (0..5).map(|_| random_nym()).collect();
+        let mut set = NymSet::new();
+        for &nym in &nyms {
+            set.insert(nym);
+        }
+        let root = set.root();
+
+        let outsider = random_nym();
+        assert!(!set.contains(&outsider));
+        assert_eq!(set.prove_membership(&outsider), None);
+
+        let proof = set.prove_membership(&nyms[2]).unwrap();
+        assert_matches!(
+            NymSet::verify_membership(&root, &outsider, &proof),
+            Err(Error::NotMember)
+        );
+        assert_matches!(
+            NymSet::verify_membership(&root, &nyms[3], &proof),
+            Err(Error::NotMember)
+        );
+
+        set.insert(outsider);
+        assert_ne!(set.root(), root);
+        assert_matches!(
+            NymSet::verify_membership(&set.root(), &nyms[2], &proof),
+            Err(Error::NotMember)
+        );
+        assert_ne!(NymSet::new().root(), root);
+    }
+}
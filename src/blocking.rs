@@ -0,0 +1,336 @@
+//! Blocking wrappers for the protocols
+//!
+//! These wrappers run the protocols to completion on the current thread, over a synchronous
+//! [`BlockingTransport`], so that they can be used without an async runtime.
+
+use curve25519_dalek::Scalar;
+use futures::{executor::block_on, io};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    error::Result,
+    key::{OrgPublicKey, UserPublicKey},
+    nym::{AttrCred, Cred, Nym, Org, User},
+    transport::LocalTransport,
+};
+
+/// A synchronous transport for protocols
+pub trait BlockingTransport {
+    /// Receives a value with a given label, blocking until it arrives
+    fn receive<V: for<'a> Deserialize<'a>>(&mut self, label: &'static [u8])
+        -> Result<V, io::Error>;
+
+    /// Sends a value with a given label
+    fn send<V: Serialize>(&mut self, label: &'static [u8], value: V) -> Result<(), io::Error>;
+}
+
+impl<T: BlockingTransport + ?Sized> BlockingTransport for &mut T {
+    fn receive<V: for<'a> Deserialize<'a>>(
+        &mut self,
+        label: &'static [u8],
+    ) -> Result<V, io::Error> {
+        (**self).receive(label)
+    }
+
+    fn send<V: Serialize>(&mut self, label: &'static [u8], value: V) -> Result<(), io::Error> {
+        (**self).send(label, value)
+    }
+}
+
+/// A transport adapter that lifts a [`BlockingTransport`] into a [`LocalTransport`]
+///
+/// The resulting futures block the thread that polls them.
+pub struct BlockingAdapter<T> {
+    inner: T,
+}
+
+impl<T> BlockingAdapter<T> {
+    /// Wraps a blocking transport
+    pub fn new(inner: T) -> Self {
+        Self { inner }
+    }
+
+    /// Gets the wrapped transport back
+    pub fn into_inner(self) -> T {
+        self.inner
+    }
+}
+
+impl<T: BlockingTransport> LocalTransport for BlockingAdapter<T> {
+    async fn receive<V: for<'a> Deserialize<'a>>(
+        &mut self,
+        label: &'static [u8],
+    ) -> Result<V, io::Error> {
+        self.inner.receive(label)
+    }
+
+    async fn send<V: Serialize>(
+        &mut self,
+        label: &'static [u8],
+        value: V,
+    ) -> Result<(), io::Error> {
+        self.inner.send(label, value)
+    }
+}
+
+/// A user that runs the protocols synchronously
+pub struct BlockingUser {
+    user: User,
+}
+
+/// An organization that runs the protocols synchronously
+pub struct BlockingOrg {
+    org: Org,
+}
+
+impl BlockingUser {
+    /// Wraps a user
+    pub fn new(user: User) -> Self {
+        Self { user }
+    }
+
+    /// Gets the wrapped user
+    pub fn user(&self) -> &User {
+        &self.user
+    }
+
+    /// Gets the wrapped user back
+    pub fn into_inner(self) -> User {
+        self.user
+    }
+
+    /// Generates a pseudonym
+    pub fn generate_nym<T: BlockingTransport>(&self, org: &mut T) -> Result<Nym> {
+        block_on(self.user.generate_nym(&mut BlockingAdapter::new(org)))
+    }
+
+    /// Generates a pseudonym with a CA
+    pub fn generate_nym_with_ca<T: BlockingTransport>(&self, org: &mut T) -> Result<Nym> {
+        block_on(
+            self.user
+                .generate_nym_with_ca(&mut BlockingAdapter::new(org)),
+        )
+    }
+
+    /// Authenticates this user as the holder of a given nym
+    pub fn authenticate_nym<T: BlockingTransport>(&self, org: &mut T, nym: Nym) -> Result {
+        block_on(
+            self.user
+                .authenticate_nym(&mut BlockingAdapter::new(org), nym),
+        )
+    }
+
+    /// Issues a new credential for a given nym, valid for a given epoch
+    pub fn issue_credential<T: BlockingTransport>(
+        &self,
+        org: &mut T,
+        nym: Nym,
+        source_key: OrgPublicKey,
+        epoch: u64,
+    ) -> Result<Cred> {
+        block_on(
+            self.user
+                .issue_credential(&mut BlockingAdapter::new(org), nym, source_key, epoch),
+        )
+    }
+
+    /// Issues a new credential for a given nym, valid for a given epoch, carrying some attributes
+    pub fn issue_credential_with_attributes<T: BlockingTransport>(
+        &self,
+        org: &mut T,
+        nym: Nym,
+        source_key: OrgPublicKey,
+        epoch: u64,
+        attributes: &[Scalar],
+    ) -> Result<AttrCred> {
+        block_on(self.user.issue_credential_with_attributes(
+            &mut BlockingAdapter::new(org),
+            nym,
+            source_key,
+            epoch,
+            attributes,
+        ))
+    }
+
+    /// Transfers a credential from one organization to another
+    pub fn transfer_credential<T: BlockingTransport>(
+        &self,
+        org: &mut T,
+        nym: Nym,
+        cred: Cred,
+    ) -> Result {
+        block_on(
+            self.user
+                .transfer_credential(&mut BlockingAdapter::new(org), nym, cred),
+        )
+    }
+}
+
+impl BlockingOrg {
+    /// Wraps an organization
+    pub fn new(org: Org) -> Self {
+        Self { org }
+    }
+
+    /// Gets the wrapped organization
+    pub fn org(&self) -> &Org {
+        &self.org
+    }
+
+    /// Gets the wrapped organization back
+    pub fn into_inner(self) -> Org {
+        self.org
+    }
+
+    /// Generates a pseudonym
+    pub fn generate_nym<T: BlockingTransport>(&self, user: &mut T) -> Result<Nym> {
+        block_on(self.org.generate_nym(&mut BlockingAdapter::new(user)))
+    }
+
+    /// Generates a pseudonym as the CA
+    pub fn generate_nym_as_ca<T: BlockingTransport>(
+        &self,
+        user: &mut T,
+        user_key: UserPublicKey,
+    ) -> Result<Nym> {
+        block_on(
+            self.org
+                .generate_nym_as_ca(&mut BlockingAdapter::new(user), user_key),
+        )
+    }
+
+    /// Authenticates a user as the holder of a given nym
+    pub fn authenticate_nym<T: BlockingTransport>(&self, user: &mut T, nym: Nym) -> Result {
+        block_on(
+            self.org
+                .authenticate_nym(&mut BlockingAdapter::new(user), nym),
+        )
+    }
+
+    /// Issues a new credential for a given nym, valid for a given epoch
+    pub fn issue_credential<T: BlockingTransport>(
+        &self,
+        user: &mut T,
+        nym: Nym,
+        epoch: u64,
+    ) -> Result {
+        block_on(
+            self.org
+                .issue_credential(&mut BlockingAdapter::new(user), nym, epoch),
+        )
+    }
+
+    /// Issues a new credential for a given nym, valid for a given epoch, carrying some attributes
+    pub fn issue_credential_with_attributes<T: BlockingTransport>(
+        &self,
+        user: &mut T,
+        nym: Nym,
+        epoch: u64,
+        attributes: &[Scalar],
+    ) -> Result {
+        block_on(self.org.issue_credential_with_attributes(
+            &mut BlockingAdapter::new(user),
+            nym,
+            epoch,
+            attributes,
+        ))
+    }
+
+    /// Transfers a credential from one organization to another
+    ///
+    /// Credentials issued for an epoch before `epoch` are rejected as expired.
+    pub fn transfer_credential<T: BlockingTransport>(
+        &self,
+        user: &mut T,
+        nym: Nym,
+        cred: Cred,
+        source_key: OrgPublicKey,
+        epoch: u64,
+    ) -> Result {
+        block_on(self.org.transfer_credential(
+            &mut BlockingAdapter::new(user),
+            nym,
+            cred,
+            source_key,
+            epoch,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{
+        sync::mpsc::{channel, Receiver, Sender},
+        thread,
+    };
+
+    use futures::io;
+    use rand::thread_rng;
+    use serde::{Deserialize, Serialize};
+
+    use crate::{Org, OrgSecretKey, User, UserSecretKey};
+
+    use super::{BlockingOrg, BlockingTransport, BlockingUser};
+
+    struct ChannelTransport {
+        sender: Sender<Vec<u8>>,
+        receiver: Receiver<Vec<u8>>,
+    }
+
+    fn pair() -> (ChannelTransport, ChannelTransport) {
+        let (s1, r2) = channel();
+        let (s2, r1) = channel();
+        (
+            ChannelTransport {
+                sender: s1,
+                receiver: r1,
+            },
+            ChannelTransport {
+                sender: s2,
+                receiver: r2,
+            },
+        )
+    }
+
+    impl BlockingTransport for ChannelTransport {
+        fn receive<V: for<'a> Deserialize<'a>>(
+            &mut self,
+            _label: &'static [u8],
+        ) -> Result<V, io::Error> {
+            let bytes = self
+                .receiver
+                .recv()
+                .map_err(|_| io::Error::from(io::ErrorKind::UnexpectedEof))?;
+            Ok(serde_json::from_slice(&bytes)?)
+        }
+
+        fn send<V: Serialize>(&mut self, _label: &'static [u8], value: V) -> Result<(), io::Error> {
+            self.sender
+                .send(serde_json::to_vec(&value)?)
+                .map_err(|_| io::Error::from(io::ErrorKind::BrokenPipe))
+        }
+    }
+
+    #[test]
+    fn blocking_protocols() {
+        let user = BlockingUser::new(User::new(UserSecretKey::random(&mut thread_rng())));
+        let org = BlockingOrg::new(Org::new(OrgSecretKey::random(&mut thread_rng())));
+        let org_key = org.org().public_key();
+
+        let (mut u_channel, mut o_channel) = pair();
+        let (n1, n2) = thread::scope(|s| {
+            let org_side = s.spawn(|| {
+                let nym = org.generate_nym(&mut o_channel)?;
+                org.authenticate_nym(&mut o_channel, nym)?;
+                org.issue_credential(&mut o_channel, nym, 0)?;
+                Ok::<_, crate::Error>(nym)
+            });
+            let nym = user.generate_nym(&mut u_channel).unwrap();
+            user.authenticate_nym(&mut u_channel, nym).unwrap();
+            user.issue_credential(&mut u_channel, nym, org_key, 0)
+                .unwrap();
+            (nym, org_side.join().unwrap().unwrap())
+        });
+        assert_eq!(n1, n2, "user and org should compute the same nym");
+    }
+}
@@ -0,0 +1,123 @@
+//! Compact binary encoding of protocol values
+
+use curve25519_dalek::{ristretto::CompressedRistretto, RistrettoPoint, Scalar};
+
+use crate::{
+    error::{Error, Result},
+    proof::dlog_eq::{OwnedPublics, Publics, Transcript},
+};
+
+/// Length of an encoded point
+pub(crate) const POINT_LENGTH: usize = 32;
+
+/// Length of an encoded scalar
+pub(crate) const SCALAR_LENGTH: usize = 32;
+
+/// Length of an encoded 64-bit integer
+pub(crate) const U64_LENGTH: usize = 8;
+
+/// Length of an encoded transcript
+pub(crate) const TRANSCRIPT_LENGTH: usize = 2 * POINT_LENGTH + 2 * SCALAR_LENGTH;
+
+/// Length of encoded public parameters
+pub(crate) const PUBLICS_LENGTH: usize = 4 * POINT_LENGTH;
+
+/// A writer of encoded values
+pub(crate) struct Writer<'a>(&'a mut [u8]);
+
+impl<'a> Writer<'a> {
+    pub(crate) fn new(buf: &'a mut [u8]) -> Self {
+        Self(buf)
+    }
+
+    fn bytes(&mut self, bytes: &[u8]) {
+        let (head, tail) = core::mem::take(&mut self.0).split_at_mut(bytes.len());
+        head.copy_from_slice(bytes);
+        self.0 = tail;
+    }
+
+    pub(crate) fn point(&mut self, p: &RistrettoPoint) {
+        self.bytes(p.compress().as_bytes());
+    }
+
+    pub(crate) fn scalar(&mut self, s: &Scalar) {
+        self.bytes(s.as_bytes());
+    }
+
+    pub(crate) fn u64(&mut self, n: u64) {
+        self.bytes(&n.to_be_bytes());
+    }
+
+    pub(crate) fn transcript(&mut self, t: &Transcript) {
+        self.point(&t.a);
+        self.point(&t.b);
+        self.scalar(&t.c);
+        self.scalar(&t.y);
+    }
+
+    pub(crate) fn publics(&mut self, publics: Publics) {
+        self.point(publics.g1);
+        self.point(publics.h1);
+        self.point(publics.g2);
+        self.point(publics.h2);
+    }
+}
+
+/// A reader of encoded values
+pub(crate) struct Reader<'a>(&'a [u8]);
+
+impl<'a> Reader<'a> {
+    pub(crate) fn new(buf: &'a [u8]) -> Self {
+        Self(buf)
+    }
+
+    fn bytes<const N: usize>(&mut self) -> Result<[u8; N]> {
+        if self.0.len() < N {
+            return Err(Error::InvalidEncoding);
+        }
+        let (head, tail) = self.0.split_at(N);
+        self.0 = tail;
+        Ok(head.try_into().unwrap())
+    }
+
+    pub(crate) fn point(&mut self) -> Result<RistrettoPoint> {
+        CompressedRistretto(self.bytes()?)
+            .decompress()
+            .ok_or(Error::InvalidEncoding)
+    }
+
+    pub(crate) fn scalar(&mut self) -> Result<Scalar> {
+        Option::from(Scalar::from_canonical_bytes(self.bytes()?)).ok_or(Error::InvalidEncoding)
+    }
+
+    pub(crate) fn u64(&mut self) -> Result<u64> {
+        Ok(u64::from_be_bytes(self.bytes()?))
+    }
+
+    pub(crate) fn transcript(&mut self) -> Result<Transcript> {
+        Ok(Transcript {
+            a: self.point()?,
+            b: self.point()?,
+            c: self.scalar()?,
+            y: self.scalar()?,
+        })
+    }
+
+    pub(crate) fn publics(&mut self) -> Result<OwnedPublics> {
+        Ok(OwnedPublics {
+            g1: self.point()?,
+            h1: self.point()?,
+            g2: self.point()?,
+            h2: self.point()?,
+        })
+    }
+
+    /// Checks that all the input was consumed
+    pub(crate) fn finish(self) -> Result {
+        if self.0.is_empty() {
+            Ok(())
+        } else {
+            Err(Error::InvalidEncoding)
+        }
+    }
+}
@@ -1,20 +1,185 @@
 //! Nym errors
 
+use core::fmt;
+
+#[cfg(feature = "std")]
 use futures::io;
 
+#[cfg(feature = "std")]
+use crate::transport::{codec::CodecError, LabelMismatch};
+
 /// An error from this crate
 #[derive(thiserror::Error, Debug)]
 pub enum Error {
     /// Verification of a proof failed
-    #[error("proof verification failed")]
-    BadProof,
+    #[error("proof verification failed ({stage})")]
+    BadProof {
+        /// The check that failed
+        stage: ProofStage,
+    },
     /// Verification of a signature failed
     #[error("signature verification failed")]
     BadSignature,
+    /// Two slices that should have the same length don't
+    #[error("length mismatch (expected {expected}, got {got})")]
+    LengthMismatch {
+        /// The length of the first slice
+        expected: usize,
+        /// The length of the second slice
+        got: usize,
+    },
+    /// Decoding of a value failed
+    #[error("invalid encoding")]
+    InvalidEncoding,
+    /// A public key is degenerate
+    #[error("invalid key")]
+    InvalidKey,
+    /// The credential is from a past epoch
+    #[error("credential expired")]
+    Expired,
+    /// The pseudonym was revoked
+    #[error("pseudonym revoked")]
+    Revoked,
+    /// The pseudonym is not in the set a membership proof was checked against
+    #[error("pseudonym not in set")]
+    NotMember,
+    /// The pseudonym was not generated with the user's key
+    #[error("unknown pseudonym")]
+    UnknownNym,
+    /// The peer speaks a different version of the protocol messages
+    #[error("protocol version mismatch (ours {ours}, theirs {theirs})")]
+    VersionMismatch {
+        /// Our version
+        ours: u32,
+        /// The peer's version
+        theirs: u32,
+    },
+    /// A protocol version this crate does not speak was asked for
+    #[error("unsupported protocol version {0}")]
+    UnsupportedVersion(u32),
+    /// A builder was given no source of randomness, and the operating system's is not available
+    #[error("no source of randomness")]
+    MissingRng,
+    /// The peer answered a nonce from an earlier session
+    #[error("stale nonce")]
+    StaleNonce,
+    /// A credential was issued, or is checked, under a different context
+    #[error("context mismatch")]
+    ContextMismatch,
+    /// A value to prove is outside of the range
+    #[error("value out of range")]
+    OutOfRange,
+    /// A credential has no attribute at an index
+    #[error("no attribute at index {0}")]
+    UnknownAttribute(usize),
+    /// A value was received with an unexpected label
+    #[cfg(feature = "std")]
+    #[error(transparent)]
+    LabelMismatch(LabelMismatch),
+    /// A value received from the peer could not be deserialized
+    #[cfg(feature = "std")]
+    #[error("serialization failed: {0}")]
+    Serialization(String),
+    /// A value could not be serialized to be sent to the peer
+    #[cfg(feature = "std")]
+    #[error("value cannot be serialized: {0}")]
+    Unserializable(String),
     /// A transport error occurred
+    #[cfg(feature = "std")]
     #[error(transparent)]
-    Transport(#[from] io::Error),
+    Transport(io::Error),
+}
+
+#[cfg(feature = "std")]
+impl From<io::Error> for Error {
+    fn from(e: io::Error) -> Self {
+        let inner = e.get_ref();
+        if let Some(mismatch) = inner.and_then(|e| e.downcast_ref::<LabelMismatch>()) {
+            return Error::LabelMismatch(mismatch.clone());
+        }
+        if let Some(CodecError(msg)) = inner.and_then(|e| e.downcast_ref::<CodecError>()) {
+            return match e.kind() {
+                io::ErrorKind::InvalidData => Error::Serialization(msg.clone()),
+                _ => Error::Unserializable(msg.clone()),
+            };
+        }
+        Error::Transport(e)
+    }
+}
+
+impl Error {
+    /// Checks whether this error comes from the peer disconnecting
+    pub fn is_disconnect(&self) -> bool {
+        match self {
+            #[cfg(feature = "std")]
+            Error::Transport(e) => matches!(
+                e.kind(),
+                io::ErrorKind::UnexpectedEof
+                    | io::ErrorKind::BrokenPipe
+                    | io::ErrorKind::ConnectionReset
+                    | io::ErrorKind::ConnectionAborted
+            ),
+            _ => false,
+        }
+    }
+
+    /// Checks whether this error comes from the peer sending malformed data
+    pub fn is_malformed(&self) -> bool {
+        match self {
+            #[cfg(feature = "std")]
+            Error::Transport(e) => e.kind() == io::ErrorKind::InvalidData,
+            #[cfg(feature = "std")]
+            Error::LabelMismatch(_) | Error::Serialization(_) => true,
+            Error::InvalidEncoding => true,
+            _ => false,
+        }
+    }
+}
+
+/// The check that failed when verifying a proof
+#[derive(PartialEq, Eq, Debug, Copy, Clone)]
+pub enum ProofStage {
+    /// The challenge does not match the commitments
+    ChallengeMismatch,
+    /// The first verification equation does not hold
+    FirstEquation,
+    /// The second verification equation does not hold
+    SecondEquation,
+    /// One of the verification equations of a proof over any number of points does not hold
+    Equation(usize),
+    /// The combined equation of a batch does not hold
+    Batch,
+    /// The number of commitments does not match the number of bases
+    CommitmentCount,
+    /// A point was the identity
+    IdentityPoint,
+    /// A base was not the expected one
+    UnexpectedBase,
+    /// A public key was not the expected one
+    UnexpectedKey,
+    /// The proof was not a valid encoding
+    Encoding,
+    /// The bits of a range proof do not add up to the committed value
+    Decomposition,
+}
+
+impl fmt::Display for ProofStage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::ChallengeMismatch => f.write_str("challenge mismatch"),
+            Self::FirstEquation => f.write_str("first equation"),
+            Self::SecondEquation => f.write_str("second equation"),
+            Self::Equation(i) => write!(f, "equation {i}"),
+            Self::Batch => f.write_str("batch equation"),
+            Self::CommitmentCount => f.write_str("commitment count"),
+            Self::IdentityPoint => f.write_str("identity point"),
+            Self::UnexpectedBase => f.write_str("unexpected base"),
+            Self::UnexpectedKey => f.write_str("unexpected key"),
+            Self::Encoding => f.write_str("encoding"),
+            Self::Decomposition => f.write_str("bit decomposition"),
+        }
+    }
 }
 
 /// This crate's Result type
-pub type Result<T = (), E = Error> = std::result::Result<T, E>;
+pub type Result<T = (), E = Error> = core::result::Result<T, E>;
@@ -0,0 +1,231 @@
+//! Prime-order groups the proofs work over
+
+use core::{
+    fmt,
+    ops::{Add, Mul, Sub},
+};
+
+use curve25519_dalek::{traits::IsIdentity as _, RistrettoPoint, Scalar};
+use rand::{CryptoRng, RngCore};
+use serde::{de::DeserializeOwned, Serialize};
+
+use crate::{
+    hash::{Challenge, Transcribe},
+    proof::mul,
+};
+
+/// A prime-order group, along with its scalar field
+///
+/// The proofs of equality of discrete logarithms in [`dlog_eq`](crate::proof::dlog_eq) and
+/// [`blind_dlog_eq`](crate::proof::blind_dlog_eq), and [`NymOf`](crate::NymOf), are generic over
+/// the group. Everything else, including keys and credentials, uses [`Ristretto`].
+pub trait Group: Copy + Eq + fmt::Debug + Send + Sync + 'static {
+    /// Scalars, modulo the order of the group
+    type Scalar: Copy
+        + Eq
+        + fmt::Debug
+        + Add<Output = Self::Scalar>
+        + Sub<Output = Self::Scalar>
+        + Mul<Output = Self::Scalar>
+        + Transcribe
+        + Challenge
+        + Serialize
+        + DeserializeOwned
+        + Send
+        + Sync;
+
+    /// Elements of the group
+    type Point: Copy
+        + Eq
+        + fmt::Debug
+        + Add<Output = Self::Point>
+        + Sub<Output = Self::Point>
+        + Mul<Self::Scalar, Output = Self::Point>
+        + Transcribe
+        + Serialize
+        + DeserializeOwned
+        + Send
+        + Sync;
+
+    /// Draws a uniformly random scalar
+    fn random_scalar<R: CryptoRng + RngCore>(rng: &mut R) -> Self::Scalar;
+
+    /// Gets the generator of the group
+    fn generator() -> Self::Point;
+
+    /// Checks whether a point is the identity
+    fn is_identity(point: &Self::Point) -> bool;
+
+    /// Multiplies a point by a scalar
+    ///
+    /// Implementations can override this to speed up multiples of the generator.
+    fn mul(scalar: &Self::Scalar, point: &Self::Point) -> Self::Point {
+        *point * *scalar
+    }
+
+    /// Checks whether two points are equal
+    ///
+    /// This is how the proofs check their verification equations, and it should run in constant
+    /// time. Implementations can override it if the points' own equality does not.
+    fn points_eq(p: &Self::Point, q: &Self::Point) -> bool {
+        p == q
+    }
+}
+
+/// The Ristretto group over Curve25519, from `curve25519_dalek`
+///
+/// Its points always compare in constant time, as `curve25519_dalek` implements their equality
+/// with `subtle`.
+#[derive(PartialEq, Eq, Debug, Copy, Clone, Default)]
+pub struct Ristretto;
+
+impl Group for Ristretto {
+    type Scalar = Scalar;
+    type Point = RistrettoPoint;
+
+    fn random_scalar<R: CryptoRng + RngCore>(rng: &mut R) -> Scalar {
+        Scalar::random(rng)
+    }
+
+    fn generator() -> RistrettoPoint {
+        curve25519_dalek::constants::RISTRETTO_BASEPOINT_POINT
+    }
+
+    fn is_identity(point: &RistrettoPoint) -> bool {
+        point.is_identity()
+    }
+
+    fn mul(scalar: &Scalar, point: &RistrettoPoint) -> RistrettoPoint {
+        mul(scalar, point)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::assert_matches::assert_matches;
+    use std::ops::{Add, Mul, Sub};
+
+    use futures::{executor::block_on, future::try_join};
+    use merlin::Transcript;
+    use rand::{thread_rng, CryptoRng, RngCore};
+    use serde::{Deserialize, Serialize};
+
+    use crate::{
+        hash::{AppendMessage, Challenge, Transcribe},
+        proof::{
+            blind_dlog_eq::{self, VerifierSecretsOf},
+            dlog_eq::{self, PublicsOf, SecretsOf},
+        },
+        transport::{codec::Json, memory::DuplexTransport},
+        Error,
+    };
+
+    use super::Group;
+
+    /// The integers modulo a prime under addition, where discrete logarithms are easy
+    #[derive(PartialEq, Eq, Debug, Copy, Clone)]
+    struct Toy;
+
+    const P: u128 = (1 << 61) - 1;
+
+    #[derive(PartialEq, Eq, Debug, Copy, Clone, Serialize, Deserialize)]
+    struct Zp(u64);
+
+    impl Zp {
+        fn new(n: u128) -> Self {
+            Self((n % P) as u64)
+        }
+    }
+
+    impl Add for Zp {
+        type Output = Self;
+        fn add(self, other: Self) -> Self {
+            Self::new(self.0 as u128 + other.0 as u128)
+        }
+    }
+
+    impl Sub for Zp {
+        type Output = Self;
+        fn sub(self, other: Self) -> Self {
+            Self::new(self.0 as u128 + P - other.0 as u128)
+        }
+    }
+
+    impl Mul for Zp {
+        type Output = Self;
+        fn mul(self, other: Self) -> Self {
+            Self::new(self.0 as u128 * other.0 as u128)
+        }
+    }
+
+    impl Transcribe for Zp {
+        fn append_to<S: AppendMessage + ?Sized>(&self, t: &mut S, label: &'static [u8]) {
+            t.append_message(label, &self.0.to_le_bytes());
+        }
+    }
+
+    impl Challenge for Zp {
+        fn challenge_from(t: &mut Transcript, label: &'static [u8]) -> Self {
+            let mut bytes = [0; 16];
+            t.challenge_bytes(label, &mut bytes);
+            Self::new(u128::from_le_bytes(bytes))
+        }
+    }
+
+    impl Group for Toy {
+        type Scalar = Zp;
+        type Point = Zp;
+
+        fn random_scalar<R: CryptoRng + RngCore>(rng: &mut R) -> Zp {
+            Zp::new(rng.next_u64() as u128)
+        }
+
+        fn generator() -> Zp {
+            Zp(1)
+        }
+
+        fn is_identity(point: &Zp) -> bool {
+            point.0 == 0
+        }
+    }
+
+    #[test]
+    fn other_group() {
+        let x = Toy::random_scalar(&mut thread_rng());
+        let g2 = Toy::random_scalar(&mut thread_rng());
+        let (h1, h2) = (Toy::generator() * x, g2 * x);
+        let publics = PublicsOf::<Toy> {
+            g1: &Toy::generator(),
+            h1: &h1,
+            g2: &g2,
+            h2: &h2,
+        };
+
+        let (mut p_channel, mut v_channel) = DuplexTransport::<Json>::pair();
+        let res = block_on(try_join(
+            dlog_eq::prove(&mut p_channel, publics, SecretsOf { x: &x }),
+            dlog_eq::verify(&mut v_channel, publics),
+        ));
+        assert_matches!(res, Ok(_));
+
+        let t = dlog_eq::prove_noninteractive(publics, SecretsOf { x: &x });
+        assert_matches!(t.verify(publics), Ok(_));
+        let wrong = x + Zp(1);
+        let t = dlog_eq::prove_noninteractive(publics, SecretsOf { x: &wrong });
+        assert_matches!(t.verify(publics), Err(Error::BadProof { .. }));
+
+        let γ = Toy::random_scalar(&mut thread_rng());
+        let (mut p_channel, mut v_channel) = DuplexTransport::<Json>::pair();
+        let (_, t) = block_on(try_join(
+            blind_dlog_eq::prove(&mut p_channel, publics, SecretsOf { x: &x }),
+            blind_dlog_eq::verify(&mut v_channel, publics, VerifierSecretsOf { γ: &γ }),
+        ))
+        .unwrap();
+        let blinded = PublicsOf {
+            g2: &(g2 * γ),
+            h2: &(h2 * γ),
+            ..publics
+        };
+        assert_matches!(t.verify(blinded), Ok(_));
+    }
+}
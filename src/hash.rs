@@ -1,6 +1,7 @@
 //! Hash functions for nyms
 
-use std::marker::PhantomData;
+use alloc::{string::String, vec::Vec};
+use core::marker::PhantomData;
 
 use curve25519_dalek::{RistrettoPoint, Scalar};
 use digest::{
@@ -8,6 +9,9 @@ use digest::{
 };
 use merlin::Transcript;
 
+#[cfg(feature = "derive")]
+pub use nym_derive::Transcribe;
+
 /// A transcript-based protocol interface
 pub trait TranscriptProtocol {
     /// Commits a value, with a given label for framing
@@ -27,6 +31,27 @@ impl TranscriptProtocol for Transcript {
     }
 }
 
+/// Hashes some input into a uniformly distributed scalar
+///
+/// The hash is domain-separated by `domain`, and the scalar is derived the same way as the
+/// challenges in this crate's proofs.
+pub fn hash_to_scalar(domain: &'static [u8], input: &[u8]) -> Scalar {
+    let mut t = Transcript::new(domain);
+    t.commit(b"input", input);
+    t.challenge(b"scalar")
+}
+
+/// Evaluates a pseudorandom function keyed by a secret scalar
+///
+/// This derives scalars deterministically from a secret, e.g. per-session blinding factors. The
+/// output is domain-separated by `domain`, and unpredictable without the key.
+pub fn prf(key: &Scalar, domain: &'static [u8], input: &[u8]) -> Scalar {
+    let mut t = Transcript::new(domain);
+    t.commit(b"key", key);
+    t.commit(b"input", input);
+    t.challenge(b"scalar")
+}
+
 /// A transcript-based hash
 pub trait TranscriptDigest {
     /// Produces a Digest from all the data that was appended
@@ -45,6 +70,80 @@ impl TranscriptDigest for Transcript {
     }
 }
 
+/// A transcript that keeps a copy of the messages appended to it
+///
+/// Merlin transcripts only keep the state of their STROBE sponge, so the data committed to them
+/// can't be hashed in any other way. This buffers every message, framed as the little-endian `u32`
+/// length of its label, the label, the little-endian `u32` length of the message, and the message,
+/// so that it can be replayed into any [`Digest`] with
+/// [`into_digest_with`](BufferedTranscript::into_digest_with). The domain separator passed to
+/// [`new`](BufferedTranscript::new) is framed as a message labelled `dom-sep`, as in merlin.
+/// Values committed with [`TranscriptProtocol::commit`] are buffered as the messages they
+/// transcribe to.
+#[derive(Clone)]
+pub struct BufferedTranscript {
+    transcript: Transcript,
+    buffer: Vec<u8>,
+}
+
+impl BufferedTranscript {
+    /// Creates a new transcript with a given domain separator
+    pub fn new(label: &'static [u8]) -> Self {
+        let mut buffer = Vec::new();
+        frame(&mut buffer, b"dom-sep", label);
+        Self {
+            transcript: Transcript::new(label),
+            buffer,
+        }
+    }
+
+    /// Appends a message to the transcript, with a given label for framing
+    pub fn append_message(&mut self, label: &'static [u8], message: &[u8]) {
+        self.transcript.append_message(label, message);
+        frame(&mut self.buffer, label, message);
+    }
+
+    /// Produces a Digest of a given type from all the data that was appended
+    ///
+    /// The framed messages are fed to a fresh `D`, which can be updated further before
+    /// finalizing.
+    pub fn into_digest_with<D: Digest>(self) -> D {
+        D::new_with_prefix(&self.buffer)
+    }
+
+    /// Gets the underlying merlin transcript
+    pub fn into_inner(self) -> Transcript {
+        self.transcript
+    }
+}
+
+impl TranscriptProtocol for BufferedTranscript {
+    fn commit<M: Transcribe + ?Sized>(&mut self, label: &'static [u8], m: &M) {
+        m.append_to(self, label)
+    }
+
+    /// Challenges are drawn from the merlin transcript, and are not buffered
+    fn challenge<C: Challenge>(&mut self, label: &'static [u8]) -> C {
+        C::challenge_from(&mut self.transcript, label)
+    }
+}
+
+impl TranscriptDigest for BufferedTranscript {
+    fn into_digest<N: ArrayLength<u8> + 'static>(self) -> impl Digest<OutputSize = N> {
+        self.transcript.into_digest()
+    }
+    fn into_xof(self) -> impl XofReader {
+        self.transcript.into_xof()
+    }
+}
+
+fn frame(buffer: &mut Vec<u8>, label: &[u8], message: &[u8]) {
+    buffer.extend_from_slice(&(label.len() as u32).to_le_bytes());
+    buffer.extend_from_slice(label);
+    buffer.extend_from_slice(&(message.len() as u32).to_le_bytes());
+    buffer.extend_from_slice(message);
+}
+
 struct TranscriptDigestImpl<N: ArrayLength<u8> + 'static>(Transcript, PhantomData<N>);
 
 impl<N: ArrayLength<u8> + 'static> HashMarker for TranscriptDigestImpl<N> {}
@@ -79,10 +178,33 @@ impl XofReader for TranscriptXofReaderImpl {
     }
 }
 
+/// A transcript that framed messages can be appended to
+///
+/// This is what [`Transcribe`] implementations write to: a merlin [`Transcript`], or a
+/// [`BufferedTranscript`] that also keeps a copy of the messages.
+pub trait AppendMessage {
+    /// Appends a message, with a given label for framing
+    fn append_message(&mut self, label: &'static [u8], message: &[u8]);
+}
+
+impl AppendMessage for Transcript {
+    fn append_message(&mut self, label: &'static [u8], message: &[u8]) {
+        Transcript::append_message(self, label, message)
+    }
+}
+
+impl AppendMessage for BufferedTranscript {
+    fn append_message(&mut self, label: &'static [u8], message: &[u8]) {
+        BufferedTranscript::append_message(self, label, message)
+    }
+}
+
 /// A type that can be appended to a transcript
+///
+/// With the `derive` feature, this can be derived for structs with `#[derive(Transcribe)]`.
 pub trait Transcribe {
     /// Appends this object to a transcript, with a given label for framing
-    fn append_to(&self, t: &mut Transcript, label: &'static [u8]);
+    fn append_to<S: AppendMessage + ?Sized>(&self, t: &mut S, label: &'static [u8]);
 }
 
 /// A type that can be read from a transcript
@@ -91,9 +213,18 @@ pub trait Challenge {
     fn challenge_from(t: &mut Transcript, label: &'static [u8]) -> Self;
 }
 
+/// Appends a length to a transcript
+///
+/// Lengths are appended as `u64`s, so that transcripts are the same on 32-bit and 64-bit
+/// platforms. This is how slices, tuples and derived impls frame their contents, and what
+/// implementations of [`Transcribe`] should use rather than transcribing a `usize`.
+pub fn append_length<S: AppendMessage + ?Sized>(t: &mut S, label: &'static [u8], len: usize) {
+    (len as u64).append_to(t, label);
+}
+
 impl<T: Transcribe> Transcribe for [T] {
-    fn append_to(&self, t: &mut Transcript, label: &'static [u8]) {
-        self.len().append_to(t, label);
+    fn append_to<S: AppendMessage + ?Sized>(&self, t: &mut S, label: &'static [u8]) {
+        append_length(t, label, self.len());
         for e in self.iter() {
             e.append_to(t, b"$");
         }
@@ -101,48 +232,102 @@ impl<T: Transcribe> Transcribe for [T] {
 }
 
 impl<T: Transcribe> Transcribe for Vec<T> {
-    fn append_to(&self, t: &mut Transcript, label: &'static [u8]) {
+    fn append_to<S: AppendMessage + ?Sized>(&self, t: &mut S, label: &'static [u8]) {
+        self.as_slice().append_to(t, label);
+    }
+}
+
+impl<T: Transcribe, const N: usize> Transcribe for [T; N] {
+    fn append_to<S: AppendMessage + ?Sized>(&self, t: &mut S, label: &'static [u8]) {
         self.as_slice().append_to(t, label);
     }
 }
 
+impl<T: Transcribe> Transcribe for Option<T> {
+    fn append_to<S: AppendMessage + ?Sized>(&self, t: &mut S, label: &'static [u8]) {
+        self.is_some().append_to(t, label);
+        if let Some(value) = self {
+            value.append_to(t, b"$");
+        }
+    }
+}
+
 impl<'a, T: Transcribe> Transcribe for &'a T {
-    fn append_to(&self, t: &mut Transcript, label: &'static [u8]) {
+    fn append_to<S: AppendMessage + ?Sized>(&self, t: &mut S, label: &'static [u8]) {
         (*self).append_to(t, label);
     }
 }
 
+macro_rules! impl_transcribe_for_tuple {
+    ($len:literal; $($t:ident $i:tt $l:literal),+) => {
+        impl<$($t: Transcribe),+> Transcribe for ($($t,)+) {
+            fn append_to<S: AppendMessage + ?Sized>(&self, t: &mut S, label: &'static [u8]) {
+                append_length(t, label, $len);
+                $(self.$i.append_to(t, $l);)+
+            }
+        }
+    };
+}
+
+impl_transcribe_for_tuple!(1; A 0 b"$0");
+impl_transcribe_for_tuple!(2; A 0 b"$0", B 1 b"$1");
+impl_transcribe_for_tuple!(3; A 0 b"$0", B 1 b"$1", C 2 b"$2");
+impl_transcribe_for_tuple!(4; A 0 b"$0", B 1 b"$1", C 2 b"$2", D 3 b"$3");
+impl_transcribe_for_tuple!(5; A 0 b"$0", B 1 b"$1", C 2 b"$2", D 3 b"$3", E 4 b"$4");
+impl_transcribe_for_tuple!(6; A 0 b"$0", B 1 b"$1", C 2 b"$2", D 3 b"$3", E 4 b"$4", F 5 b"$5");
+impl_transcribe_for_tuple!(7; A 0 b"$0", B 1 b"$1", C 2 b"$2", D 3 b"$3", E 4 b"$4", F 5 b"$5", G 6 b"$6");
+impl_transcribe_for_tuple!(8; A 0 b"$0", B 1 b"$1", C 2 b"$2", D 3 b"$3", E 4 b"$4", F 5 b"$5", G 6 b"$6", H 7 b"$7");
+
 impl Transcribe for bool {
-    fn append_to(&self, t: &mut Transcript, label: &'static [u8]) {
+    fn append_to<S: AppendMessage + ?Sized>(&self, t: &mut S, label: &'static [u8]) {
         t.append_message(label, &[*self as u8]);
     }
 }
 
+/// Deprecated: commit a fixed-width integer instead, or use [`append_length`] for lengths
+///
+/// This is kept for compatibility, and is the same as transcribing the value as a `u64`.
 impl Transcribe for usize {
-    fn append_to(&self, t: &mut Transcript, label: &'static [u8]) {
-        t.append_message(label, &(*self as u64).to_be_bytes());
+    fn append_to<S: AppendMessage + ?Sized>(&self, t: &mut S, label: &'static [u8]) {
+        append_length(t, label, *self);
     }
 }
 
+macro_rules! impl_transcribe_for_int {
+    ($($t:ty),+) => {
+        $(
+            impl Transcribe for $t {
+                fn append_to<S: AppendMessage + ?Sized>(&self, t: &mut S, label: &'static [u8]) {
+                    t.append_message(label, &self.to_be_bytes());
+                }
+            }
+        )+
+    };
+}
+
+// Fixed-width and big-endian, so that transcripts are the same on every platform. There is no
+// impl for `u8`, as `[u8]` is transcribed as a byte string rather than element by element.
+impl_transcribe_for_int!(u16, u32, u64, i32, i64);
+
 impl Transcribe for [u8] {
-    fn append_to(&self, t: &mut Transcript, label: &'static [u8]) {
+    fn append_to<S: AppendMessage + ?Sized>(&self, t: &mut S, label: &'static [u8]) {
         t.append_message(label, self);
     }
 }
 
 impl Transcribe for str {
-    fn append_to(&self, t: &mut Transcript, label: &'static [u8]) {
+    fn append_to<S: AppendMessage + ?Sized>(&self, t: &mut S, label: &'static [u8]) {
         t.append_message(label, self.as_bytes());
     }
 }
 impl Transcribe for String {
-    fn append_to(&self, t: &mut Transcript, label: &'static [u8]) {
+    fn append_to<S: AppendMessage + ?Sized>(&self, t: &mut S, label: &'static [u8]) {
         self.as_str().append_to(t, label);
     }
 }
 
 impl Transcribe for Scalar {
-    fn append_to(&self, t: &mut Transcript, label: &'static [u8]) {
+    fn append_to<S: AppendMessage + ?Sized>(&self, t: &mut S, label: &'static [u8]) {
         self.as_bytes().append_to(t, label);
     }
 }
@@ -156,7 +341,232 @@ impl Challenge for Scalar {
 }
 
 impl Transcribe for RistrettoPoint {
-    fn append_to(&self, t: &mut Transcript, label: &'static [u8]) {
+    fn append_to<S: AppendMessage + ?Sized>(&self, t: &mut S, label: &'static [u8]) {
         self.compress().as_bytes().append_to(t, label);
     }
 }
+
+impl Challenge for RistrettoPoint {
+    fn challenge_from(t: &mut Transcript, label: &'static [u8]) -> Self {
+        let mut buf = [0; 64];
+        t.challenge_bytes(label, &mut buf);
+        Self::from_uniform_bytes(&buf)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use curve25519_dalek::{traits::IsIdentity as _, RistrettoPoint, Scalar};
+    use merlin::Transcript;
+    use rand::thread_rng;
+
+    use digest::generic_array::typenum::U32;
+    use sha2::{Digest as _, Sha512};
+
+    use super::{
+        hash_to_scalar, prf, BufferedTranscript, TranscriptDigest as _, TranscriptProtocol as _,
+    };
+
+    #[test]
+    fn hash_to_scalar_is_deterministic() {
+        assert_eq!(
+            hash_to_scalar(b"test-domain", b"input"),
+            hash_to_scalar(b"test-domain", b"input"),
+        );
+        assert_ne!(
+            hash_to_scalar(b"test-domain", b"input"),
+            hash_to_scalar(b"test-domain", b"other input"),
+        );
+    }
+
+    #[test]
+    fn hash_to_scalar_is_domain_separated() {
+        assert_ne!(
+            hash_to_scalar(b"test-domain", b"input"),
+            hash_to_scalar(b"other-domain", b"input"),
+        );
+    }
+
+    #[test]
+    fn prf_is_keyed() {
+        let key = Scalar::random(&mut thread_rng());
+        let other = Scalar::random(&mut thread_rng());
+        assert_eq!(
+            prf(&key, b"test-domain", b"input"),
+            prf(&key, b"test-domain", b"input"),
+        );
+        assert_ne!(
+            prf(&key, b"test-domain", b"input"),
+            prf(&other, b"test-domain", b"input"),
+        );
+        assert_ne!(
+            prf(&key, b"test-domain", b"input"),
+            prf(&key, b"test-domain", b"other input"),
+        );
+        assert_ne!(
+            prf(&key, b"test-domain", b"input"),
+            prf(&key, b"other-domain", b"input"),
+        );
+    }
+
+    #[test]
+    fn array_transcribes_like_slice() {
+        let points: [RistrettoPoint; 4] =
+            std::array::from_fn(|_| RistrettoPoint::random(&mut thread_rng()));
+
+        let mut t1 = Transcript::new(b"test-transcript");
+        t1.commit(b"points", &points);
+        let mut t2 = Transcript::new(b"test-transcript");
+        t2.commit(b"points", &points[..]);
+        assert_eq!(t1.challenge::<Scalar>(b"c"), t2.challenge::<Scalar>(b"c"));
+    }
+
+    #[test]
+    fn tuple_transcription() {
+        let a = RistrettoPoint::random(&mut thread_rng());
+        let b = RistrettoPoint::random(&mut thread_rng());
+        let challenge_for = |m: &(RistrettoPoint, RistrettoPoint)| {
+            let mut t = Transcript::new(b"test-transcript");
+            t.commit(b"pair", m);
+            t.challenge::<Scalar>(b"c")
+        };
+        assert_eq!(challenge_for(&(a, b)), challenge_for(&(a, b)));
+        assert_ne!(challenge_for(&(a, b)), challenge_for(&(b, a)));
+    }
+
+    #[test]
+    fn option_transcription() {
+        let challenge_for = |m: Option<Scalar>| {
+            let mut t = Transcript::new(b"test-transcript");
+            t.commit(b"option", &m);
+            t.challenge::<Scalar>(b"c")
+        };
+        assert_eq!(challenge_for(None), challenge_for(None));
+        assert_ne!(challenge_for(None), challenge_for(Some(Scalar::ZERO)));
+        assert_ne!(
+            challenge_for(Some(Scalar::ZERO)),
+            challenge_for(Some(Scalar::ONE))
+        );
+    }
+
+    #[test]
+    fn point_challenge() {
+        let challenge_for = |label: &'static [u8]| {
+            let mut t = Transcript::new(b"test-transcript");
+            t.commit(b"input", b"hello".as_slice());
+            t.challenge::<RistrettoPoint>(label)
+        };
+        assert_eq!(challenge_for(b"p"), challenge_for(b"p"));
+
+        let labels: [&'static [u8]; 8] = [b"p0", b"p1", b"p2", b"p3", b"p4", b"p5", b"p6", b"p7"];
+        let points = labels.map(challenge_for);
+        for (i, p) in points.iter().enumerate() {
+            assert!(!p.is_identity());
+            for q in &points[i + 1..] {
+                assert_ne!(p, q, "different labels should give different points");
+            }
+        }
+    }
+
+    #[test]
+    fn integer_transcription() {
+        fn challenge(f: impl FnOnce(&mut Transcript)) -> Scalar {
+            let mut t = Transcript::new(b"test-transcript");
+            f(&mut t);
+            t.challenge(b"c")
+        }
+
+        let bytes = |b: &'static [u8]| challenge(|t| t.commit(b"n", b));
+        assert_eq!(
+            challenge(|t| t.commit(b"n", &0x1234u16)),
+            bytes(&[0x12, 0x34])
+        );
+        assert_eq!(
+            challenge(|t| t.commit(b"n", &0x1234_5678u32)),
+            bytes(&[0x12, 0x34, 0x56, 0x78])
+        );
+        assert_eq!(
+            challenge(|t| t.commit(b"n", &-2i32)),
+            bytes(&[0xff, 0xff, 0xff, 0xfe])
+        );
+        assert_eq!(
+            challenge(|t| t.commit(b"n", &-2i64)),
+            bytes(&[0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xfe])
+        );
+        assert_eq!(
+            challenge(|t| t.commit(b"n", &0x1234_5678u64)),
+            bytes(&[0, 0, 0, 0, 0x12, 0x34, 0x56, 0x78])
+        );
+        assert_eq!(
+            challenge(|t| t.commit(b"n", &0x1234_5678usize)),
+            challenge(|t| t.commit(b"n", &0x1234_5678u64)),
+            "usize should transcribe like u64 on every platform"
+        );
+    }
+
+    /// This vector was generated on x86_64, and should come out the same on 32-bit platforms
+    #[test]
+    fn length_transcription() {
+        let mut t = Transcript::new(b"test-transcript");
+        t.commit(b"values", &[1u32, 2, 3][..]);
+        t.commit(b"tuple", &(7u64, true));
+        let mut buf = [0; 16];
+        t.challenge_bytes(b"c", &mut buf);
+        assert_eq!(
+            buf,
+            [
+                0xb0, 0xb6, 0x98, 0xed, 0xc9, 0x60, 0xd1, 0x06, 0x2f, 0xac, 0x04, 0x3c, 0xde, 0x6e,
+                0xbd, 0x22
+            ]
+        );
+    }
+
+    #[test]
+    fn buffered_digest() {
+        let mut t = BufferedTranscript::new(b"test-domain");
+        t.append_message(b"a", b"hello");
+        t.append_message(b"bc", b"");
+
+        let mut framed = Vec::new();
+        framed.extend_from_slice(b"\x07\0\0\0dom-sep\x0b\0\0\0test-domain");
+        framed.extend_from_slice(b"\x01\0\0\0a\x05\0\0\0hello");
+        framed.extend_from_slice(b"\x02\0\0\0bc\0\0\0\0");
+        assert_eq!(
+            t.clone().into_digest_with::<Sha512>().finalize(),
+            Sha512::digest(&framed)
+        );
+
+        let mut plain = Transcript::new(b"test-domain");
+        plain.append_message(b"a", b"hello");
+        plain.append_message(b"bc", b"");
+        assert_eq!(
+            t.into_digest::<U32>().finalize(),
+            plain.into_digest::<U32>().finalize()
+        );
+    }
+
+    #[test]
+    fn buffered_commitments() {
+        let p = RistrettoPoint::random(&mut thread_rng());
+        let s = Scalar::random(&mut thread_rng());
+
+        let mut t = BufferedTranscript::new(b"test-domain");
+        t.commit(b"point", &p);
+        t.commit(b"scalar", &s);
+        let mut plain = Transcript::new(b"test-domain");
+        plain.commit(b"point", &p);
+        plain.commit(b"scalar", &s);
+        assert_eq!(t.challenge::<Scalar>(b"c"), plain.challenge::<Scalar>(b"c"));
+
+        let mut framed = Vec::new();
+        framed.extend_from_slice(b"\x07\0\0\0dom-sep\x0b\0\0\0test-domain");
+        framed.extend_from_slice(b"\x05\0\0\0point\x20\0\0\0");
+        framed.extend_from_slice(p.compress().as_bytes());
+        framed.extend_from_slice(b"\x06\0\0\0scalar\x20\0\0\0");
+        framed.extend_from_slice(s.as_bytes());
+        assert_eq!(
+            t.into_digest_with::<Sha512>().finalize(),
+            Sha512::digest(&framed)
+        );
+    }
+}
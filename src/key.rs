@@ -1,37 +1,136 @@
 //! Secret and public keys
 
-use curve25519_dalek::{constants::RISTRETTO_BASEPOINT_POINT, RistrettoPoint, Scalar};
+use core::fmt;
+#[cfg(feature = "std")]
+use std::{collections::HashMap, time::Duration};
+
+use curve25519_dalek::{
+    constants::{RISTRETTO_BASEPOINT_POINT, RISTRETTO_BASEPOINT_TABLE},
+    traits::IsIdentity as _,
+    RistrettoPoint, Scalar,
+};
 use rand::{CryptoRng, RngCore};
-use schnorrkel::{ExpansionMode, MiniSecretKey, SecretKey, PublicKey};
+use rand_core::CryptoRngCore;
+use schnorrkel::{ExpansionMode, MiniSecretKey, PublicKey, SecretKey};
+use serde::{Deserialize, Serialize};
+use subtle::{Choice, ConstantTimeEq};
 
+#[cfg(feature = "std")]
+use crate::transport::LocalTransport;
 use crate::{
-    error::Result,
-    proof::dlog_eq::{self, Publics, Secrets},
-    transport::LocalTransport,
+    error::{Error, Result},
+    hash::TranscriptProtocol as _,
+    proof::dlog::{self, Publics, Secrets},
 };
 
 /// The secret part of a user's master key
+#[derive(Serialize, Deserialize)]
 pub struct UserSecretKey {
     pub(crate) key: SecretKey,
 }
 
 /// The public part of a user's master key
-#[derive(PartialEq, Eq, Copy, Clone)]
+#[derive(Eq, Copy, Clone, Serialize, Deserialize)]
 pub struct UserPublicKey {
     key: PublicKey,
 }
 
-/// The secret part of an organization's credential key
-pub struct OrgSecretKey {
-    pub(crate) key1: SecretKey,
-    pub(crate) key2: SecretKey,
+/// The secret part of an organization's credential key, made of `N` sub-keys
+#[derive(Serialize, Deserialize)]
+pub struct OrgSecretKey<const N: usize = 2> {
+    #[serde(with = "serde_array")]
+    pub(crate) keys: [SecretKey; N],
+}
+
+/// The public part of an organization's credential key, made of `N` sub-keys
+#[derive(Eq, Copy, Clone, Serialize, Deserialize)]
+pub struct OrgPublicKey<const N: usize = 2> {
+    #[serde(with = "serde_array")]
+    keys: [PublicKey; N],
+}
+
+/// A non-interactive proof that an organization owns each of its `N` sub-keys
+#[derive(PartialEq, Eq, Debug, Copy, Clone, Serialize, Deserialize)]
+pub struct OwnershipProof<const N: usize = 2> {
+    #[serde(with = "serde_array")]
+    transcripts: [dlog::Transcript; N],
+}
+
+/// A record of organization keys whose ownership was verified recently
+///
+/// Verifications are remembered for a given time to live, after which the key has to be verified
+/// again. See [`OrgPublicKey::verify_ownership_cached`]. The cache reads the time from a clock,
+/// which is the system's by default, or any other given to [`OwnershipCache::with_clock`].
+#[cfg(feature = "std")]
+#[derive(Debug, Clone)]
+pub struct OwnershipCache {
+    ttl: Duration,
+    clock: fn() -> Duration,
+    verified: HashMap<Vec<u8>, Duration>,
+}
+
+impl ConstantTimeEq for UserPublicKey {
+    fn ct_eq(&self, other: &Self) -> Choice {
+        self.key.ct_eq(&other.key)
+    }
+}
+
+impl PartialEq for UserPublicKey {
+    fn eq(&self, other: &Self) -> bool {
+        self.ct_eq(other).into()
+    }
+}
+
+impl<const N: usize> ConstantTimeEq for OrgPublicKey<N> {
+    fn ct_eq(&self, other: &Self) -> Choice {
+        self.keys
+            .iter()
+            .zip(&other.keys)
+            .fold(Choice::from(1), |acc, (a, b)| acc & a.ct_eq(b))
+    }
+}
+
+impl<const N: usize> PartialEq for OrgPublicKey<N> {
+    fn eq(&self, other: &Self) -> bool {
+        self.ct_eq(other).into()
+    }
+}
+
+impl fmt::Debug for UserSecretKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("UserSecretKey(<redacted>)")
+    }
 }
 
-/// The public part of an organization's credential key
-#[derive(PartialEq, Eq, Copy, Clone)]
-pub struct OrgPublicKey {
-    key1: PublicKey,
-    key2: PublicKey,
+impl<const N: usize> fmt::Debug for OrgSecretKey<N> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("OrgSecretKey(<redacted>)")
+    }
+}
+
+impl fmt::Debug for UserPublicKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("UserPublicKey")
+            .field(&Hex(self.key.to_bytes()))
+            .finish()
+    }
+}
+
+impl<const N: usize> fmt::Debug for OrgPublicKey<N> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("OrgPublicKey")
+            .field(&self.keys.each_ref().map(|key| Hex(key.to_bytes())))
+            .finish()
+    }
+}
+
+/// Formats a compressed point as hex
+struct Hex([u8; 32]);
+
+impl fmt::Debug for Hex {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.iter().try_for_each(|b| write!(f, "{b:02x}"))
+    }
 }
 
 impl UserSecretKey {
@@ -43,12 +142,43 @@ impl UserSecretKey {
         }
     }
 
+    /// Generates a new random user secret key, from a source of randomness chosen at runtime.
+    ///
+    /// This gives the same key as [`UserSecretKey::random`] for the same random bytes.
+    pub fn random_dyn(mut rng: &mut dyn CryptoRngCore) -> Self {
+        Self::random(&mut rng)
+    }
+
+    /// Deterministically derives a user secret key from a seed.
+    pub fn from_seed(seed: &[u8; 32]) -> Self {
+        Self {
+            key: expand_seed(seed),
+        }
+    }
+
+    /// Gets the schnorrkel keypair underlying this key.
+    ///
+    /// This allows using the key with other schnorrkel protocols, such as VRFs. Be careful about
+    /// reusing it: the public key links whatever those protocols produce to this user, something
+    /// nyms are meant to avoid, and a protocol that reveals or misuses the secret scalar breaks
+    /// every nym and credential held with it. Only sign with domain-separated transcripts that
+    /// cannot be confused with this crate's own.
+    pub fn keypair(&self) -> schnorrkel::Keypair {
+        self.key.clone().to_keypair()
+    }
+
     /// Gets the public part of this key.
     pub fn to_public(&self) -> UserPublicKey {
         UserPublicKey {
-            key: self.key.to_public()
+            key: self.key.to_public(),
         }
     }
+
+    /// Proves ownership of this key to an organization
+    #[cfg(feature = "std")]
+    pub async fn prove_ownership<T: LocalTransport>(&self, org: &mut T) -> Result {
+        prove_ownership(org, self.key.to_public().as_point(), self.key.exponent()).await
+    }
 }
 
 impl UserPublicKey {
@@ -56,60 +186,398 @@ impl UserPublicKey {
     pub fn point(&self) -> RistrettoPoint {
         self.key.into_point()
     }
+
+    /// Decodes a public key from its compressed point
+    ///
+    /// This fails with [`Error::InvalidEncoding`] if the point does not decompress.
+    pub fn from_bytes(bytes: &[u8; 32]) -> Result<UserPublicKey> {
+        PublicKey::from_bytes(bytes)
+            .map(|key| UserPublicKey { key })
+            .map_err(|_| Error::InvalidEncoding)
+    }
+
+    /// Encodes this public key as a compressed point
+    pub fn to_bytes(&self) -> [u8; 32] {
+        self.key.to_bytes()
+    }
+
+    /// Verifies a user's ownership of this key
+    #[cfg(feature = "std")]
+    pub async fn verify_ownership<T: LocalTransport>(&self, user: &mut T) -> Result {
+        verify_ownership(user, self.key.as_point()).await
+    }
 }
 
 impl OrgSecretKey {
     /// Generates a new random organization secret key.
     pub fn random<R: CryptoRng + RngCore>(rng: &mut R) -> Self {
-        let seed1 = MiniSecretKey::generate_with(&mut *rng);
-        let seed2 = MiniSecretKey::generate_with(&mut *rng);
+        Self::random_keys(rng)
+    }
+
+    /// Generates a new random organization secret key, from a source of randomness chosen at
+    /// runtime.
+    ///
+    /// This gives the same key as [`OrgSecretKey::random`] for the same random bytes.
+    pub fn random_dyn(mut rng: &mut dyn CryptoRngCore) -> Self {
+        Self::random(&mut rng)
+    }
+
+    /// Deterministically derives an organization secret key from a seed.
+    ///
+    /// Each half of the seed is expanded into one of the two sub-keys.
+    pub fn from_seed(seed: &[u8; 64]) -> Self {
+        let (seed1, seed2) = seed.split_at(32);
+        Self {
+            keys: [
+                expand_seed(seed1.try_into().unwrap()),
+                expand_seed(seed2.try_into().unwrap()),
+            ],
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl OrgSecretKey {
+    /// Gets the exponent of the first sub-key, tweaked for a given epoch
+    pub(crate) fn issuer_exponent(&self, epoch: u64) -> Scalar {
+        self.keys[0].exponent() + issuer_tweak(epoch)
+    }
+
+    /// Gets the exponent of the second sub-key, tweaked for a given epoch
+    pub(crate) fn epoch_exponent(&self, epoch: u64) -> Scalar {
+        self.keys[1].exponent() + epoch_tweak(epoch)
+    }
+
+    /// Gets the exponent of the second sub-key, tweaked for a committed attribute at a given epoch
+    pub(crate) fn attribute_exponent(
+        &self,
+        epoch: u64,
+        index: usize,
+        commitment: &RistrettoPoint,
+    ) -> Scalar {
+        self.keys[1].exponent() + attribute_tweak(epoch, index, commitment)
+    }
+
+    /// Gets the exponent of the second sub-key, tweaked for a committed integer attribute at a
+    /// given epoch
+    pub(crate) fn range_exponent(
+        &self,
+        epoch: u64,
+        index: usize,
+        commitment: &RistrettoPoint,
+    ) -> Scalar {
+        self.keys[1].exponent() + range_tweak(epoch, index, commitment)
+    }
+
+    /// Gets the exponent of the second sub-key, tweaked for a context at a given epoch
+    pub(crate) fn context_exponent(&self, epoch: u64, context: &[u8]) -> Scalar {
+        self.keys[1].exponent() + context_tweak(epoch, context)
+    }
+}
+
+impl<const N: usize> OrgSecretKey<N> {
+    /// Generates a new random organization secret key with `N` sub-keys.
+    pub fn random_keys<R: CryptoRng + RngCore>(rng: &mut R) -> Self {
         Self {
-            key1: seed1.expand(ExpansionMode::Uniform),
-            key2: seed2.expand(ExpansionMode::Uniform),
+            keys: core::array::from_fn(|_| {
+                MiniSecretKey::generate_with(&mut *rng).expand(ExpansionMode::Uniform)
+            }),
         }
     }
 
     /// Gets the public part of this key.
-    pub fn to_public(&self) -> OrgPublicKey {
+    pub fn to_public(&self) -> OrgPublicKey<N> {
         OrgPublicKey {
-            key1: self.key1.to_public(),
-            key2: self.key2.to_public(),
+            keys: self.keys.each_ref().map(SecretKey::to_public),
         }
     }
 
     /// Proves ownership of this key to a user
+    #[cfg(feature = "std")]
     pub async fn prove_ownership<T: LocalTransport>(&self, user: &mut T) -> Result {
-        prove_ownership(user, self.to_public().key1.as_point(), self.key1.exponent()).await?;
-        prove_ownership(user, self.to_public().key2.as_point(), self.key2.exponent()).await
+        for key in &self.keys {
+            prove_ownership(user, key.to_public().as_point(), key.exponent()).await?;
+        }
+        Ok(())
+    }
+
+    /// Proves ownership of this key non-interactively, so that the proof can be published
+    #[cfg(feature = "getrandom")]
+    pub fn ownership_proof(&self) -> OwnershipProof<N> {
+        self.ownership_proof_with_rng(&mut crate::proof::rng())
+    }
+
+    /// Proves ownership of this key non-interactively, with a given source of randomness
+    pub fn ownership_proof_with_rng<R: CryptoRng + RngCore>(
+        &self,
+        rng: &mut R,
+    ) -> OwnershipProof<N> {
+        OwnershipProof {
+            transcripts: self.keys.each_ref().map(|key| {
+                dlog::prove_noninteractive_with_rng(
+                    Publics {
+                        g: &RISTRETTO_BASEPOINT_POINT,
+                        h: key.to_public().as_point(),
+                    },
+                    Secrets { x: key.exponent() },
+                    rng,
+                )
+            }),
+        }
     }
 }
 
-impl OrgPublicKey {
+impl<const N: usize> OrgPublicKey<N> {
     /// Gets this public key's points on the ristretto curve.
-    pub fn points(&self) -> (&RistrettoPoint, &RistrettoPoint) {
-        (self.key1.as_point(), self.key2.as_point())
+    pub fn points(&self) -> [&RistrettoPoint; N] {
+        self.keys.each_ref().map(PublicKey::as_point)
+    }
+
+    /// Checks that this key's sub-keys are distinct and none is the identity
+    ///
+    /// Keys made by [`OrgSecretKey::random`] always pass, but one received from elsewhere should
+    /// be checked before issuing or transferring credentials under it. This fails with
+    /// [`Error::InvalidKey`] otherwise.
+    pub fn validate(&self) -> Result {
+        let points = self.points();
+        for (i, point) in points.iter().enumerate() {
+            if point.is_identity() || points[..i].contains(point) {
+                return Err(Error::InvalidKey);
+            }
+        }
+        Ok(())
     }
 
     /// Verifies an organization's ownership of this key
+    #[cfg(feature = "std")]
     pub async fn verify_ownership<T: LocalTransport>(&self, org: &mut T) -> Result {
-        verify_ownership(org, self.key1.as_point()).await?;
-        verify_ownership(org, self.key2.as_point()).await
+        for key in &self.keys {
+            verify_ownership(org, key.as_point()).await?;
+        }
+        Ok(())
+    }
+
+    /// Verifies an organization's ownership of this key, unless it was verified recently
+    ///
+    /// If `cache` has a verification of this key younger than its time to live, this succeeds
+    /// without using the transport at all, so the organization must not be running
+    /// [`OrgSecretKey::prove_ownership`] either; how both sides agree on that is up to the
+    /// application. Otherwise, this runs [`OrgPublicKey::verify_ownership`], and records the time
+    /// of a successful verification in `cache`.
+    #[cfg(feature = "std")]
+    pub async fn verify_ownership_cached<T: LocalTransport>(
+        &self,
+        cache: &mut OwnershipCache,
+        org: &mut T,
+    ) -> Result {
+        let id = self.cache_id();
+        if cache.is_fresh(&id) {
+            return Ok(());
+        }
+        self.verify_ownership(org).await?;
+        cache.verified.insert(id, (cache.clock)());
+        Ok(())
+    }
+
+    #[cfg(feature = "std")]
+    fn cache_id(&self) -> Vec<u8> {
+        self.keys.iter().flat_map(PublicKey::to_bytes).collect()
+    }
+
+    /// Verifies a non-interactive proof of an organization's ownership of this key
+    pub fn verify_ownership_proof(&self, proof: &OwnershipProof<N>) -> Result {
+        for (key, transcript) in self.keys.iter().zip(&proof.transcripts) {
+            transcript.verify(Publics {
+                g: &RISTRETTO_BASEPOINT_POINT,
+                h: key.as_point(),
+            })?;
+        }
+        Ok(())
+    }
+}
+
+impl OrgPublicKey {
+    /// Gets the point of the first sub-key, tweaked for a given epoch
+    pub(crate) fn issuer_point(&self, epoch: u64) -> RistrettoPoint {
+        self.points()[0] + &issuer_tweak(epoch) * RISTRETTO_BASEPOINT_TABLE
     }
+
+    /// Gets the point of the second sub-key, tweaked for a given epoch
+    pub(crate) fn epoch_point(&self, epoch: u64) -> RistrettoPoint {
+        self.points()[1] + &epoch_tweak(epoch) * RISTRETTO_BASEPOINT_TABLE
+    }
+
+    /// Gets the point of the second sub-key, tweaked for a committed attribute at a given epoch
+    pub(crate) fn attribute_point(
+        &self,
+        epoch: u64,
+        index: usize,
+        commitment: &RistrettoPoint,
+    ) -> RistrettoPoint {
+        self.points()[1] + &attribute_tweak(epoch, index, commitment) * RISTRETTO_BASEPOINT_TABLE
+    }
+
+    /// Gets the point of the second sub-key, tweaked for a committed integer attribute at a given
+    /// epoch
+    pub(crate) fn range_point(
+        &self,
+        epoch: u64,
+        index: usize,
+        commitment: &RistrettoPoint,
+    ) -> RistrettoPoint {
+        self.points()[1] + &range_tweak(epoch, index, commitment) * RISTRETTO_BASEPOINT_TABLE
+    }
+
+    /// Gets the point of the second sub-key, tweaked for a context at a given epoch
+    pub(crate) fn context_point(&self, epoch: u64, context: &[u8]) -> RistrettoPoint {
+        self.points()[1] + &context_tweak(epoch, context) * RISTRETTO_BASEPOINT_TABLE
+    }
+}
+
+/// Derives the tweak of the first sub-key that gives each epoch its own issuing key
+///
+/// Together with [`epoch_tweak`], this makes the key that credentials are issued with differ in
+/// both of its parts from one epoch to the next, so nothing learned from credentials of one epoch
+/// helps to forge or re-randomise credentials for another.
+fn issuer_tweak(epoch: u64) -> Scalar {
+    let mut t = merlin::Transcript::new(b"nym/0.1/credential-issuer");
+    t.commit(b"epoch", &epoch);
+    t.challenge(b"tweak")
+}
+
+/// Derives the tweak that binds credentials to an epoch
+fn epoch_tweak(epoch: u64) -> Scalar {
+    let mut t = merlin::Transcript::new(b"nym/0.1/credential-epoch");
+    t.commit(b"epoch", &epoch);
+    t.challenge(b"tweak")
+}
+
+/// Derives the tweak that binds a committed attribute to a credential
+fn attribute_tweak(epoch: u64, index: usize, commitment: &RistrettoPoint) -> Scalar {
+    let mut t = merlin::Transcript::new(b"nym/0.1/credential-attribute");
+    t.commit(b"epoch", &epoch);
+    t.commit(b"index", &(index as u64));
+    t.commit(b"commitment", commitment);
+    t.challenge(b"tweak")
+}
+
+/// Derives the tweak that binds a committed integer attribute to a credential
+fn range_tweak(epoch: u64, index: usize, commitment: &RistrettoPoint) -> Scalar {
+    let mut t = merlin::Transcript::new(b"nym/0.1/credential-range-attribute");
+    t.commit(b"epoch", &epoch);
+    t.commit(b"index", &(index as u64));
+    t.commit(b"commitment", commitment);
+    t.challenge(b"tweak")
+}
+
+/// Derives the tweak that binds a credential to a context
+fn context_tweak(epoch: u64, context: &[u8]) -> Scalar {
+    let mut t = merlin::Transcript::new(b"nym/0.1/credential-context");
+    t.commit(b"epoch", &epoch);
+    t.commit(b"context", context);
+    t.challenge(b"tweak")
+}
+
+/// Expands a seed into a secret key
+fn expand_seed(seed: &[u8; 32]) -> SecretKey {
+    MiniSecretKey::from_bytes(seed)
+        .expect("seed should have the right length")
+        .expand(ExpansionMode::Uniform)
+}
+
+/// Serde support for arrays of any length
+mod serde_array {
+    use alloc::{format, vec::Vec};
+
+    use serde::{de::Error as _, Deserialize, Deserializer, Serialize, Serializer};
+
+    pub fn serialize<S: Serializer, T: Serialize, const N: usize>(
+        array: &[T; N],
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        array.as_slice().serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>, T: Deserialize<'de>, const N: usize>(
+        deserializer: D,
+    ) -> Result<[T; N], D::Error> {
+        let items = Vec::<T>::deserialize(deserializer)?;
+        let len = items.len();
+        items
+            .try_into()
+            .map_err(|_| D::Error::invalid_length(len, &&*format!("{N} elements")))
+    }
+}
+
+#[cfg(feature = "std")]
+impl OwnershipCache {
+    /// Creates an empty cache, remembering verifications for a given time
+    ///
+    /// On `wasm32-unknown-unknown`, the system clock needs the `wasm` feature, which reads it
+    /// from JavaScript; without it, use [`OwnershipCache::with_clock`].
+    #[cfg(any(
+        not(all(target_arch = "wasm32", target_os = "unknown")),
+        feature = "wasm"
+    ))]
+    pub fn new(ttl: Duration) -> Self {
+        Self::with_clock(ttl, system_clock)
+    }
+
+    /// Creates an empty cache, remembering verifications for a given time as read from a clock
+    ///
+    /// The clock gives the current time as the time elapsed since any fixed point, such as the
+    /// Unix epoch. A verification is stale once the clock reads earlier than when it was recorded.
+    pub fn with_clock(ttl: Duration, clock: fn() -> Duration) -> Self {
+        Self {
+            ttl,
+            clock,
+            verified: HashMap::new(),
+        }
+    }
+
+    /// Forgets every verification
+    pub fn clear(&mut self) {
+        self.verified.clear();
+    }
+
+    fn is_fresh(&self, id: &[u8]) -> bool {
+        let now = (self.clock)();
+        self.verified
+            .get(id)
+            .and_then(|verified| now.checked_sub(*verified))
+            .is_some_and(|age| age < self.ttl)
+    }
+}
+
+/// Reads the system clock, as the time elapsed since the Unix epoch
+#[cfg(all(
+    feature = "std",
+    not(all(target_arch = "wasm32", target_os = "unknown"))
+))]
+fn system_clock() -> Duration {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default()
+}
+
+/// Reads the system clock from JavaScript, as the time elapsed since the Unix epoch
+#[cfg(all(feature = "wasm", target_arch = "wasm32", target_os = "unknown"))]
+fn system_clock() -> Duration {
+    Duration::from_secs_f64(js_sys::Date::now() / 1000.0)
 }
 
 /// Proves ownership of a public key
+#[cfg(feature = "std")]
 async fn prove_ownership<T: LocalTransport>(
     transport: &mut T,
     public: &RistrettoPoint,
     secret: &Scalar,
 ) -> Result {
-    dlog_eq::prove(
+    dlog::prove(
         transport,
         Publics {
-            g1: &RISTRETTO_BASEPOINT_POINT,
-            h1: public,
-            g2: &RISTRETTO_BASEPOINT_POINT,
-            h2: public,
+            g: &RISTRETTO_BASEPOINT_POINT,
+            h: public,
         },
         Secrets { x: secret },
     )
@@ -117,15 +585,271 @@ async fn prove_ownership<T: LocalTransport>(
 }
 
 /// Verifies ownership of a public key
+#[cfg(feature = "std")]
 async fn verify_ownership<T: LocalTransport>(transport: &mut T, public: &RistrettoPoint) -> Result {
-    dlog_eq::verify(
+    dlog::verify(
         transport,
         Publics {
-            g1: &RISTRETTO_BASEPOINT_POINT,
-            h1: public,
-            g2: &RISTRETTO_BASEPOINT_POINT,
-            h2: public,
+            g: &RISTRETTO_BASEPOINT_POINT,
+            h: public,
         },
     )
     .await
 }
+
+#[cfg(test)]
+mod test {
+    use std::{
+        assert_matches::assert_matches,
+        sync::atomic::{AtomicU64, Ordering},
+        time::Duration,
+    };
+
+    use curve25519_dalek::{traits::Identity as _, RistrettoPoint};
+    use futures::{executor::block_on, future::try_join};
+    use rand::{thread_rng, SeedableRng as _};
+    use rand_chacha::ChaCha20Rng;
+    use schnorrkel::PublicKey;
+
+    use crate::{
+        transport::{codec::Json, memory::DuplexTransport, Recording},
+        Error,
+    };
+
+    use super::{
+        OrgPublicKey, OrgSecretKey, OwnershipCache, OwnershipProof, UserPublicKey, UserSecretKey,
+    };
+
+    #[test]
+    fn debug_redaction() {
+        let sk = UserSecretKey::random(&mut thread_rng());
+        let debug = format!("{sk:?}");
+        assert!(debug.contains("redacted"));
+        let secret = sk.key.to_bytes();
+        assert!(!debug.contains(&hex(&secret[..32])));
+        assert!(!debug.contains(&format!("{:?}", &secret[..32])));
+
+        let sk = OrgSecretKey::random(&mut thread_rng());
+        let debug = format!("{sk:#?}");
+        assert!(debug.contains("redacted"));
+        for key in &sk.keys {
+            assert!(!debug.contains(&hex(&key.to_bytes()[..32])));
+        }
+
+        let pk = sk.to_public();
+        let debug = format!("{pk:?}");
+        for point in pk.points() {
+            assert!(debug.contains(&hex(point.compress().as_bytes())));
+        }
+    }
+
+    fn hex(bytes: &[u8]) -> String {
+        bytes.iter().map(|b| format!("{b:02x}")).collect()
+    }
+
+    #[test]
+    fn public_key_serde() {
+        let pk = OrgSecretKey::random(&mut thread_rng()).to_public();
+        let json = serde_json::to_vec(&pk).unwrap();
+        let pk2: OrgPublicKey = serde_json::from_slice(&json).unwrap();
+        assert_eq!(pk.points(), pk2.points());
+    }
+
+    #[test]
+    fn secret_key_serde() {
+        let sk = UserSecretKey::random(&mut thread_rng());
+        let json = serde_json::to_vec(&sk).unwrap();
+        let sk2: UserSecretKey = serde_json::from_slice(&json).unwrap();
+        assert_eq!(sk.key.to_bytes(), sk2.key.to_bytes());
+        assert!(sk.to_public() == sk2.to_public());
+    }
+
+    #[test]
+    fn public_key_bytes() {
+        let pk = UserSecretKey::random(&mut thread_rng()).to_public();
+        let bytes = pk.to_bytes();
+        assert_eq!(bytes, *pk.point().compress().as_bytes());
+        assert_matches!(UserPublicKey::from_bytes(&bytes), Ok(pk2) if pk2 == pk);
+
+        assert_matches!(
+            UserPublicKey::from_bytes(&[0xff; 32]),
+            Err(Error::InvalidEncoding)
+        );
+        let mut bytes = bytes;
+        bytes[31] |= 0x80;
+        assert_matches!(
+            UserPublicKey::from_bytes(&bytes),
+            Err(Error::InvalidEncoding)
+        );
+    }
+
+    #[test]
+    fn degenerate_key() {
+        let pk = OrgSecretKey::random(&mut thread_rng()).to_public();
+        assert_matches!(pk.validate(), Ok(()));
+
+        let repeated = OrgPublicKey {
+            keys: [pk.keys[0]; 2],
+        };
+        assert_matches!(repeated.validate(), Err(Error::InvalidKey));
+
+        let identity = OrgPublicKey {
+            keys: [
+                pk.keys[0],
+                PublicKey::from_point(RistrettoPoint::identity()),
+            ],
+        };
+        assert_matches!(identity.validate(), Err(Error::InvalidKey));
+    }
+
+    #[test]
+    fn keypair() {
+        let sk = UserSecretKey::random(&mut thread_rng());
+        let keypair = sk.keypair();
+        assert_eq!(keypair.public.to_bytes(), sk.to_public().to_bytes());
+        assert_eq!(keypair.secret.to_bytes(), sk.key.to_bytes());
+    }
+
+    #[test]
+    fn many_org_keys() {
+        let sk = OrgSecretKey::<5>::random_keys(&mut thread_rng());
+        let pk = sk.to_public();
+        let json = serde_json::to_vec(&pk).unwrap();
+        let pk2: OrgPublicKey<5> = serde_json::from_slice(&json).unwrap();
+        assert_eq!(pk.points(), pk2.points());
+        assert!(serde_json::from_slice::<OrgPublicKey<4>>(&json).is_err());
+    }
+
+    #[test]
+    fn public_key_equality() {
+        let sk1 = UserSecretKey::random(&mut thread_rng());
+        let sk2 = UserSecretKey::random(&mut thread_rng());
+        assert!(sk1.to_public() == sk1.to_public());
+        assert!(sk1.to_public() != sk2.to_public());
+
+        let sk1 = OrgSecretKey::random(&mut thread_rng());
+        let sk2 = OrgSecretKey::random(&mut thread_rng());
+        assert!(sk1.to_public() == sk1.to_public());
+        assert!(sk1.to_public() != sk2.to_public());
+    }
+
+    #[test]
+    fn keys_from_seed() {
+        let pk1 = UserSecretKey::from_seed(&[7; 32]).to_public();
+        let pk2 = UserSecretKey::from_seed(&[7; 32]).to_public();
+        let pk3 = UserSecretKey::from_seed(&[8; 32]).to_public();
+        assert!(pk1 == pk2, "same seed should give the same key");
+        assert!(pk1 != pk3, "different seeds should give different keys");
+
+        let mut seed = [7; 64];
+        seed[32..].fill(8);
+        let pk1 = OrgSecretKey::from_seed(&seed).to_public();
+        let pk2 = OrgSecretKey::from_seed(&seed).to_public();
+        assert!(pk1 == pk2, "same seed should give the same key");
+        assert_ne!(
+            pk1.points()[0],
+            pk1.points()[1],
+            "sub-keys should be independent"
+        );
+    }
+
+    #[test]
+    fn keys_from_dyn_rng() {
+        let mut rng1 = ChaCha20Rng::seed_from_u64(42);
+        let mut rng2 = ChaCha20Rng::seed_from_u64(42);
+        let pk1 = UserSecretKey::random(&mut rng1).to_public();
+        let pk2 = UserSecretKey::random_dyn(&mut rng2).to_public();
+        assert!(pk1 == pk2, "same random bytes should give the same key");
+
+        let pk1 = OrgSecretKey::random(&mut rng1).to_public();
+        let pk2 = OrgSecretKey::random_dyn(&mut rng2).to_public();
+        assert!(pk1 == pk2, "same random bytes should give the same key");
+    }
+
+    #[test]
+    fn ownership_proof() {
+        let sk = OrgSecretKey::random(&mut thread_rng());
+        let proof = sk.ownership_proof();
+        let json = serde_json::to_vec(&proof).unwrap();
+        let proof: OwnershipProof = serde_json::from_slice(&json).unwrap();
+        assert!(sk.to_public().verify_ownership_proof(&proof).is_ok());
+
+        let other = OrgSecretKey::random(&mut thread_rng()).to_public();
+        assert!(other.verify_ownership_proof(&proof).is_err());
+    }
+
+    #[test]
+    fn cached_ownership() {
+        let sk = OrgSecretKey::random(&mut thread_rng());
+        let pk = sk.to_public();
+        let mut cache = OwnershipCache::new(Duration::from_secs(60));
+
+        let (u_channel, mut o_channel) = DuplexTransport::<Json>::pair();
+        let mut u_channel = Recording::new(u_channel);
+        block_on(try_join(
+            pk.verify_ownership_cached(&mut cache, &mut u_channel),
+            sk.prove_ownership(&mut o_channel),
+        ))
+        .unwrap();
+        let exchanged = u_channel.log().len();
+        assert!(exchanged > 0);
+
+        block_on(pk.verify_ownership_cached(&mut cache, &mut u_channel)).unwrap();
+        assert_eq!(
+            u_channel.log().len(),
+            exchanged,
+            "second verification should skip the proof"
+        );
+
+        cache.clear();
+        block_on(try_join(
+            pk.verify_ownership_cached(&mut cache, &mut u_channel),
+            sk.prove_ownership(&mut o_channel),
+        ))
+        .unwrap();
+        assert_eq!(u_channel.log().len(), 2 * exchanged);
+    }
+
+    #[test]
+    fn cached_ownership_clock() {
+        static NOW: AtomicU64 = AtomicU64::new(100);
+        let clock = || Duration::from_secs(NOW.load(Ordering::Relaxed));
+
+        let sk = OrgSecretKey::random(&mut thread_rng());
+        let pk = sk.to_public();
+        let mut cache = OwnershipCache::with_clock(Duration::from_secs(60), clock);
+
+        let (u_channel, mut o_channel) = DuplexTransport::<Json>::pair();
+        let mut u_channel = Recording::new(u_channel);
+        block_on(try_join(
+            pk.verify_ownership_cached(&mut cache, &mut u_channel),
+            sk.prove_ownership(&mut o_channel),
+        ))
+        .unwrap();
+        let exchanged = u_channel.log().len();
+
+        NOW.store(159, Ordering::Relaxed);
+        block_on(pk.verify_ownership_cached(&mut cache, &mut u_channel)).unwrap();
+        assert_eq!(u_channel.log().len(), exchanged);
+
+        NOW.store(160, Ordering::Relaxed);
+        block_on(try_join(
+            pk.verify_ownership_cached(&mut cache, &mut u_channel),
+            sk.prove_ownership(&mut o_channel),
+        ))
+        .unwrap();
+        assert_eq!(u_channel.log().len(), 2 * exchanged);
+
+        NOW.store(100, Ordering::Relaxed);
+        block_on(try_join(
+            pk.verify_ownership_cached(&mut cache, &mut u_channel),
+            sk.prove_ownership(&mut o_channel),
+        ))
+        .unwrap();
+        assert_eq!(
+            u_channel.log().len(),
+            3 * exchanged,
+            "a clock that went back should not extend a verification"
+        );
+    }
+}
@@ -0,0 +1,131 @@
+//! Labels of the messages sent in each protocol
+//!
+//! Labels only need to be unique within a protocol, including the sub-protocols it runs, so the
+//! same bytes are reused across protocols.
+
+/// Labels for the proof of knowledge of a discrete logarithm
+pub(crate) mod dlog {
+    /// Commitment
+    pub(crate) const COMMITMENT: &[u8] = b"a";
+    /// Challenge
+    pub(crate) const CHALLENGE: &[u8] = b"c";
+    /// Response
+    pub(crate) const RESPONSE: &[u8] = b"y";
+
+    #[cfg(test)]
+    pub(crate) const ALL: &[&[u8]] = &[COMMITMENT, CHALLENGE, RESPONSE];
+}
+
+/// Labels for the proofs of equality of two discrete logarithms, blinded or not
+pub(crate) mod dlog_eq {
+    /// Commitments for both points
+    pub(crate) const COMMITMENTS: &[u8] = b"ab";
+    /// Challenge
+    pub(crate) const CHALLENGE: &[u8] = b"c";
+    /// Response
+    pub(crate) const RESPONSE: &[u8] = b"y";
+
+    #[cfg(test)]
+    pub(crate) const ALL: &[&[u8]] = &[COMMITMENTS, CHALLENGE, RESPONSE];
+}
+
+/// Labels for the proof of equality of any number of discrete logarithms
+pub(crate) mod dlog_eq_n {
+    /// Commitments, one per base
+    pub(crate) const COMMITMENTS: &[u8] = b"a";
+    /// Challenge
+    pub(crate) const CHALLENGE: &[u8] = b"c";
+    /// Response
+    pub(crate) const RESPONSE: &[u8] = b"y";
+
+    #[cfg(test)]
+    pub(crate) const ALL: &[&[u8]] = &[COMMITMENTS, CHALLENGE, RESPONSE];
+}
+
+/// Labels for nym generation, followed by a proof of equality of discrete logarithms
+pub(crate) mod nym {
+    /// Wire version
+    pub(crate) const VERSION: &[u8] = b"version";
+    /// User's base
+    pub(crate) const USER_BASE: &[u8] = b"a~";
+    /// User's key on their base
+    pub(crate) const USER_POINT: &[u8] = b"b~";
+    /// Nym's base
+    pub(crate) const BASE: &[u8] = b"a";
+    /// Nym's point
+    pub(crate) const POINT: &[u8] = b"b";
+
+    #[cfg(test)]
+    pub(crate) const ALL: &[&[u8]] = &[VERSION, USER_BASE, USER_POINT, BASE, POINT];
+}
+
+/// Labels for credential issuance, followed by two blinded proofs of equality of discrete
+/// logarithms
+pub(crate) mod issuance {
+    /// Credential's base
+    pub(crate) const BASE: &[u8] = b"a";
+    /// First credential point
+    pub(crate) const A: &[u8] = b"A";
+    /// Second credential point
+    pub(crate) const B: &[u8] = b"B";
+    /// Context the credential is bound to
+    pub(crate) const CONTEXT: &[u8] = b"x";
+    /// Blinding factor of the commitment to an attribute
+    pub(crate) const OPENING: &[u8] = b"o";
+
+    #[cfg(test)]
+    pub(crate) const ALL: &[&[u8]] = &[BASE, A, B, CONTEXT, OPENING];
+}
+
+/// Labels for credential refresh, followed by credential issuance
+pub(crate) mod refresh {
+    /// Credential to refresh, along with a proof of holding it
+    pub(crate) const CREDENTIAL: &[u8] = b"r";
+
+    #[cfg(test)]
+    pub(crate) const ALL: &[&[u8]] = &[CREDENTIAL];
+}
+
+/// Labels for nym authentication
+pub(crate) mod auth {
+    /// Organization's nonce
+    pub(crate) const NONCE: &[u8] = b"n";
+    /// Proof bound to the nonce, along with the nonce answered
+    pub(crate) const PROOF: &[u8] = b"p";
+
+    #[cfg(test)]
+    pub(crate) const ALL: &[&[u8]] = &[NONCE, PROOF];
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashSet;
+
+    fn assert_unique(protocol: &str, labels: &[&[&[u8]]]) {
+        let all: Vec<_> = labels.concat();
+        let unique: HashSet<_> = all.iter().collect();
+        assert_eq!(
+            unique.len(),
+            all.len(),
+            "labels in {protocol} should be unique"
+        );
+    }
+
+    #[test]
+    fn unique_labels() {
+        assert_unique("dlog", &[super::dlog::ALL]);
+        assert_unique("dlog_eq", &[super::dlog_eq::ALL]);
+        assert_unique("dlog_eq_n", &[super::dlog_eq_n::ALL]);
+        assert_unique("nym", &[super::nym::ALL, super::dlog_eq::ALL]);
+        assert_unique("issuance", &[super::issuance::ALL, super::dlog_eq::ALL]);
+        assert_unique("auth", &[super::auth::ALL]);
+        assert_unique(
+            "refresh",
+            &[
+                super::refresh::ALL,
+                super::issuance::ALL,
+                super::dlog_eq::ALL,
+            ],
+        );
+    }
+}
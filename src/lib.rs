@@ -1,18 +1,50 @@
 #![allow(mixed_script_confusables, confusable_idents)]
 #![warn(missing_docs)]
 #![deny(clippy::correctness)]
-
+#![cfg_attr(not(feature = "std"), no_std)]
 #![cfg_attr(test, feature(assert_matches))]
 
 //! An implementation of a pseudonym system as described in <https://www.princeton.edu/~rblee/ELE572Papers/Fall04Readings/lrsw.pdf>
+//!
+//! Without the default `std` feature, this crate only needs `alloc`. That leaves out the
+//! interactive protocols and transports; the keys, nyms, credentials, showings, hashing, and
+//! non-interactive proofs remain, taking their randomness from a given RNG, such as the one set
+//! with [`UserBuilder::rng`]. Randomness is otherwise drawn from the operating
+//! system's generator through `getrandom`, which `std` enables; without `std`, the `getrandom`
+//! feature brings back the functions that draw from it. The `wasm` feature enables `getrandom`'s
+//! JavaScript backend.
+//!
+//! The `hazmat` feature exposes functions that pin the nonces and challenges of interactive proofs,
+//! such as [`dlog_eq::prove_with_nonce`](proof::dlog_eq::prove_with_nonce), for test harnesses that
+//! compare messages with other implementations. Misusing them reveals secrets.
+
+extern crate alloc;
 
+#[cfg(feature = "std")]
+mod accumulator;
+#[cfg(feature = "std")]
+pub use accumulator::*;
 mod error;
 pub use error::*;
 mod key;
 pub use key::*;
 mod nym;
 pub use nym::*;
+mod revocation;
+pub use revocation::*;
+#[cfg(feature = "std")]
+pub mod session;
+
+#[cfg(feature = "blocking")]
+pub mod blocking;
+mod encoding;
+pub mod group;
+pub mod hash;
+#[cfg(feature = "std")]
+mod labels;
+pub mod proof;
+#[cfg(feature = "std")]
+pub mod transport;
 
-mod hash;
-mod proof;
-mod transport;
+/// The version of `schnorrkel` that keys are built on, for use with [`UserSecretKey::keypair`]
+pub use schnorrkel;
@@ -1,27 +1,62 @@
 //! Pseudo*nym* generation and verification
 
-use curve25519_dalek::{constants::RISTRETTO_BASEPOINT_POINT, RistrettoPoint, Scalar};
-use rand::thread_rng;
-use schnorrkel::{points::RistrettoBoth, PublicKey};
+#[cfg(feature = "std")]
+use std::{collections::VecDeque, sync::Mutex};
+
+use alloc::{boxed::Box, vec::Vec};
+use curve25519_dalek::{
+    constants::{RISTRETTO_BASEPOINT_POINT, RISTRETTO_BASEPOINT_TABLE},
+    ristretto::CompressedRistretto,
+    traits::{IsIdentity as _, MultiscalarMul as _},
+    RistrettoPoint, Scalar,
+};
+#[cfg(feature = "std")]
+use futures::future::try_join_all;
+use rand::{CryptoRng, RngCore};
+use rand_core::CryptoRngCore;
+use schnorrkel::{context::SigningTranscript as _, points::RistrettoBoth, PublicKey};
 use serde::{Deserialize, Serialize};
+use subtle::{Choice, ConstantTimeEq};
 
+#[cfg(feature = "getrandom")]
+use crate::proof::rng;
 use crate::{
-    error::{Error, Result},
+    encoding::{Reader, Writer, POINT_LENGTH, TRANSCRIPT_LENGTH, U64_LENGTH},
+    error::{Error, ProofStage, Result},
+    group::{Group, Ristretto},
+    hash::{append_length, AppendMessage, Transcribe, TranscriptProtocol as _},
     key::{OrgPublicKey, OrgSecretKey, UserPublicKey, UserSecretKey},
+    proof::{
+        dlog_eq::{self, Publics, Secrets, Transcript},
+        dlog_eq_n, range, WIRE_VERSION,
+    },
+    revocation::RevocationList,
+};
+#[cfg(feature = "std")]
+use crate::{
+    labels,
     proof::{
         blind_dlog_eq::{self, ProverSecrets, VerifierSecrets},
-        dlog_eq::{self, Publics, Transcript},
+        dlog_eq::OwnedPublics,
+        ensure, ensure_not_identity,
     },
-    transport::LocalTransport,
+    transport::{Guarded, LocalTransport},
 };
 
-/// A pseudonym
-#[derive(PartialEq, Eq, Debug, Copy, Clone, Serialize, Deserialize)]
-pub struct Nym {
-    a: RistrettoPoint,
-    b: RistrettoPoint,
+/// A pseudonym, in a given group
+///
+/// Only the base and point of a nym are generic: the protocols that generate and use nyms work
+/// with [`Nym`], over [`Ristretto`].
+#[derive(Debug, Copy, Clone, Serialize, Deserialize)]
+#[serde(bound = "")]
+pub struct NymOf<G: Group> {
+    pub(crate) a: G::Point,
+    pub(crate) b: G::Point,
 }
 
+/// A pseudonym
+pub type Nym = NymOf<Ristretto>;
+
 /// A credential
 #[derive(PartialEq, Eq, Debug, Copy, Clone, Serialize, Deserialize)]
 #[allow(non_snake_case)]
@@ -32,18 +67,197 @@ pub struct Cred {
     B: RistrettoPoint,
     T1: Transcript,
     T2: Transcript,
+    epoch: u64,
+}
+
+/// A self-contained showing of a credential, bound to a nym
+///
+/// Unlike a credential transfer, a showing can be verified offline, without interacting with the
+/// user.
+#[derive(PartialEq, Eq, Debug, Copy, Clone, Serialize, Deserialize)]
+pub struct CredShow {
+    nym: Nym,
+    cred: Cred,
+    proof: Transcript,
+}
+
+/// A credential carrying attributes
+///
+/// Each attribute is committed to at issuance, with [`range::commit_scalar`], and the commitment
+/// is certified separately for the same user, so that attributes can be disclosed selectively.
+/// The commitment to each attribute is fixed at issuance and revealed in every showing, as with a
+/// [`RangeCred`], so showings can be linked to each other, and to the issuance by the
+/// organization.
+#[derive(PartialEq, Eq, Debug, Clone, Serialize, Deserialize)]
+pub struct AttrCred {
+    cred: Cred,
+    attributes: Vec<(Scalar, Scalar, Cred)>,
+}
+
+/// A self-contained showing of a credential that discloses some of its attributes, bound to a nym
+///
+/// Every attribute is shown, in order: disclosed ones with the opening of their commitment, and
+/// the others with only their commitment.
+#[derive(PartialEq, Eq, Debug, Clone, Serialize, Deserialize)]
+pub struct AttributeShow {
+    nym: Nym,
+    cred: Cred,
+    attributes: Vec<(ShownAttribute, Cred)>,
+    proof: dlog_eq_n::Transcript,
+}
+
+/// An attribute in an [`AttributeShow`]
+#[derive(PartialEq, Eq, Debug, Copy, Clone, Serialize, Deserialize)]
+enum ShownAttribute {
+    /// A disclosed attribute, with the blinding factor of its commitment
+    Disclosed { value: Scalar, blinding: Scalar },
+    /// An attribute that is not disclosed
+    Hidden { commitment: RistrettoPoint },
+}
+
+impl ShownAttribute {
+    /// Gets the commitment to the attribute
+    fn commitment(&self) -> RistrettoPoint {
+        match self {
+            ShownAttribute::Disclosed { value, blinding } => range::commit_scalar(value, blinding),
+            ShownAttribute::Hidden { commitment } => *commitment,
+        }
+    }
+}
+
+/// A credential carrying integer attributes, each committed to at issuance
+///
+/// Unlike those of an [`AttrCred`], these attributes can be shown to lie in a range without
+/// being disclosed, with [`User::prove_attribute_range`]. The commitment to each attribute is
+/// fixed at issuance and revealed in every showing, though, so showings of the same attribute can
+/// be linked to each other, and to the issuance by the organization.
+#[derive(PartialEq, Eq, Debug, Clone, Serialize, Deserialize)]
+pub struct RangeCred {
+    cred: Cred,
+    attributes: Vec<(u64, Scalar, Cred)>,
+}
+
+/// A self-contained showing that an attribute of a credential lies in a range, bound to a nym
+#[derive(PartialEq, Eq, Debug, Clone, Serialize, Deserialize)]
+pub struct RangeShow {
+    nym: Nym,
+    cred: Cred,
+    index: usize,
+    commitment: RistrettoPoint,
+    attribute: Cred,
+    proof: dlog_eq_n::Transcript,
+    range: range::Transcript,
+}
+
+/// A self-contained showing of credentials from several organizations, bound to a nym
+///
+/// A single proof covers the nym and every credential, so they must all belong to the same user.
+#[derive(PartialEq, Eq, Debug, Clone, Serialize, Deserialize)]
+pub struct MultiShow {
+    nym: Nym,
+    creds: Vec<Cred>,
+    proof: dlog_eq_n::Transcript,
 }
 
+/// A record of the public parameters an organization used to issue a credential
+///
+/// The user blinds the credential during issuance, so these parameters differ from the ones the
+/// credential's own proofs are checked against, and cannot be linked to it.
+#[cfg(feature = "std")]
+#[derive(PartialEq, Eq, Debug, Copy, Clone, Serialize, Deserialize)]
+pub struct IssuanceRecord {
+    /// Nym the credential was issued for
+    pub nym: Nym,
+    /// Epoch the credential was issued for
+    pub epoch: u64,
+    /// Parameters of the two proofs given to the user, in order
+    pub publics: [OwnedPublics; 2],
+}
+
+/// The points of a credential computed ahead of its issuance, on a fresh base for its nym
+///
+/// [`Org::issue_credentials_parallel`] computes these, and [`Org::issue_prepared_credential`]
+/// issues them. They are consumed on issuance, as each base must only carry one credential.
+#[cfg(feature = "std")]
+#[derive(PartialEq, Eq, Debug)]
+#[allow(non_snake_case)]
+pub struct PreparedCredential {
+    nym: Nym,
+    base: Nym,
+    A: RistrettoPoint,
+    B: RistrettoPoint,
+    epoch: u64,
+}
+
+#[cfg(feature = "std")]
+impl PreparedCredential {
+    /// Gets the nym this credential is for
+    pub fn nym(&self) -> Nym {
+        self.nym
+    }
+
+    /// Gets the base the credential is issued on, and its `A` and `B` points
+    pub fn points(&self) -> (Nym, RistrettoPoint, RistrettoPoint) {
+        (self.base, self.A, self.B)
+    }
+}
+
+/// A hook called with each issuance record
+#[cfg(feature = "std")]
+type IssuanceAudit = dyn Fn(&IssuanceRecord) + Send + Sync;
+
+/// A source of randomness given to a builder
+type BoxedRng = Box<dyn CryptoRngCore + Send>;
+
+/// A source of randomness shared by the protocols an organization or user runs
+#[cfg(feature = "std")]
+type SharedRng = Mutex<BoxedRng>;
+
+/// A source of randomness used by the proofs a user makes, without `std`'s locks
+#[cfg(not(feature = "std"))]
+type SharedRng = core::cell::RefCell<BoxedRng>;
+
 /// An organization
+///
+/// Without `std`, an organization can only verify what users show it, and its secret key is
+/// unused until it runs the interactive protocols.
 pub struct Org {
+    #[cfg_attr(not(feature = "std"), allow(dead_code))]
     sk: OrgSecretKey,
     pk: OrgPublicKey,
+    revoked: RevocationList,
+    #[cfg(feature = "std")]
+    audit: Option<Box<IssuanceAudit>>,
+    #[cfg(feature = "std")]
+    version: u32,
+    #[cfg(feature = "std")]
+    nonces: Mutex<VecDeque<[u8; 32]>>,
+    #[cfg(feature = "std")]
+    rng: Option<SharedRng>,
 }
 
 /// A user
 pub struct User {
     sk: UserSecretKey,
     pk: UserPublicKey,
+    #[cfg(feature = "std")]
+    version: u32,
+    rng: Option<SharedRng>,
+}
+
+/// A builder for an [`Org`]
+pub struct OrgBuilder {
+    sk: Option<OrgSecretKey>,
+    version: u32,
+    #[cfg(feature = "std")]
+    rng: Option<BoxedRng>,
+}
+
+/// A builder for a [`User`]
+pub struct UserBuilder {
+    sk: Option<UserSecretKey>,
+    version: u32,
+    rng: Option<BoxedRng>,
 }
 
 /// A nym-based signature
@@ -56,8 +270,35 @@ impl UserSecretKey {
         self.key
             .sign_with_base(t, &RistrettoBoth::from_point(nym.b), &nym.a)
     }
+
+    /// Signs a message with a nym generated with this key, bound to some context
+    pub fn sign_with_context(&self, context: &[u8], message: &[u8], nym: &Nym) -> Signature {
+        self.sign(context_transcript(context, message), nym)
+    }
+}
+
+/// Builds the transcript for a message signed with some context
+fn context_transcript(context: &[u8], message: &[u8]) -> merlin::Transcript {
+    let mut t = merlin::Transcript::new(b"nym/0.1/signing-context");
+    t.commit(b"context", context);
+    t.commit(b"message", message);
+    t
+}
+
+impl ConstantTimeEq for Nym {
+    fn ct_eq(&self, other: &Self) -> Choice {
+        self.a.ct_eq(&other.a) & self.b.ct_eq(&other.b)
+    }
 }
 
+impl PartialEq for Nym {
+    fn eq(&self, other: &Self) -> bool {
+        self.ct_eq(other).into()
+    }
+}
+
+impl Eq for Nym {}
+
 impl Nym {
     /// Verifies a transcript signed with this nym
     #[allow(non_snake_case)]
@@ -66,47 +307,497 @@ impl Nym {
             .verify_with_base(t, sig, &self.a)
             .map_err(|_| Error::BadSignature)
     }
+
+    /// Verifies a message signed with this nym, bound to some context
+    pub fn verify_with_context(&self, context: &[u8], message: &[u8], sig: &Signature) -> Result {
+        self.verify(context_transcript(context, message), sig)
+    }
+
+    /// Verifies a transcript signed with a nym, given its compressed base and point
+    ///
+    /// This is [`Nym::verify`] for verifiers that store nyms as in [`Nym::compressed`]. Points
+    /// that don't decompress fail with [`Error::BadSignature`] too, as no signature verifies for
+    /// them.
+    pub fn verify_compressed(
+        a: &CompressedRistretto,
+        b: &CompressedRistretto,
+        t: merlin::Transcript,
+        sig: &Signature,
+    ) -> Result {
+        Self::try_from_compressed(a, b)
+            .map_err(|_| Error::BadSignature)?
+            .verify(t, sig)
+    }
+
+    /// Verifies several transcripts, each signed with a given nym
+    ///
+    /// All the verification equations are checked together as a random linear combination, with a
+    /// single multiscalar multiplication, as in [`dlog_eq::batch_verify`]. The whole batch fails
+    /// with [`Error::BadSignature`] if any one of the signatures doesn't verify.
+    #[cfg(feature = "getrandom")]
+    pub fn batch_verify(items: &[(&Nym, merlin::Transcript, &Signature)]) -> Result {
+        Self::batch_verify_with_rng(items, &mut rng())
+    }
+
+    /// Verifies several transcripts, each signed with a given nym, with a given source of
+    /// randomness for the linear combination
+    #[allow(non_snake_case)]
+    pub fn batch_verify_with_rng<R: CryptoRng + RngCore>(
+        items: &[(&Nym, merlin::Transcript, &Signature)],
+        rng: &mut R,
+    ) -> Result {
+        let mut scalars = Vec::with_capacity(items.len() * 3);
+        let mut points = Vec::with_capacity(items.len() * 3);
+        for (nym, t, sig) in items {
+            let bytes = sig.to_bytes();
+            let R_bytes = CompressedRistretto(bytes[..32].try_into().unwrap());
+            let R = R_bytes.decompress().ok_or(Error::BadSignature)?;
+            let mut s = [0; 32];
+            s.copy_from_slice(&bytes[32..]);
+            s[31] &= 127;
+            let s = Option::<Scalar>::from(Scalar::from_canonical_bytes(s))
+                .ok_or(Error::BadSignature)?;
+
+            // The same challenge as schnorrkel's, with the nym's point as the public key
+            let mut t = t.clone();
+            t.proto_name(b"Schnorr-sig");
+            t.commit_point(b"sign:pk", &nym.b.compress());
+            t.commit_point(b"sign:R", &R_bytes);
+            let k = t.challenge_scalar(b"sign:c");
+
+            // s·a = R + k·b
+            let z = Scalar::random(rng);
+            scalars.extend([z * s, -z, -z * k]);
+            points.extend([nym.a, R, nym.b]);
+        }
+        if RistrettoPoint::multiscalar_mul(scalars, points).is_identity() {
+            Ok(())
+        } else {
+            Err(Error::BadSignature)
+        }
+    }
+}
+
+impl<G: Group> NymOf<G> {
+    /// Gets the base and the point of this nym
+    ///
+    /// For a [`Nym`], these are Ristretto points. The Edwards points that represent them are not
+    /// canonical, and curve25519-dalek does not expose them, so external systems should consume
+    /// the compressed Ristretto encodings from [`Nym::compressed`] instead.
+    pub fn points(&self) -> (G::Point, G::Point) {
+        (self.a, self.b)
+    }
+
+    /// Rebuilds a nym from its base and point
+    ///
+    /// Nothing is checked: a nym obtained this way is only as trustworthy as the channel it came
+    /// through, and should be authenticated before use.
+    pub fn from_points(a: G::Point, b: G::Point) -> Self {
+        Self { a, b }
+    }
+}
+
+impl Nym {
+    /// Gets the compressed base and point of this nym
+    pub fn compressed(&self) -> (CompressedRistretto, CompressedRistretto) {
+        (self.a.compress(), self.b.compress())
+    }
+
+    /// Rebuilds a nym from its compressed base and point
+    ///
+    /// This fails with [`Error::InvalidEncoding`] if either point does not decompress.
+    pub fn try_from_compressed(a: &CompressedRistretto, b: &CompressedRistretto) -> Result<Nym> {
+        match (a.decompress(), b.decompress()) {
+            (Some(a), Some(b)) => Ok(Nym { a, b }),
+            _ => Err(Error::InvalidEncoding),
+        }
+    }
+
+    /// Length of the binary encoding of a nym
+    pub const LENGTH: usize = 2 * POINT_LENGTH;
+
+    /// Encodes this nym as compressed points
+    pub fn to_bytes(&self) -> [u8; Self::LENGTH] {
+        let mut bytes = [0; Self::LENGTH];
+        let mut w = Writer::new(&mut bytes);
+        w.point(&self.a);
+        w.point(&self.b);
+        bytes
+    }
+
+    /// Decodes a nym from compressed points
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        let mut r = Reader::new(bytes);
+        let nym = Self {
+            a: r.point()?,
+            b: r.point()?,
+        };
+        r.finish()?;
+        Ok(nym)
+    }
+}
+
+impl Transcribe for Cred {
+    fn append_to<S: AppendMessage + ?Sized>(&self, t: &mut S, label: &'static [u8]) {
+        append_length(t, label, 7);
+        self.a.append_to(t, b"a");
+        self.b.append_to(t, b"b");
+        self.A.append_to(t, b"A");
+        self.B.append_to(t, b"B");
+        self.T1.append_to(t, b"T1");
+        self.T2.append_to(t, b"T2");
+        self.epoch.append_to(t, b"epoch");
+    }
+}
+
+impl Cred {
+    /// Gets the epoch this credential was issued for
+    pub fn epoch(&self) -> u64 {
+        self.epoch
+    }
+
+    /// Gets a binding commitment to this credential
+    ///
+    /// The commitment is a hash of the whole credential, so no two credentials share one, and it
+    /// hides nothing about the credential. To bind a credential into a larger proof, commit it to
+    /// the proof's transcript directly, as it implements [`Transcribe`].
+    pub fn commitment(&self) -> RistrettoPoint {
+        let mut t = merlin::Transcript::new(b"nym/0.1/credential-commitment");
+        t.commit(b"cred", self);
+        t.challenge(b"commitment")
+    }
+
+    /// Verifies that this credential was issued with a given key
+    ///
+    /// This only checks the issuance proofs embedded in the credential, without involving its
+    /// holder, so it is a cheap way to reject invalid credentials before
+    /// [`Org::transfer_credential`]. It does not check the epoch, nor that the credential belongs
+    /// to any particular nym.
+    pub fn verify_against(&self, source_key: &OrgPublicKey) -> Result {
+        self.verify_issuer_with(*source_key, &source_key.epoch_point(self.epoch))
+    }
+
+    /// Verifies that this credential was issued with a given key, under a given context
+    ///
+    /// This is [`Cred::verify_against`] for credentials from
+    /// [`User::issue_credential_with_context`], and fails with [`Error::ContextMismatch`] if the
+    /// credential was issued under another context, or none.
+    pub fn verify_against_with_context(&self, source_key: &OrgPublicKey, context: &[u8]) -> Result {
+        let y_point = source_key.context_point(self.epoch, context);
+        match self.verify_issuer_with(*source_key, &y_point) {
+            Err(Error::BadProof {
+                stage: ProofStage::ChallengeMismatch,
+            }) => Err(Error::ContextMismatch),
+            res => res,
+        }
+    }
+
+    /// Verifies that this credential was issued with a given key, tweaked to a given point
+    fn verify_issuer_with(&self, source_key: OrgPublicKey, y_point: &RistrettoPoint) -> Result {
+        self.T1.verify(Publics {
+            g1: &RISTRETTO_BASEPOINT_POINT,
+            h1: y_point,
+            g2: &self.b,
+            h2: &self.A,
+        })?;
+        self.T2.verify(Publics {
+            g1: &RISTRETTO_BASEPOINT_POINT,
+            h1: &source_key.issuer_point(self.epoch),
+            g2: &(self.a + self.A),
+            h2: &self.B,
+        })
+    }
+
+    /// Encodes the two issuance proofs of this credential
+    ///
+    /// These are the encodings of two [`Transcript`]s, concatenated, each of which can be decoded
+    /// with [`Transcript::from_bytes`] and checked with [`Transcript::verify`].
+    pub fn issuance_proof_bytes(&self) -> Vec<u8> {
+        [self.T1.to_bytes(), self.T2.to_bytes()].concat()
+    }
+
+    /// Length of the binary encoding of a credential
+    pub const LENGTH: usize = 4 * POINT_LENGTH + 2 * TRANSCRIPT_LENGTH + U64_LENGTH;
+
+    /// Encodes this credential as compressed points and canonical scalars
+    pub fn to_bytes(&self) -> [u8; Self::LENGTH] {
+        let mut bytes = [0; Self::LENGTH];
+        let mut w = Writer::new(&mut bytes);
+        w.point(&self.a);
+        w.point(&self.b);
+        w.point(&self.A);
+        w.point(&self.B);
+        w.transcript(&self.T1);
+        w.transcript(&self.T2);
+        w.u64(self.epoch);
+        bytes
+    }
+
+    /// Decodes a credential from compressed points and canonical scalars
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        let mut r = Reader::new(bytes);
+        let cred = Self {
+            a: r.point()?,
+            b: r.point()?,
+            A: r.point()?,
+            B: r.point()?,
+            T1: r.transcript()?,
+            T2: r.transcript()?,
+            epoch: r.u64()?,
+        };
+        r.finish()?;
+        Ok(cred)
+    }
 }
 
 impl Org {
     /// Initializes a new organization with the given secret key
     pub fn new(sk: OrgSecretKey) -> Self {
-        Self {
-            pk: sk.to_public(),
-            sk,
+        Self::builder().secret_key(sk).assemble()
+    }
+
+    /// Starts building an organization
+    pub fn builder() -> OrgBuilder {
+        OrgBuilder {
+            sk: None,
+            version: WIRE_VERSION,
+            #[cfg(feature = "std")]
+            rng: None,
         }
     }
 
+    /// Forks a source of randomness for one run of a protocol
+    #[cfg(feature = "std")]
+    fn rng(&self) -> merlin::TranscriptRng {
+        fork_rng(&self.rng)
+    }
+
     /// Gets this organization's public key
     pub fn public_key(&self) -> OrgPublicKey {
         self.pk
     }
+
+    /// Gets this organization's list of revoked pseudonyms
+    pub fn revocation_list(&self) -> &RevocationList {
+        &self.revoked
+    }
+
+    /// Revokes a pseudonym, so that it can no longer be authenticated with this organization
+    pub fn revoke(&mut self, nym: Nym) {
+        self.revoked.revoke(nym)
+    }
+
+    /// Sets a hook that is called with a record of each credential this organization issues
+    #[cfg(feature = "std")]
+    pub fn set_issuance_audit(&mut self, hook: impl Fn(&IssuanceRecord) + Send + Sync + 'static) {
+        self.audit = Some(Box::new(hook));
+    }
 }
 
 impl User {
     /// Initializes a new user with the given secret key
+    ///
+    /// The user draws its randomness from the operating system, so this needs the `getrandom`
+    /// feature; otherwise, give it a source of randomness with [`UserBuilder::rng`].
+    #[cfg(feature = "getrandom")]
     pub fn new(sk: UserSecretKey) -> Self {
-        Self {
-            pk: sk.to_public(),
-            sk,
+        Self::builder().secret_key(sk).assemble()
+    }
+
+    /// Starts building a user
+    pub fn builder() -> UserBuilder {
+        UserBuilder {
+            sk: None,
+            version: WIRE_VERSION,
+            rng: None,
         }
     }
 
+    /// Forks a source of randomness for one run of a protocol
+    fn rng(&self) -> merlin::TranscriptRng {
+        fork_rng(&self.rng)
+    }
+
     /// Gets this user's public key
     pub fn public_key(&self) -> UserPublicKey {
         self.pk
     }
 }
 
+/// Forks a source of randomness for one run of a protocol from the one given to a builder, or from
+/// the operating system if none was
+///
+/// The shared source is only locked while seeding the fork, so protocols running concurrently
+/// never hold the lock across an await.
+fn fork_rng(source: &Option<SharedRng>) -> merlin::TranscriptRng {
+    let builder = merlin::Transcript::new(b"nym/0.1/rng").build_rng();
+    let Some(source) = source else {
+        #[cfg(feature = "getrandom")]
+        return builder.finalize(&mut rng());
+        #[cfg(not(feature = "getrandom"))]
+        unreachable!("builders should require a source of randomness without getrandom");
+    };
+    #[cfg(feature = "std")]
+    let mut source = source.lock().unwrap();
+    #[cfg(not(feature = "std"))]
+    let mut source = source.borrow_mut();
+    builder.finalize(&mut &mut **source)
+}
+
+impl OrgBuilder {
+    /// Sets the organization's secret key
+    pub fn secret_key(mut self, sk: OrgSecretKey) -> Self {
+        self.sk = Some(sk);
+        self
+    }
+
+    /// Sets the organization's source of randomness
+    ///
+    /// This generates the secret key, unless one is set with [`OrgBuilder::secret_key`], and
+    /// seeds the nonces of the proofs and every other random value the protocols draw. Without
+    /// it, those come from the operating system.
+    pub fn rng<R: CryptoRngCore + Send + 'static>(mut self, mut rng: R) -> Self {
+        self.sk
+            .get_or_insert_with(|| OrgSecretKey::random(&mut rng));
+        #[cfg(feature = "std")]
+        {
+            self.rng = Some(Box::new(rng));
+        }
+        self
+    }
+
+    /// Sets the protocol version announced to users when generating nyms
+    ///
+    /// This defaults to [`WIRE_VERSION`], the only version this crate speaks, and building fails
+    /// with [`Error::UnsupportedVersion`] for any other.
+    pub fn protocol_version(mut self, version: u32) -> Self {
+        self.version = version;
+        self
+    }
+
+    /// Builds the organization, with a random secret key if none was set
+    ///
+    /// Without the `getrandom` feature, this fails with [`Error::MissingRng`] unless a secret key
+    /// or a source of randomness was set.
+    pub fn build(self) -> Result<Org> {
+        if self.version != WIRE_VERSION {
+            return Err(Error::UnsupportedVersion(self.version));
+        }
+        #[cfg(not(feature = "getrandom"))]
+        if self.sk.is_none() {
+            return Err(Error::MissingRng);
+        }
+        Ok(self.assemble())
+    }
+
+    fn assemble(self) -> Org {
+        #[cfg(feature = "getrandom")]
+        let sk = self.sk.unwrap_or_else(|| OrgSecretKey::random(&mut rng()));
+        #[cfg(not(feature = "getrandom"))]
+        let sk = self.sk.expect("a secret key should have been set");
+        Org {
+            pk: sk.to_public(),
+            sk,
+            revoked: RevocationList::new(),
+            #[cfg(feature = "std")]
+            audit: None,
+            #[cfg(feature = "std")]
+            version: self.version,
+            #[cfg(feature = "std")]
+            nonces: Mutex::new(VecDeque::new()),
+            #[cfg(feature = "std")]
+            rng: self.rng.map(SharedRng::new),
+        }
+    }
+}
+
+impl UserBuilder {
+    /// Sets the user's secret key
+    pub fn secret_key(mut self, sk: UserSecretKey) -> Self {
+        self.sk = Some(sk);
+        self
+    }
+
+    /// Sets the user's source of randomness
+    ///
+    /// This generates the secret key, unless one is set with [`UserBuilder::secret_key`], and
+    /// seeds the nonces of the proofs and every other random value the protocols draw. Without
+    /// it, those come from the operating system, which needs the `getrandom` feature.
+    pub fn rng<R: CryptoRngCore + Send + 'static>(mut self, mut rng: R) -> Self {
+        self.sk
+            .get_or_insert_with(|| UserSecretKey::random(&mut rng));
+        self.rng = Some(Box::new(rng));
+        self
+    }
+
+    /// Sets the protocol version announced to organizations when generating nyms
+    ///
+    /// This defaults to [`WIRE_VERSION`], the only version this crate speaks, and building fails
+    /// with [`Error::UnsupportedVersion`] for any other.
+    pub fn protocol_version(mut self, version: u32) -> Self {
+        self.version = version;
+        self
+    }
+
+    /// Builds the user, with a random secret key if none was set
+    ///
+    /// Without the `getrandom` feature, this fails with [`Error::MissingRng`] unless a source of
+    /// randomness was set.
+    pub fn build(self) -> Result<User> {
+        if self.version != WIRE_VERSION {
+            return Err(Error::UnsupportedVersion(self.version));
+        }
+        #[cfg(not(feature = "getrandom"))]
+        if self.rng.is_none() {
+            return Err(Error::MissingRng);
+        }
+        Ok(self.assemble())
+    }
+
+    fn assemble(self) -> User {
+        #[cfg(feature = "getrandom")]
+        let sk = self.sk.unwrap_or_else(|| UserSecretKey::random(&mut rng()));
+        #[cfg(not(feature = "getrandom"))]
+        let sk = self
+            .sk
+            .expect("the source of randomness should have generated a key");
+        User {
+            pk: sk.to_public(),
+            sk,
+            #[cfg(feature = "std")]
+            version: self.version,
+            rng: self.rng.map(SharedRng::new),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
 impl Org {
     /// Generates a pseudonym
+    ///
+    /// This fails with [`Error::VersionMismatch`] if the user speaks a different
+    /// [`WIRE_VERSION`](crate::proof::WIRE_VERSION).
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(name = "org::generate_nym", skip_all)
+    )]
     pub async fn generate_nym<T: LocalTransport>(&self, user: &mut T) -> Result<Nym> {
-        let a_ = user.receive(b"a~").await?;
-        let b_ = user.receive(b"b~").await?;
-        let r = Scalar::random(&mut thread_rng());
+        let mut user = Guarded::new(user, "org::generate_nym");
+        let nym = self.exchange_nym(&mut user).await;
+        user.finish();
+        nym
+    }
+
+    async fn exchange_nym<T: LocalTransport>(&self, user: &mut T) -> Result<Nym> {
+        negotiate_version(user, self.version).await?;
+        let a_ = user.receive(labels::nym::USER_BASE).await?;
+        let b_ = user.receive(labels::nym::USER_POINT).await?;
+        ensure_not_identity(&[&a_, &b_])?;
+        let r = self.nym_factor(&a_, &b_);
         let a = r * a_;
-        user.send(b"a", a).await?;
-        let b: RistrettoPoint = user.receive(b"b").await?;
+        user.send(labels::nym::BASE, a).await?;
+        let b: RistrettoPoint = user.receive(labels::nym::POINT).await?;
+        ensure_not_identity(&[&b])?;
         dlog_eq::verify(
             user,
             Publics {
@@ -120,24 +811,46 @@ impl Org {
         Ok(Nym { a, b })
     }
 
+    /// Derives the factor that a user base is multiplied by to get a nym
+    ///
+    /// This depends on the organization's secret key and on the user base and point, so a user
+    /// that presents the same ones again, as from [`User::deterministic_nym`], gets the same nym
+    /// back. Fresh user bases get unrelated factors, as if drawn at random.
+    fn nym_factor(&self, a_: &RistrettoPoint, b_: &RistrettoPoint) -> Scalar {
+        let mut t = merlin::Transcript::new(b"nym/0.1/nym-factor");
+        for key in &self.sk.keys {
+            t.commit(b"sk", key.exponent());
+        }
+        t.commit(b"a~", a_);
+        t.commit(b"b~", b_);
+        t.challenge(b"r")
+    }
+
+    /// Generates a pseudonym with each of several users concurrently
+    pub async fn generate_nyms<T: LocalTransport>(&self, users: &mut [T]) -> Result<Vec<Nym>> {
+        try_join_all(users.iter_mut().map(|user| self.generate_nym(user))).await
+    }
+
     /// Generates a pseudonym as the CA
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(name = "org::generate_nym_as_ca", skip_all)
+    )]
     pub async fn generate_nym_as_ca<T: LocalTransport>(
         &self,
         user: &mut T,
         user_key: UserPublicKey,
     ) -> Result<Nym> {
-        let a_ = user.receive(b"a~").await?;
-        let b_ = user.receive(b"b~").await?;
-        if a_ != RISTRETTO_BASEPOINT_POINT {
-            return Err(Error::BadProof);
-        }
-        if b_ != user_key.point() {
-            return Err(Error::BadProof);
-        }
-        let r = Scalar::random(&mut thread_rng());
+        negotiate_version(user, self.version).await?;
+        let a_ = user.receive(labels::nym::USER_BASE).await?;
+        let b_ = user.receive(labels::nym::USER_POINT).await?;
+        ensure(a_ == RISTRETTO_BASEPOINT_POINT, ProofStage::UnexpectedBase)?;
+        ensure(b_ == user_key.point(), ProofStage::UnexpectedKey)?;
+        let r = Scalar::random(&mut self.rng());
         let a = r * a_;
-        user.send(b"a", a).await?;
-        let b: RistrettoPoint = user.receive(b"b").await?;
+        user.send(labels::nym::BASE, a).await?;
+        let b: RistrettoPoint = user.receive(labels::nym::POINT).await?;
+        ensure_not_identity(&[&b])?;
         dlog_eq::verify(
             user,
             Publics {
@@ -150,17 +863,98 @@ impl Org {
         .await?;
         Ok(Nym { a, b })
     }
+
+    /// Generates a pseudonym as the CA, after the user proves ownership of their key
+    ///
+    /// [`Org::generate_nym_as_ca`] trusts that `user_key` was authenticated elsewhere. This first
+    /// has the user prove ownership of it over the same transport, and fails with
+    /// [`Error::BadProof`] if they can't. The user runs
+    /// [`User::generate_nym_with_ca_authenticated`].
+    pub async fn generate_nym_as_ca_authenticated<T: LocalTransport>(
+        &self,
+        user: &mut T,
+        user_key: UserPublicKey,
+    ) -> Result<Nym> {
+        user_key.verify_ownership(user).await?;
+        self.generate_nym_as_ca(user, user_key).await
+    }
+}
+
+impl User {
+    /// Derives the blinding factor and the base of a pseudonym deterministically, scoped to an
+    /// organization
+    ///
+    /// This returns `γ`, the user base `γ·G` and the user point `x·γ·G` that
+    /// [`User::generate_nym`] otherwise draws at random, derived from the user's secret key and
+    /// the organization's public key. The same user always regenerates the same nym base with the
+    /// same organization, and as the organization derives its side of the nym from that base,
+    /// [`User::generate_deterministic_nym`] gets the same nym back each time, so an identity can be
+    /// recovered after losing its data.
+    ///
+    /// The flip side is that re-enrollments with the organization are linkable: it sees the same
+    /// user base each time. Different organizations still get unrelated bases.
+    pub fn deterministic_nym(
+        &self,
+        org_pk: &OrgPublicKey,
+    ) -> (Scalar, RistrettoPoint, RistrettoPoint) {
+        let mut t = merlin::Transcript::new(b"nym/0.1/deterministic-nym");
+        t.commit(b"sk", self.sk.key.exponent());
+        t.commit(b"org", &org_pk.points());
+        let γ: Scalar = t.challenge(b"gamma");
+        let a_ = &γ * RISTRETTO_BASEPOINT_TABLE;
+        let b_ = self.sk.key.exponent() * a_;
+        (γ, a_, b_)
+    }
 }
 
+#[cfg(feature = "std")]
 impl User {
     /// Generates a pseudonym
     pub async fn generate_nym<T: LocalTransport>(&self, org: &mut T) -> Result<Nym> {
-        let γ = Scalar::random(&mut thread_rng());
-        let a_ = γ * RISTRETTO_BASEPOINT_POINT;
+        let γ = Scalar::random(&mut self.rng());
+        let a_ = &γ * RISTRETTO_BASEPOINT_TABLE;
         let b_ = self.sk.key.exponent() * a_;
         self.generate_nym_impl(org, a_, b_).await
     }
 
+    /// Generates a pseudonym for a given domain
+    ///
+    /// The user base is derived from the user's secret key, the organization's public key and
+    /// `domain`, as in [`User::deterministic_nym`], so the same user always presents the same user
+    /// point to an organization for the same domain, and unrelated ones for different domains or
+    /// organizations. This lets a user hold several pseudonyms with an organization, one for each
+    /// context it interacts in, that the organization cannot link to each other, while it can tell
+    /// when the user comes back in the same context. Organizations that see the same domain still
+    /// cannot link the user through it. The organization runs [`Org::generate_nym`] as usual.
+    pub async fn generate_nym_tagged<T: LocalTransport>(
+        &self,
+        org: &mut T,
+        org_pk: &OrgPublicKey,
+        domain: &[u8],
+    ) -> Result<Nym> {
+        let mut t = merlin::Transcript::new(b"nym/0.1/tagged-nym");
+        t.commit(b"sk", self.sk.key.exponent());
+        t.commit(b"org", &org_pk.points());
+        t.commit(b"domain", domain);
+        let γ: Scalar = t.challenge(b"gamma");
+        let a_ = &γ * RISTRETTO_BASEPOINT_TABLE;
+        let b_ = self.sk.key.exponent() * a_;
+        self.generate_nym_impl(org, a_, b_).await
+    }
+
+    /// Generates a pseudonym from the base of [`User::deterministic_nym`]
+    ///
+    /// The organization runs [`Org::generate_nym`] as usual, and the nym is the same every time
+    /// this runs with the same organization.
+    pub async fn generate_deterministic_nym<T: LocalTransport>(
+        &self,
+        org: &mut T,
+        org_pk: &OrgPublicKey,
+    ) -> Result<Nym> {
+        let (_, a_, b_) = self.deterministic_nym(org_pk);
+        self.generate_nym_impl(org, a_, b_).await
+    }
+
     /// Generates a pseudonym with a CA
     pub async fn generate_nym_with_ca<T: LocalTransport>(&self, org: &mut T) -> Result<Nym> {
         let a_ = RISTRETTO_BASEPOINT_POINT;
@@ -168,18 +962,51 @@ impl User {
         self.generate_nym_impl(org, a_, b_).await
     }
 
+    /// Generates a pseudonym with a CA, after proving ownership of the user's key, paired with
+    /// [`Org::generate_nym_as_ca_authenticated`]
+    pub async fn generate_nym_with_ca_authenticated<T: LocalTransport>(
+        &self,
+        org: &mut T,
+    ) -> Result<Nym> {
+        self.sk.prove_ownership(org).await?;
+        self.generate_nym_with_ca(org).await
+    }
+
+    /// Generates a pseudonym with each of several organizations concurrently
+    pub async fn generate_nyms<T: LocalTransport>(&self, orgs: &mut [T]) -> Result<Vec<Nym>> {
+        try_join_all(orgs.iter_mut().map(|org| self.generate_nym(org))).await
+    }
+
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(name = "user::generate_nym", skip_all)
+    )]
     async fn generate_nym_impl<T: LocalTransport>(
         &self,
         org: &mut T,
         a_: RistrettoPoint,
         b_: RistrettoPoint,
     ) -> Result<Nym> {
-        org.send(b"a~", a_).await?;
-        org.send(b"b~", b_).await?;
-        let a = org.receive(b"a").await?;
+        let mut org = Guarded::new(org, "user::generate_nym");
+        let nym = self.exchange_nym(&mut org, a_, b_).await;
+        org.finish();
+        nym
+    }
+
+    async fn exchange_nym<T: LocalTransport>(
+        &self,
+        org: &mut T,
+        a_: RistrettoPoint,
+        b_: RistrettoPoint,
+    ) -> Result<Nym> {
+        negotiate_version(org, self.version).await?;
+        org.send(labels::nym::USER_BASE, a_).await?;
+        org.send(labels::nym::USER_POINT, b_).await?;
+        org.flush().await?;
+        let a = org.receive(labels::nym::BASE).await?;
         let b = self.sk.key.exponent() * a;
-        org.send(b"b", b).await?;
-        dlog_eq::prove(
+        org.send(labels::nym::POINT, b).await?;
+        dlog_eq::prove_with_rng(
             org,
             Publics {
                 g1: &a,
@@ -187,157 +1014,771 @@ impl User {
                 g2: &a_,
                 h2: &b_,
             },
-            ProverSecrets {
+            Secrets {
                 x: self.sk.key.exponent(),
             },
+            &mut self.rng(),
         )
         .await?;
         Ok(Nym { a, b })
     }
 }
 
+/// Exchanges wire versions with the peer, failing if they differ
+#[cfg(feature = "std")]
+async fn negotiate_version<T: LocalTransport>(peer: &mut T, ours: u32) -> Result {
+    peer.send(labels::nym::VERSION, ours).await?;
+    let theirs: u32 = peer.receive(labels::nym::VERSION).await?;
+    if theirs != ours {
+        return Err(Error::VersionMismatch { ours, theirs });
+    }
+    Ok(())
+}
+
+/// Number of recent nonces an organization remembers, to detect replayed authentications
+#[cfg(feature = "std")]
+pub const NONCE_WINDOW: usize = 1024;
+
+#[cfg(feature = "std")]
 impl Org {
     /// Authenticates a user as the holder of a given nym
+    ///
+    /// The user's proof is bound to a fresh nonce, so a transcript from an earlier session cannot
+    /// be replayed. A proof for one of the last [`NONCE_WINDOW`] nonces sent by this organization
+    /// fails with [`Error::StaleNonce`].
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(name = "org::authenticate_nym", skip_all)
+    )]
     pub async fn authenticate_nym<T: LocalTransport>(&self, user: &mut T, nym: Nym) -> Result {
-        dlog_eq::verify(
-            user,
+        if self.revoked.is_revoked(&nym) {
+            return Err(Error::Revoked);
+        }
+        let nonce = self.fresh_nonce();
+        user.send(labels::auth::NONCE, nonce).await?;
+        let (answered, proof): ([u8; 32], Transcript) = user.receive(labels::auth::PROOF).await?;
+        if answered != nonce && self.nonces.lock().unwrap().contains(&answered) {
+            return Err(Error::StaleNonce);
+        }
+        proof.verify_in_context(
             Publics {
                 g1: &nym.a,
                 h1: &nym.b,
                 g2: &nym.a,
                 h2: &nym.b,
             },
+            &nonce,
         )
-        .await?;
-        Ok(())
+    }
+
+    fn fresh_nonce(&self) -> [u8; 32] {
+        let mut nonce = [0; 32];
+        self.rng().fill_bytes(&mut nonce);
+        let mut nonces = self.nonces.lock().unwrap();
+        if nonces.len() == NONCE_WINDOW {
+            nonces.pop_front();
+        }
+        nonces.push_back(nonce);
+        nonce
     }
 }
 
+#[cfg(feature = "std")]
 impl User {
     /// Authenticates this user as the holder of a given nym
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(name = "user::authenticate_nym", skip_all)
+    )]
     pub async fn authenticate_nym<T: LocalTransport>(&self, org: &mut T, nym: Nym) -> Result {
-        dlog_eq::prove(
-            org,
+        let nonce: [u8; 32] = org.receive(labels::auth::NONCE).await?;
+        let proof = dlog_eq::prove_noninteractive_in_context_with_rng(
             Publics {
                 g1: &nym.a,
                 h1: &nym.b,
                 g2: &nym.a,
                 h2: &nym.b,
             },
-            ProverSecrets {
+            Secrets {
                 x: self.sk.key.exponent(),
             },
-        )
-        .await?;
+            &nonce,
+            &mut self.rng(),
+        );
+        org.send(labels::auth::PROOF, (nonce, proof)).await?;
+        org.flush().await?;
         Ok(())
     }
 }
 
-impl Org {
-    /// Issues a new credential for a given nym
-    #[allow(non_snake_case)]
-    pub async fn issue_credential<T: LocalTransport>(&self, user: &mut T, nym: Nym) -> Result {
-        let A = self.sk.key2.exponent() * nym.b;
-        let B = self.sk.key1.exponent() * (nym.a + self.sk.key2.exponent() * nym.b);
-        user.send(b"A", A).await?;
-        user.send(b"B", B).await?;
+impl User {
+    /// Re-randomizes a nym into one that cannot be linked to it, along with the blinding factor
+    ///
+    /// The new nym is held with the same key, so it can be authenticated and signed with as usual.
+    /// Use [`User::prove_same_holder`] to link the two nyms when needed.
+    pub fn rerandomize_nym(&self, nym: &Nym) -> (Nym, Scalar) {
+        let γ = Scalar::random(&mut self.rng());
+        let rerandomized = Nym {
+            a: γ * nym.a,
+            b: γ * nym.b,
+        };
+        (rerandomized, γ)
+    }
 
-        blind_dlog_eq::prove(
-            user,
-            Publics {
-                g1: &RISTRETTO_BASEPOINT_POINT,
-                h1: self.pk.points().1,
-                g2: &nym.b,
-                h2: &A,
-            },
-            ProverSecrets {
-                x: self.sk.key2.exponent(),
+    /// Proves that two nyms are held with the same key, without interaction
+    pub fn prove_same_holder(&self, original: &Nym, nym: &Nym) -> Transcript {
+        dlog_eq::prove_noninteractive_with_rng(
+            same_holder_publics(original, nym),
+            Secrets {
+                x: self.sk.key.exponent(),
             },
+            &mut self.rng(),
         )
-        .await?;
-        blind_dlog_eq::prove(
-            user,
-            Publics {
-                g1: &RISTRETTO_BASEPOINT_POINT,
-                h1: self.pk.points().0,
-                g2: &(nym.a + A),
-                h2: &B,
-            },
-            ProverSecrets {
-                x: self.sk.key1.exponent(),
+    }
+}
+
+impl Nym {
+    /// Verifies a proof that this nym is held with the same key as another
+    pub fn verify_same_holder(&self, original: &Nym, proof: &Transcript) -> Result {
+        proof.verify(same_holder_publics(original, self))
+    }
+}
+
+/// Builds the public parameters for proving that two nyms are held with the same key
+fn same_holder_publics<'a>(original: &'a Nym, nym: &'a Nym) -> Publics<'a> {
+    Publics {
+        g1: &original.a,
+        h1: &original.b,
+        g2: &nym.a,
+        h2: &nym.b,
+    }
+}
+
+/// Builds the public parameters for proving that a nym generated with a CA is held with a given
+/// user key
+#[cfg(feature = "std")]
+fn ca_nym_publics(nym: &Nym, user_key: &UserPublicKey) -> OwnedPublics {
+    OwnedPublics {
+        g1: nym.a,
+        h1: nym.b,
+        g2: RISTRETTO_BASEPOINT_POINT,
+        h2: user_key.point(),
+    }
+}
+
+#[cfg(feature = "std")]
+impl User {
+    /// Proves to an organization that two nyms, e.g. held with different organizations, belong to
+    /// this user
+    ///
+    /// This gives up the unlinkability of the two nyms to whoever runs [`Org::verify_link`], so it
+    /// should only be run when the user explicitly agrees to it. The proof is interactive, so the
+    /// verifier can't pass it on: it convinces nobody else that the nyms are linked.
+    pub async fn link_nyms<T: LocalTransport>(&self, org: &mut T, nym1: Nym, nym2: Nym) -> Result {
+        dlog_eq::prove_with_rng(
+            org,
+            same_holder_publics(&nym1, &nym2),
+            Secrets {
+                x: self.sk.key.exponent(),
             },
+            &mut self.rng(),
         )
-        .await?;
+        .await
+    }
+}
+
+#[cfg(feature = "std")]
+impl Org {
+    /// Verifies that two nyms belong to the same user, paired with [`User::link_nyms`]
+    pub async fn verify_link<T: LocalTransport>(
+        &self,
+        user: &mut T,
+        nym1: Nym,
+        nym2: Nym,
+    ) -> Result {
+        dlog_eq::verify(user, same_holder_publics(&nym1, &nym2)).await
+    }
+}
+
+#[cfg(feature = "std")]
+impl Org {
+    /// Issues a new credential for a given nym, valid for a given epoch
+    pub async fn issue_credential<T: LocalTransport>(
+        &self,
+        user: &mut T,
+        nym: Nym,
+        epoch: u64,
+    ) -> Result {
+        let y = self.sk.epoch_exponent(epoch);
+        self.issue(user, nym, &y, &self.pk.epoch_point(epoch), epoch)
+            .await
+    }
+
+    /// Issues a new credential for a nym generated with a CA, valid for a given epoch
+    ///
+    /// The nym may come from [`Org::generate_nym_as_ca`] run by another organization, so this
+    /// organization never saw it being generated. Before issuing, the user proves that the nym's
+    /// point has the same discrete logarithm to its base as `user_key` has to the basepoint, i.e.
+    /// that the nym belongs to the holder of the registered key. This fails with
+    /// [`Error::UnknownNym`] if they can't. The user's side is
+    /// [`User::issue_credential_for_ca_nym`].
+    pub async fn issue_credential_for_ca_nym<T: LocalTransport>(
+        &self,
+        user: &mut T,
+        nym: Nym,
+        user_key: UserPublicKey,
+        epoch: u64,
+    ) -> Result {
+        dlog_eq::verify(user, ca_nym_publics(&nym, &user_key).as_ref())
+            .await
+            .map_err(|e| match e {
+                Error::BadProof { .. } => Error::UnknownNym,
+                e => e,
+            })?;
+        self.issue_credential(user, nym, epoch).await
+    }
+
+    /// Issues a new credential for a given nym, valid for a given epoch, bound to a given context
+    ///
+    /// The context is a public message, such as a policy or a credential type. The organization
+    /// tweaks its key with it, as it does for attributes, so the credential only verifies under
+    /// that context, whatever the user does. It is sent to the user, whose side is
+    /// [`User::issue_credential_with_context`], and checked again on transfer with
+    /// [`Org::transfer_credential_with_context`].
+    pub async fn issue_credential_with_context<T: LocalTransport>(
+        &self,
+        user: &mut T,
+        nym: Nym,
+        epoch: u64,
+        context: &[u8],
+    ) -> Result {
+        user.send(labels::issuance::CONTEXT, context).await?;
+        let y = self.sk.context_exponent(epoch, context);
+        let y_point = self.pk.context_point(epoch, context);
+        self.issue(user, nym, &y, &y_point, epoch).await
+    }
+
+    /// Issues a new credential for a given nym, valid for a given epoch, carrying some attributes
+    pub async fn issue_credential_with_attributes<T: LocalTransport>(
+        &self,
+        user: &mut T,
+        nym: Nym,
+        epoch: u64,
+        attributes: &[Scalar],
+    ) -> Result {
+        self.issue_credential(user, nym, epoch).await?;
+        for (index, value) in attributes.iter().enumerate() {
+            let blinding = Scalar::random(&mut self.rng());
+            user.send(labels::issuance::OPENING, blinding).await?;
+            let commitment = range::commit_scalar(value, &blinding);
+            let y = self.sk.attribute_exponent(epoch, index, &commitment);
+            let y_point = self.pk.attribute_point(epoch, index, &commitment);
+            self.issue(user, nym, &y, &y_point, epoch).await?;
+        }
+        Ok(())
+    }
+
+    /// Issues a new credential for a given nym, valid for a given epoch, carrying some integer
+    /// attributes committed to at issuance
+    ///
+    /// The user's side is [`User::issue_credential_with_range_attributes`].
+    pub async fn issue_credential_with_range_attributes<T: LocalTransport>(
+        &self,
+        user: &mut T,
+        nym: Nym,
+        epoch: u64,
+        attributes: &[u64],
+    ) -> Result {
+        self.issue_credential(user, nym, epoch).await?;
+        for (index, value) in attributes.iter().enumerate() {
+            let blinding = Scalar::random(&mut self.rng());
+            user.send(labels::issuance::OPENING, blinding).await?;
+            let commitment = range::commit(*value, &blinding);
+            let y = self.sk.range_exponent(epoch, index, &commitment);
+            let y_point = self.pk.range_point(epoch, index, &commitment);
+            self.issue(user, nym, &y, &y_point, epoch).await?;
+        }
+        Ok(())
+    }
+
+    /// Computes the credential points for several nyms at once, valid for a given epoch
+    ///
+    /// Each nym gets its own fresh base, as in [`Org::issue_credential`], and the points on it are
+    /// computed over rayon's thread pool. The interactive proofs still need to run with each user,
+    /// through [`Org::issue_prepared_credential`].
+    #[cfg(feature = "rayon")]
+    pub fn issue_credentials_parallel(&self, nyms: &[Nym], epoch: u64) -> Vec<PreparedCredential> {
+        use rayon::prelude::*;
+
+        let x = self.sk.issuer_exponent(epoch);
+        let y = self.sk.epoch_exponent(epoch);
+        nyms.par_iter()
+            .map(|nym| self.prepare_credential(*nym, &x, &y, epoch))
+            .collect()
+    }
+
+    /// Issues a credential computed by [`Org::issue_credentials_parallel`]
+    ///
+    /// The user's side is [`User::issue_credential`], as for [`Org::issue_credential`].
+    #[cfg(feature = "rayon")]
+    pub async fn issue_prepared_credential<T: LocalTransport>(
+        &self,
+        user: &mut T,
+        prepared: PreparedCredential,
+    ) -> Result {
+        let y = self.sk.epoch_exponent(prepared.epoch);
+        let y_point = self.pk.epoch_point(prepared.epoch);
+        self.issue_prepared(user, prepared, &y, &y_point).await
+    }
+
+    /// Computes the points of a credential for a nym on a fresh base, with given exponents for
+    /// both sub-keys
+    #[allow(non_snake_case)]
+    fn prepare_credential(
+        &self,
+        nym: Nym,
+        x: &Scalar,
+        y: &Scalar,
+        epoch: u64,
+    ) -> PreparedCredential {
+        let ρ = Scalar::random(&mut self.rng());
+        let base = Nym {
+            a: ρ * nym.a,
+            b: ρ * nym.b,
+        };
+        let A = y * base.b;
+        let B = x * (base.a + A);
+        PreparedCredential {
+            nym,
+            base,
+            A,
+            B,
+            epoch,
+        }
+    }
+
+    /// Issues a credential with a given exponent for the second sub-key
+    ///
+    /// Each credential gets a fresh base `ρ·a`, for a random `ρ`. Two credentials on the same base
+    /// under tweaks `t₁` and `t₂` of the second sub-key have `B₂ - B₁ = x(t₂ - t₁)·b`, which
+    /// reveals `x·b` and with it a credential for any other tweak in the same epoch, be it another
+    /// attribute or context. The first sub-key is tweaked per epoch too, so this does not carry
+    /// over to other epochs. The user derives `ρ·b` from the base with its own exponent, which is
+    /// why it checks that the nym is its own before issuance.
+    async fn issue<T: LocalTransport>(
+        &self,
+        user: &mut T,
+        nym: Nym,
+        y: &Scalar,
+        y_point: &RistrettoPoint,
+        epoch: u64,
+    ) -> Result {
+        let x = self.sk.issuer_exponent(epoch);
+        let prepared = self.prepare_credential(nym, &x, y, epoch);
+        self.issue_prepared(user, prepared, y, y_point).await
+    }
+
+    /// Sends the points of a prepared credential and proves them, with a given exponent for the
+    /// second sub-key
+    #[allow(non_snake_case)]
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            name = "org::issue_credential",
+            skip_all,
+            fields(epoch = prepared.epoch)
+        )
+    )]
+    async fn issue_prepared<T: LocalTransport>(
+        &self,
+        user: &mut T,
+        prepared: PreparedCredential,
+        y: &Scalar,
+        y_point: &RistrettoPoint,
+    ) -> Result {
+        let PreparedCredential {
+            nym,
+            base: Nym { a, b },
+            A,
+            B,
+            epoch,
+        } = prepared;
+        let x = self.sk.issuer_exponent(epoch);
+        user.send(labels::issuance::BASE, a).await?;
+        user.send(labels::issuance::A, A).await?;
+        user.send(labels::issuance::B, B).await?;
+
+        let publics = [
+            OwnedPublics {
+                g1: RISTRETTO_BASEPOINT_POINT,
+                h1: *y_point,
+                g2: b,
+                h2: A,
+            },
+            OwnedPublics {
+                g1: RISTRETTO_BASEPOINT_POINT,
+                h1: self.pk.issuer_point(epoch),
+                g2: a + A,
+                h2: B,
+            },
+        ];
+        let mut rng = self.rng();
+        let (y_publics, x_publics) = (publics[0].as_ref(), publics[1].as_ref());
+        blind_dlog_eq::prove_with_rng(user, y_publics, ProverSecrets { x: y }, &mut rng).await?;
+        blind_dlog_eq::prove_with_rng(user, x_publics, ProverSecrets { x: &x }, &mut rng).await?;
+        if let Some(audit) = &self.audit {
+            audit(&IssuanceRecord {
+                nym,
+                epoch,
+                publics,
+            });
+        }
         Ok(())
     }
 }
 
+#[cfg(feature = "std")]
 impl User {
-    /// Issues a new credential for a given nym
-    #[allow(non_snake_case)]
+    /// Issues a new credential for a given nym, valid for a given epoch
     pub async fn issue_credential<T: LocalTransport>(
         &self,
         org: &mut T,
         nym: Nym,
         source_key: OrgPublicKey,
+        epoch: u64,
+    ) -> Result<Cred> {
+        let (cred, _) = self
+            .issue_credential_with_blinding(org, nym, source_key, epoch)
+            .await?;
+        Ok(cred)
+    }
+
+    /// Issues a new credential for a nym generated with a CA, valid for a given epoch
+    ///
+    /// This first proves to the organization that the nym was generated with the user's key, as
+    /// [`Org::issue_credential_for_ca_nym`] requires.
+    pub async fn issue_credential_for_ca_nym<T: LocalTransport>(
+        &self,
+        org: &mut T,
+        nym: Nym,
+        source_key: OrgPublicKey,
+        epoch: u64,
+    ) -> Result<Cred> {
+        dlog_eq::prove_with_rng(
+            org,
+            ca_nym_publics(&nym, &self.pk).as_ref(),
+            Secrets {
+                x: self.sk.key.exponent(),
+            },
+            &mut self.rng(),
+        )
+        .await?;
+        self.issue_credential(org, nym, source_key, epoch).await
+    }
+
+    /// Issues a new credential for a given nym, valid for a given epoch, along with the factor it
+    /// was blinded with
+    ///
+    /// The organization's side is [`Org::issue_credential`]. The blinding factor links the
+    /// credential to what the organization saw during issuance, so anyone holding it can tell
+    /// which issuance a showing of the credential came from.
+    pub async fn issue_credential_with_blinding<T: LocalTransport>(
+        &self,
+        org: &mut T,
+        nym: Nym,
+        source_key: OrgPublicKey,
+        epoch: u64,
+    ) -> Result<(Cred, Scalar)> {
+        ensure(
+            nym.b == self.sk.key.exponent() * nym.a,
+            ProofStage::UnexpectedKey,
+        )?;
+        self.issue(org, source_key, &source_key.epoch_point(epoch), epoch)
+            .await
+    }
+
+    /// Issues a new credential for a given nym, valid for a given epoch, bound to a given context
+    ///
+    /// The organization's side is [`Org::issue_credential_with_context`]. This fails with
+    /// [`Error::ContextMismatch`] if the organization issues under a different context.
+    pub async fn issue_credential_with_context<T: LocalTransport>(
+        &self,
+        org: &mut T,
+        nym: Nym,
+        source_key: OrgPublicKey,
+        epoch: u64,
+        context: &[u8],
     ) -> Result<Cred> {
-        let A = org.receive(b"A").await?;
-        let B = org.receive(b"B").await?;
-        let γ = &Scalar::random(&mut thread_rng());
+        ensure(
+            nym.b == self.sk.key.exponent() * nym.a,
+            ProofStage::UnexpectedKey,
+        )?;
+        let theirs: Vec<u8> = org.receive(labels::issuance::CONTEXT).await?;
+        if theirs != context {
+            return Err(Error::ContextMismatch);
+        }
+        let y_point = source_key.context_point(epoch, context);
+        let (cred, _) = self.issue(org, source_key, &y_point, epoch).await?;
+        Ok(cred)
+    }
+
+    /// Issues a new credential for a given nym, valid for a given epoch, carrying some attributes
+    ///
+    /// The organization's side is [`Org::issue_credential_with_attributes`], which picks the
+    /// blinding factors of the commitments.
+    pub async fn issue_credential_with_attributes<T: LocalTransport>(
+        &self,
+        org: &mut T,
+        nym: Nym,
+        source_key: OrgPublicKey,
+        epoch: u64,
+        attributes: &[Scalar],
+    ) -> Result<AttrCred> {
+        let cred = self.issue_credential(org, nym, source_key, epoch).await?;
+        let mut certified = Vec::with_capacity(attributes.len());
+        for (index, value) in attributes.iter().enumerate() {
+            let blinding: Scalar = org.receive(labels::issuance::OPENING).await?;
+            let commitment = range::commit_scalar(value, &blinding);
+            let y_point = source_key.attribute_point(epoch, index, &commitment);
+            let (cred, _) = self.issue(org, source_key, &y_point, epoch).await?;
+            certified.push((*value, blinding, cred));
+        }
+        Ok(AttrCred {
+            cred,
+            attributes: certified,
+        })
+    }
+
+    /// Issues a new credential for a given nym, valid for a given epoch, carrying some integer
+    /// attributes committed to at issuance
+    ///
+    /// The organization's side is [`Org::issue_credential_with_range_attributes`], which picks
+    /// the blinding factors of the commitments. Issuance fails if the organization certifies
+    /// values other than the expected ones.
+    pub async fn issue_credential_with_range_attributes<T: LocalTransport>(
+        &self,
+        org: &mut T,
+        nym: Nym,
+        source_key: OrgPublicKey,
+        epoch: u64,
+        attributes: &[u64],
+    ) -> Result<RangeCred> {
+        let cred = self.issue_credential(org, nym, source_key, epoch).await?;
+        let mut certified = Vec::with_capacity(attributes.len());
+        for (index, value) in attributes.iter().enumerate() {
+            let blinding: Scalar = org.receive(labels::issuance::OPENING).await?;
+            let commitment = range::commit(*value, &blinding);
+            let y_point = source_key.range_point(epoch, index, &commitment);
+            let (cred, _) = self.issue(org, source_key, &y_point, epoch).await?;
+            certified.push((*value, blinding, cred));
+        }
+        Ok(RangeCred {
+            cred,
+            attributes: certified,
+        })
+    }
+
+    /// Obtains a credential with a given point for the second sub-key, along with the factor it
+    /// was blinded with
+    #[allow(non_snake_case)]
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(name = "user::issue_credential", skip_all, fields(epoch = epoch))
+    )]
+    async fn issue<T: LocalTransport>(
+        &self,
+        org: &mut T,
+        source_key: OrgPublicKey,
+        y_point: &RistrettoPoint,
+        epoch: u64,
+    ) -> Result<(Cred, Scalar)> {
+        let a = org.receive(labels::issuance::BASE).await?;
+        ensure_not_identity(&[&a])?;
+        let b = self.sk.key.exponent() * a;
+        let A = org.receive(labels::issuance::A).await?;
+        let B = org.receive(labels::issuance::B).await?;
+        let γ = Scalar::random(&mut self.rng());
         let T1 = blind_dlog_eq::verify(
             org,
             Publics {
                 g1: &RISTRETTO_BASEPOINT_POINT,
-                h1: source_key.points().1,
-                g2: &nym.b,
+                h1: y_point,
+                g2: &b,
                 h2: &A,
             },
-            VerifierSecrets { γ },
+            VerifierSecrets { γ: &γ },
         )
         .await?;
         let T2 = blind_dlog_eq::verify(
             org,
             Publics {
                 g1: &RISTRETTO_BASEPOINT_POINT,
-                h1: source_key.points().0,
-                g2: &(nym.a + A),
+                h1: &source_key.issuer_point(epoch),
+                g2: &(a + A),
                 h2: &B,
             },
-            VerifierSecrets { γ },
+            VerifierSecrets { γ: &γ },
         )
         .await?;
-        Ok(Cred {
-            a: nym.a * γ,
-            b: nym.b * γ,
+        let cred = Cred {
+            a: a * γ,
+            b: b * γ,
             A: A * γ,
             B: B * γ,
             T1,
             T2,
-        })
+            epoch,
+        };
+        Ok((cred, γ))
+    }
+}
+
+/// Context that binds a proof of holding a credential to the epoch it is refreshed for
+///
+/// The epoch is big-endian, as are the integers that [`Transcribe`](crate::hash::Transcribe)
+/// commits.
+#[cfg(feature = "std")]
+fn refresh_context(epoch: u64) -> Vec<u8> {
+    [b"refresh" as &[u8], &epoch.to_be_bytes()].concat()
+}
+
+#[cfg(feature = "std")]
+impl Org {
+    /// Refreshes a credential issued by this organization, for a new epoch
+    ///
+    /// The user proves they hold a credential from this organization, bound to the new epoch, and
+    /// gets a new one for it, as with [`Org::issue_credential`], without generating a new nym.
+    /// Credentials issued for an epoch before `oldest`, or after `epoch`, are rejected as expired.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(name = "org::accept_refresh", skip_all, fields(epoch = epoch))
+    )]
+    pub async fn accept_refresh<T: LocalTransport>(
+        &self,
+        user: &mut T,
+        oldest: u64,
+        epoch: u64,
+    ) -> Result {
+        let (cred, proof): (Cred, Transcript) = user.receive(labels::refresh::CREDENTIAL).await?;
+        if !(oldest..=epoch).contains(&cred.epoch) {
+            return Err(Error::Expired);
+        }
+        cred.verify_against(&self.pk)?;
+        proof.verify_in_context(
+            Publics {
+                g1: &cred.a,
+                h1: &cred.b,
+                g2: &cred.a,
+                h2: &cred.b,
+            },
+            &refresh_context(epoch),
+        )?;
+        let nym = Nym {
+            a: cred.a,
+            b: cred.b,
+        };
+        self.issue_credential(user, nym, epoch).await
+    }
+}
+
+#[cfg(feature = "std")]
+impl User {
+    /// Refreshes a credential for a new epoch, with the organization that issued it
+    ///
+    /// The organization's side is [`Org::accept_refresh`]. The new credential is blinded afresh,
+    /// so it cannot be linked to the one it replaces.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(name = "user::refresh_credential", skip_all, fields(epoch = epoch))
+    )]
+    pub async fn refresh_credential<T: LocalTransport>(
+        &self,
+        org: &mut T,
+        cred: Cred,
+        source_key: OrgPublicKey,
+        epoch: u64,
+    ) -> Result<Cred> {
+        ensure(
+            cred.b == self.sk.key.exponent() * cred.a,
+            ProofStage::UnexpectedKey,
+        )?;
+        let proof = dlog_eq::prove_noninteractive_in_context_with_rng(
+            Publics {
+                g1: &cred.a,
+                h1: &cred.b,
+                g2: &cred.a,
+                h2: &cred.b,
+            },
+            Secrets {
+                x: self.sk.key.exponent(),
+            },
+            &refresh_context(epoch),
+            &mut self.rng(),
+        );
+        org.send(labels::refresh::CREDENTIAL, (cred, proof)).await?;
+        let (cred, _) = self
+            .issue(org, source_key, &source_key.epoch_point(epoch), epoch)
+            .await?;
+        Ok(cred)
     }
 }
 
+#[cfg(feature = "std")]
 impl Org {
     /// Transfers a credential from one organization to another
+    ///
+    /// Credentials issued for an epoch before `epoch` are rejected as expired.
     pub async fn transfer_credential<T: LocalTransport>(
         &self,
         user: &mut T,
         nym: Nym,
         cred: Cred,
         source_key: OrgPublicKey,
+        epoch: u64,
     ) -> Result {
-        cred.T1.verify(Publics {
-            g1: &RISTRETTO_BASEPOINT_POINT,
-            h1: source_key.points().1,
-            g2: &cred.b,
-            h2: &cred.A,
-        })?;
-        cred.T2.verify(Publics {
-            g1: &RISTRETTO_BASEPOINT_POINT,
-            h1: source_key.points().0,
-            g2: &(cred.a + cred.A),
-            h2: &cred.B,
-        })?;
+        self.transfer(user, nym, cred, source_key, epoch, None)
+            .await
+    }
+
+    /// Transfers a credential from one organization to another, checking it was issued under a
+    /// given context
+    ///
+    /// This is [`Org::transfer_credential`] for credentials from
+    /// [`User::issue_credential_with_context`]. The user's side is unchanged.
+    pub async fn transfer_credential_with_context<T: LocalTransport>(
+        &self,
+        user: &mut T,
+        nym: Nym,
+        cred: Cred,
+        source_key: OrgPublicKey,
+        epoch: u64,
+        context: &[u8],
+    ) -> Result {
+        self.transfer(user, nym, cred, source_key, epoch, Some(context))
+            .await
+    }
+
+    /// Transfers a credential, optionally checking the context it was issued under
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(name = "org::transfer_credential", skip_all, fields(epoch = epoch))
+    )]
+    async fn transfer<T: LocalTransport>(
+        &self,
+        user: &mut T,
+        nym: Nym,
+        cred: Cred,
+        source_key: OrgPublicKey,
+        epoch: u64,
+        context: Option<&[u8]>,
+    ) -> Result {
+        if cred.epoch < epoch {
+            return Err(Error::Expired);
+        }
+        match context {
+            Some(context) => cred.verify_against_with_context(&source_key, context)?,
+            None => cred.verify_against(&source_key)?,
+        }
         dlog_eq::verify(
             user,
             Publics {
@@ -352,174 +1793,1769 @@ impl Org {
     }
 }
 
-impl User {
-    /// Transfers a credential from one organization to another
-    pub async fn transfer_credential<T: LocalTransport>(
-        &self,
-        org: &mut T,
-        nym: Nym,
-        cred: Cred,
-    ) -> Result {
-        dlog_eq::prove(
-            org,
-            Publics {
-                g1: &nym.a,
-                h1: &nym.b,
-                g2: &cred.a,
-                h2: &cred.b,
-            },
-            ProverSecrets {
-                x: self.sk.key.exponent(),
-            },
-        )
-        .await?;
-        Ok(())
+#[cfg(feature = "std")]
+impl Org {
+    /// Transfers several credentials from one organization to another at once
+    ///
+    /// This accepts the same credentials as [`Org::transfer_credential`] would individually, but
+    /// the proofs for all of them share a single challenge.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(name = "org::transfer_credentials", skip_all, fields(epoch = epoch))
+    )]
+    pub async fn transfer_credentials<T: LocalTransport>(
+        &self,
+        user: &mut T,
+        creds: &[(Nym, Cred)],
+        source_key: OrgPublicKey,
+        epoch: u64,
+    ) -> Result {
+        for (_, cred) in creds {
+            if cred.epoch < epoch {
+                return Err(Error::Expired);
+            }
+            cred.verify_against(&source_key)?;
+        }
+        let publics: Vec<_> = creds
+            .iter()
+            .map(|(nym, cred)| Publics {
+                g1: &nym.a,
+                h1: &nym.b,
+                g2: &cred.a,
+                h2: &cred.b,
+            })
+            .collect();
+        dlog_eq::verify_all(user, &publics).await
+    }
+}
+
+#[cfg(feature = "std")]
+impl User {
+    /// Transfers several credentials from one organization to another at once
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(name = "user::transfer_credentials", skip_all)
+    )]
+    pub async fn transfer_credentials<T: LocalTransport>(
+        &self,
+        org: &mut T,
+        creds: &[(Nym, Cred)],
+    ) -> Result {
+        let items: Vec<_> = creds
+            .iter()
+            .map(|(nym, cred)| {
+                (
+                    Publics {
+                        g1: &nym.a,
+                        h1: &nym.b,
+                        g2: &cred.a,
+                        h2: &cred.b,
+                    },
+                    Secrets {
+                        x: self.sk.key.exponent(),
+                    },
+                )
+            })
+            .collect();
+        dlog_eq::prove_all_with_rng(org, &items, &mut self.rng()).await
+    }
+}
+
+#[cfg(feature = "std")]
+impl Org {
+    /// Verifies that a user holds credentials from two organizations, with a single challenge
+    ///
+    /// Each credential is given with the public key of the organization that issued it, and both
+    /// must be valid for the given epoch. The proof binds both credentials to the same exponent
+    /// as the nym, so they were issued to the same user that holds the nym. The user's side is
+    /// [`User::prove_conjunction`].
+    pub async fn verify_conjunction<T: LocalTransport>(
+        &self,
+        user: &mut T,
+        nym: Nym,
+        (cred_a, key_a): (Cred, OrgPublicKey),
+        (cred_b, key_b): (Cred, OrgPublicKey),
+        epoch: u64,
+    ) -> Result {
+        for (cred, key) in [(&cred_a, &key_a), (&cred_b, &key_b)] {
+            if cred.epoch < epoch {
+                return Err(Error::Expired);
+            }
+            cred.verify_against(key)?;
+        }
+        let publics = [&cred_a, &cred_b].map(|cred| Publics {
+            g1: &nym.a,
+            h1: &nym.b,
+            g2: &cred.a,
+            h2: &cred.b,
+        });
+        dlog_eq::verify_all(user, &publics).await
+    }
+}
+
+#[cfg(feature = "std")]
+impl User {
+    /// Proves holding credentials from two organizations under one nym, with a single challenge
+    ///
+    /// This proves that both credentials share the exponent of the nym, in one round. The
+    /// organization's side is [`Org::verify_conjunction`].
+    pub async fn prove_conjunction<T: LocalTransport>(
+        &self,
+        org: &mut T,
+        nym: Nym,
+        cred_a: Cred,
+        cred_b: Cred,
+    ) -> Result {
+        self.transfer_credentials(org, &[(nym, cred_a), (nym, cred_b)])
+            .await
+    }
+}
+
+#[cfg(feature = "std")]
+impl User {
+    /// Transfers a credential from one organization to another
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(name = "user::transfer_credential", skip_all)
+    )]
+    pub async fn transfer_credential<T: LocalTransport>(
+        &self,
+        org: &mut T,
+        nym: Nym,
+        cred: Cred,
+    ) -> Result {
+        dlog_eq::prove_with_rng(
+            org,
+            Publics {
+                g1: &nym.a,
+                h1: &nym.b,
+                g2: &cred.a,
+                h2: &cred.b,
+            },
+            Secrets {
+                x: self.sk.key.exponent(),
+            },
+            &mut self.rng(),
+        )
+        .await?;
+        Ok(())
+    }
+}
+
+impl CredShow {
+    /// Gets the nym this credential is shown for
+    pub fn nym(&self) -> Nym {
+        self.nym
+    }
+
+    /// Gets the credential being shown
+    pub fn cred(&self) -> Cred {
+        self.cred
+    }
+}
+
+impl Org {
+    /// Verifies a credential showing
+    ///
+    /// The credential's epoch is not checked; use [`Cred::epoch`] to reject expired showings.
+    pub fn verify_show(&self, show: &CredShow, source_key: OrgPublicKey) -> Result {
+        show.cred.verify_against(&source_key)?;
+        show.proof.verify(Publics {
+            g1: &show.nym.a,
+            h1: &show.nym.b,
+            g2: &show.cred.a,
+            h2: &show.cred.b,
+        })
+    }
+}
+
+impl User {
+    /// Shows a credential for a given nym, without interaction
+    pub fn show_credential(&self, nym: Nym, cred: Cred) -> CredShow {
+        let proof = dlog_eq::prove_noninteractive_with_rng(
+            Publics {
+                g1: &nym.a,
+                h1: &nym.b,
+                g2: &cred.a,
+                h2: &cred.b,
+            },
+            Secrets {
+                x: self.sk.key.exponent(),
+            },
+            &mut self.rng(),
+        );
+        CredShow { nym, cred, proof }
+    }
+}
+
+impl AttrCred {
+    /// Gets the underlying credential
+    pub fn cred(&self) -> Cred {
+        self.cred
+    }
+
+    /// Gets the values of the attributes, in order
+    pub fn attributes(&self) -> impl Iterator<Item = &Scalar> {
+        self.attributes.iter().map(|(value, _, _)| value)
+    }
+}
+
+impl AttributeShow {
+    /// Gets the nym this credential is shown for
+    pub fn nym(&self) -> Nym {
+        self.nym
+    }
+
+    /// Gets the credential being shown
+    pub fn cred(&self) -> Cred {
+        self.cred
+    }
+
+    /// Gets the disclosed attributes, as pairs of index and value
+    pub fn attributes(&self) -> impl Iterator<Item = (usize, Scalar)> + '_ {
+        self.attributes
+            .iter()
+            .enumerate()
+            .filter_map(|(index, (shown, _))| match shown {
+                ShownAttribute::Disclosed { value, .. } => Some((index, *value)),
+                ShownAttribute::Hidden { .. } => None,
+            })
+    }
+
+    /// Gets the number of attributes of the credential, disclosed or not
+    pub fn attribute_count(&self) -> usize {
+        self.attributes.len()
+    }
+}
+
+/// Gets the bases and points of a nym and some credentials, which all share the user's exponent
+fn shared_publics<'a>(
+    nym: &Nym,
+    creds: impl IntoIterator<Item = &'a Cred>,
+) -> (Vec<RistrettoPoint>, Vec<RistrettoPoint>) {
+    [(nym.a, nym.b)]
+        .into_iter()
+        .chain(creds.into_iter().map(|cred| (cred.a, cred.b)))
+        .unzip()
+}
+
+impl Org {
+    /// Verifies a showing of a credential with attributes
+    ///
+    /// Every attribute of the credential is checked against its commitment, whether it is
+    /// disclosed or not. The number of attributes is not checked; use
+    /// [`AttributeShow::attribute_count`] to reject showings of credentials with other attributes.
+    /// The credential's epoch is not checked; use [`Cred::epoch`] to reject expired showings.
+    pub fn verify_attributes(&self, show: &AttributeShow, source_key: OrgPublicKey) -> Result {
+        show.cred.verify_against(&source_key)?;
+        for (index, (shown, cred)) in show.attributes.iter().enumerate() {
+            let y_point = source_key.attribute_point(show.cred.epoch, index, &shown.commitment());
+            cred.verify_issuer_with(source_key, &y_point)?;
+        }
+        let creds = show.attributes.iter().map(|(_, cred)| cred);
+        let (g, h) = shared_publics(&show.nym, [&show.cred].into_iter().chain(creds));
+        show.proof.verify(dlog_eq_n::Publics { g: &g, h: &h })
+    }
+}
+
+impl User {
+    /// Shows a credential for a given nym, disclosing the attributes at the given indices
+    ///
+    /// The other attributes are shown by their commitments only. This fails with
+    /// [`Error::UnknownAttribute`] if any of the indices is out of range.
+    pub fn show_attributes(
+        &self,
+        nym: Nym,
+        cred: &AttrCred,
+        reveal: &[usize],
+    ) -> Result<AttributeShow> {
+        if let Some(&index) = reveal.iter().find(|&&i| i >= cred.attributes.len()) {
+            return Err(Error::UnknownAttribute(index));
+        }
+        let attributes: Vec<_> = cred
+            .attributes
+            .iter()
+            .enumerate()
+            .map(|(index, (value, blinding, cred))| {
+                let shown = if reveal.contains(&index) {
+                    ShownAttribute::Disclosed {
+                        value: *value,
+                        blinding: *blinding,
+                    }
+                } else {
+                    ShownAttribute::Hidden {
+                        commitment: range::commit_scalar(value, blinding),
+                    }
+                };
+                (shown, *cred)
+            })
+            .collect();
+        let creds = attributes.iter().map(|(_, cred)| cred);
+        let (g, h) = shared_publics(&nym, [&cred.cred].into_iter().chain(creds));
+        let proof = dlog_eq_n::prove_noninteractive_with_rng(
+            dlog_eq_n::Publics { g: &g, h: &h },
+            dlog_eq_n::Secrets {
+                x: self.sk.key.exponent(),
+            },
+            &mut self.rng(),
+        )
+        .expect("there should be one point per base");
+        Ok(AttributeShow {
+            nym,
+            cred: cred.cred,
+            attributes,
+            proof,
+        })
+    }
+}
+
+impl RangeCred {
+    /// Gets the underlying credential
+    pub fn cred(&self) -> Cred {
+        self.cred
+    }
+
+    /// Gets the values of the attributes, in order
+    pub fn attributes(&self) -> impl Iterator<Item = u64> + '_ {
+        self.attributes.iter().map(|(value, _, _)| *value)
+    }
+}
+
+impl RangeShow {
+    /// Gets the nym this credential is shown for
+    pub fn nym(&self) -> Nym {
+        self.nym
+    }
+
+    /// Gets the credential being shown
+    pub fn cred(&self) -> Cred {
+        self.cred
+    }
+
+    /// Gets the index of the attribute shown to lie in the range
+    pub fn index(&self) -> usize {
+        self.index
+    }
+}
+
+impl Org {
+    /// Verifies a showing that an attribute of a credential lies in a given range
+    ///
+    /// The range is the one this organization requires, and the showing fails to verify if it was
+    /// made for any other. The credential's epoch is not checked; use [`Cred::epoch`] to reject
+    /// expired showings.
+    pub fn verify_attribute_range(
+        &self,
+        show: &RangeShow,
+        source_key: OrgPublicKey,
+        min: u64,
+        max: u64,
+    ) -> Result {
+        show.cred.verify_against(&source_key)?;
+        let y_point = source_key.range_point(show.cred.epoch, show.index, &show.commitment);
+        show.attribute.verify_issuer_with(source_key, &y_point)?;
+        show.range.verify(range::Publics {
+            commitment: &show.commitment,
+            min,
+            max,
+        })?;
+        let (g, h) = shared_publics(&show.nym, [&show.cred, &show.attribute]);
+        show.proof.verify(dlog_eq_n::Publics { g: &g, h: &h })
+    }
+}
+
+impl User {
+    /// Shows that an attribute of a credential lies in a range, for a given nym, without
+    /// disclosing it
+    ///
+    /// This fails with [`Error::OutOfRange`] if the attribute is not in the range.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the index is out of range.
+    pub fn prove_attribute_range(
+        &self,
+        nym: Nym,
+        cred: &RangeCred,
+        index: usize,
+        min: u64,
+        max: u64,
+    ) -> Result<RangeShow> {
+        let (value, blinding, attribute) = cred.attributes[index];
+        let commitment = range::commit(value, &blinding);
+        let range = range::prove_noninteractive_with_rng(
+            range::Publics {
+                commitment: &commitment,
+                min,
+                max,
+            },
+            range::Secrets {
+                value,
+                blinding: &blinding,
+            },
+            &mut self.rng(),
+        )?;
+        let (g, h) = shared_publics(&nym, [&cred.cred, &attribute]);
+        let proof = dlog_eq_n::prove_noninteractive_with_rng(
+            dlog_eq_n::Publics { g: &g, h: &h },
+            dlog_eq_n::Secrets {
+                x: self.sk.key.exponent(),
+            },
+            &mut self.rng(),
+        )
+        .expect("there should be one point per base");
+        Ok(RangeShow {
+            nym,
+            cred: cred.cred,
+            index,
+            commitment,
+            attribute,
+            proof,
+            range,
+        })
+    }
+}
+
+impl MultiShow {
+    /// Gets the nym these credentials are shown for
+    pub fn nym(&self) -> Nym {
+        self.nym
+    }
+
+    /// Gets the credentials being shown, in order
+    pub fn creds(&self) -> &[Cred] {
+        &self.creds
+    }
+}
+
+impl Org {
+    /// Verifies a showing of credentials from several organizations
+    ///
+    /// The source keys are those of the organizations that issued each credential, in the same
+    /// order. The credentials' epochs are not checked; use [`Cred::epoch`] to reject expired
+    /// showings.
+    pub fn verify_multi(&self, show: &MultiShow, source_keys: &[OrgPublicKey]) -> Result {
+        if show.creds.len() != source_keys.len() {
+            return Err(Error::LengthMismatch {
+                expected: show.creds.len(),
+                got: source_keys.len(),
+            });
+        }
+        for (cred, source_key) in show.creds.iter().zip(source_keys) {
+            cred.verify_against(source_key)?;
+        }
+        let (g, h) = shared_publics(&show.nym, &show.creds);
+        show.proof.verify(dlog_eq_n::Publics { g: &g, h: &h })
+    }
+}
+
+impl User {
+    /// Shows credentials from several organizations for a given nym, in a single proof
+    ///
+    /// Each credential is given with the public key of the organization that issued it, and is
+    /// checked against it first. The proof's challenge covers the nym and all the credentials, so
+    /// credentials of different users cannot be mixed in one showing. The organization's side is
+    /// [`Org::verify_multi`].
+    pub fn prove_multi(&self, nym: Nym, creds: &[(Cred, OrgPublicKey)]) -> Result<MultiShow> {
+        for (cred, source_key) in creds {
+            cred.verify_against(source_key)?;
+        }
+        let creds: Vec<_> = creds.iter().map(|(cred, _)| *cred).collect();
+        let (g, h) = shared_publics(&nym, &creds);
+        let proof = dlog_eq_n::prove_noninteractive_with_rng(
+            dlog_eq_n::Publics { g: &g, h: &h },
+            dlog_eq_n::Secrets {
+                x: self.sk.key.exponent(),
+            },
+            &mut self.rng(),
+        )?;
+        Ok(MultiShow { nym, creds, proof })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::assert_matches::assert_matches;
+    use std::sync::{Arc, Mutex};
+
+    use curve25519_dalek::{
+        constants::RISTRETTO_BASEPOINT_POINT, ristretto::CompressedRistretto,
+        traits::Identity as _, RistrettoPoint, Scalar,
+    };
+    use futures::{
+        executor::block_on,
+        future::{try_join, try_join_all},
+    };
+    use merlin::Transcript;
+    use rand::{thread_rng, SeedableRng as _};
+    use rand_chacha::ChaCha20Rng;
+
+    use crate::{
+        hash::TranscriptProtocol as _,
+        key::{OrgSecretKey, UserSecretKey},
+        labels,
+        proof::{
+            dlog_eq::{self, Publics, Secrets},
+            range, WIRE_VERSION,
+        },
+        transport::{codec::Json, memory::DuplexTransport, LocalTransport as _, Recording},
+        AttrCred, Cred, CredShow, Error, Nym, ProofStage, Signature,
+    };
+
+    use super::{Org, ShownAttribute, User};
+
+    #[test]
+    fn nym_generation() {
+        let user = User::new(UserSecretKey::random(&mut thread_rng()));
+        let org = Org::new(OrgSecretKey::random(&mut thread_rng()));
+
+        let (mut u_channel, mut o_channel) = DuplexTransport::<Json>::pair();
+        let (n1, n2) = block_on(try_join(
+            user.generate_nym(&mut u_channel),
+            org.generate_nym(&mut o_channel),
+        ))
+        .unwrap();
+        assert_eq!(n1, n2, "user and org should compute the same nym");
+        assert_eq!(n1.a * user.sk.key.exponent(), n1.b, "nym should be valid");
+    }
+
+    #[test]
+    fn version_mismatch() {
+        let org = Org::new(OrgSecretKey::random(&mut thread_rng()));
+
+        let (mut u_channel, mut o_channel) = DuplexTransport::<Json>::pair();
+        let user = async {
+            u_channel
+                .send(labels::nym::VERSION, WIRE_VERSION + 1)
+                .await?;
+            let _: u32 = u_channel.receive(labels::nym::VERSION).await?;
+            Ok(())
+        };
+        let res = block_on(try_join(user, org.generate_nym(&mut o_channel)));
+        assert_matches!(
+            res,
+            Err(Error::VersionMismatch { ours, theirs })
+                if ours == WIRE_VERSION && theirs == WIRE_VERSION + 1
+        );
+    }
+
+    #[test]
+    fn builders() {
+        let org = Org::builder()
+            .rng(ChaCha20Rng::seed_from_u64(1))
+            .build()
+            .unwrap();
+        let again = Org::builder()
+            .rng(ChaCha20Rng::seed_from_u64(1))
+            .build()
+            .unwrap();
+        assert!(org.public_key() == again.public_key());
+        let user = User::builder()
+            .rng(ChaCha20Rng::seed_from_u64(2))
+            .build()
+            .unwrap();
+        let again = User::builder()
+            .secret_key(UserSecretKey::from_seed(&[2; 32]))
+            .rng(ChaCha20Rng::seed_from_u64(2))
+            .build()
+            .unwrap();
+        assert!(
+            user.public_key() != again.public_key(),
+            "an explicit key should take precedence"
+        );
+
+        let (mut u_channel, mut o_channel) = DuplexTransport::<Json>::pair();
+        let res = block_on(try_join(
+            user.generate_nym(&mut u_channel),
+            org.generate_nym(&mut o_channel),
+        ));
+        assert_matches!(res, Ok((n1, n2)) if n1 == n2);
+
+        assert_matches!(
+            User::builder().protocol_version(WIRE_VERSION + 1).build(),
+            Err(Error::UnsupportedVersion(v)) if v == WIRE_VERSION + 1
+        );
+        assert_matches!(
+            Org::builder().protocol_version(WIRE_VERSION + 1).build(),
+            Err(Error::UnsupportedVersion(v)) if v == WIRE_VERSION + 1
+        );
+    }
+
+    #[test]
+    fn builder_rng_seeds_proofs() {
+        let seeded = |seed| {
+            User::builder()
+                .secret_key(UserSecretKey::from_seed(&[3; 32]))
+                .rng(ChaCha20Rng::seed_from_u64(seed))
+                .build()
+                .unwrap()
+        };
+        let nym = Nym {
+            a: RISTRETTO_BASEPOINT_POINT,
+            b: seeded(0).public_key().point(),
+        };
+        let (user, again, other) = (seeded(4), seeded(4), seeded(5));
+
+        let (rerandomized, γ) = user.rerandomize_nym(&nym);
+        assert_eq!(
+            (rerandomized, γ),
+            again.rerandomize_nym(&nym),
+            "the same seed should draw the same values"
+        );
+        assert!(other.rerandomize_nym(&nym).1 != γ);
+
+        let proof = user.prove_same_holder(&nym, &rerandomized);
+        assert_eq!(proof, again.prove_same_holder(&nym, &rerandomized));
+        assert!(proof != other.prove_same_holder(&nym, &rerandomized));
+        assert_matches!(
+            proof.verify(same_holder_publics(&nym, &rerandomized)),
+            Ok(_)
+        );
+    }
+
+    #[test]
+    fn identity_points_rejected() {
+        let org = Org::new(OrgSecretKey::random(&mut thread_rng()));
+
+        let (mut u_channel, mut o_channel) = DuplexTransport::<Json>::pair();
+        let user = async {
+            u_channel.send(labels::nym::VERSION, WIRE_VERSION).await?;
+            u_channel
+                .send(labels::nym::USER_BASE, RistrettoPoint::identity())
+                .await?;
+            u_channel
+                .send(labels::nym::USER_POINT, RistrettoPoint::identity())
+                .await?;
+            Ok(())
+        };
+        let res = block_on(try_join(user, org.generate_nym(&mut o_channel)));
+        assert_matches!(
+            res,
+            Err(Error::BadProof {
+                stage: ProofStage::IdentityPoint
+            })
+        );
+
+        let (mut p_channel, mut v_channel) = DuplexTransport::<Json>::pair();
+        let h = RistrettoPoint::random(&mut thread_rng());
+        let publics = Publics {
+            g1: &RISTRETTO_BASEPOINT_POINT,
+            h1: &h,
+            g2: &RISTRETTO_BASEPOINT_POINT,
+            h2: &h,
+        };
+        let prover = async {
+            let identity = RistrettoPoint::identity();
+            p_channel
+                .send(labels::dlog_eq::COMMITMENTS, (identity, identity))
+                .await?;
+            Ok(())
+        };
+        let res = block_on(try_join(prover, dlog_eq::verify(&mut v_channel, publics)));
+        assert_matches!(
+            res,
+            Err(Error::BadProof {
+                stage: ProofStage::IdentityPoint
+            })
+        );
+    }
+
+    #[test]
+    fn proof_with_seeded_rng() {
+        let x = Scalar::random(&mut thread_rng());
+        let h = x * RISTRETTO_BASEPOINT_POINT;
+        let publics = Publics {
+            g1: &RISTRETTO_BASEPOINT_POINT,
+            h1: &h,
+            g2: &RISTRETTO_BASEPOINT_POINT,
+            h2: &h,
+        };
+        let c = Scalar::random(&mut thread_rng());
+        let run = || {
+            let (mut p_channel, mut v_channel) = DuplexTransport::<Json>::pair();
+            let mut rng = ChaCha20Rng::seed_from_u64(42);
+            let verifier = async {
+                let (a, b): (RistrettoPoint, RistrettoPoint) =
+                    v_channel.receive(labels::dlog_eq::COMMITMENTS).await?;
+                v_channel.send(labels::dlog_eq::CHALLENGE, c).await?;
+                let y: Scalar = v_channel.receive(labels::dlog_eq::RESPONSE).await?;
+                Ok::<_, Error>((a, b, y))
+            };
+            let prover =
+                dlog_eq::prove_with_rng(&mut p_channel, publics, Secrets { x: &x }, &mut rng);
+            block_on(try_join(prover, verifier)).unwrap().1
+        };
+        assert_eq!(run(), run(), "same seed should give the same transcript");
+    }
+
+    #[test]
+    fn batch_nym_generation() {
+        let user = User::new(UserSecretKey::random(&mut thread_rng()));
+        let orgs: Vec<_> = (0..3)
+            .map(|_| Org::new(OrgSecretKey::random(&mut thread_rng())))
+            .collect();
+
+        let (mut u_channels, o_channels): (Vec<_>, Vec<_>) =
+            (0..3).map(|_| DuplexTransport::<Json>::pair()).unzip();
+        let org_side = try_join_all(
+            orgs.iter()
+                .zip(o_channels)
+                .map(|(org, mut channel)| async move { org.generate_nym(&mut channel).await }),
+        );
+        let (n1, n2) = block_on(try_join(user.generate_nyms(&mut u_channels), org_side)).unwrap();
+        assert_eq!(n1, n2, "user and orgs should compute the same nyms");
+        assert_ne!(n1[0], n1[1], "nyms should be distinct");
+        assert_ne!(n1[0], n1[2], "nyms should be distinct");
+        assert_ne!(n1[1], n1[2], "nyms should be distinct");
+    }
+
+    #[test]
+    fn nym_equality() {
+        let nym = Nym {
+            a: RistrettoPoint::random(&mut thread_rng()),
+            b: RistrettoPoint::random(&mut thread_rng()),
+        };
+        assert_eq!(nym, nym);
+        assert_ne!(
+            nym,
+            Nym {
+                a: RistrettoPoint::random(&mut thread_rng()),
+                ..nym
+            }
+        );
+        assert_ne!(
+            nym,
+            Nym {
+                b: RistrettoPoint::random(&mut thread_rng()),
+                ..nym
+            }
+        );
+    }
+
+    #[test]
+    fn nym_points() {
+        let nym = Nym {
+            a: RistrettoPoint::random(&mut thread_rng()),
+            b: RistrettoPoint::random(&mut thread_rng()),
+        };
+        assert_eq!(nym.points(), (nym.a, nym.b));
+
+        let (a, b) = nym.compressed();
+        assert_eq!(a.decompress(), Some(nym.a));
+        assert_eq!(b.decompress(), Some(nym.b));
+        assert_eq!(nym.to_bytes()[..32], a.to_bytes());
+        assert_eq!(nym.to_bytes()[32..], b.to_bytes());
+    }
+
+    #[test]
+    fn nym_from_points() {
+        let a = RistrettoPoint::random(&mut thread_rng());
+        let b = RistrettoPoint::random(&mut thread_rng());
+        let nym = Nym::from_points(a, b);
+        assert_eq!(nym.points(), (a, b));
+
+        let (ca, cb) = nym.compressed();
+        assert_matches!(Nym::try_from_compressed(&ca, &cb), Ok(n) if n == nym);
+        let bad = CompressedRistretto([0xff; 32]);
+        assert_matches!(
+            Nym::try_from_compressed(&ca, &bad),
+            Err(Error::InvalidEncoding)
+        );
+        assert_matches!(
+            Nym::try_from_compressed(&bad, &cb),
+            Err(Error::InvalidEncoding)
+        );
+    }
+
+    #[test]
+    fn nym_authentication() {
+        let user = User::new(UserSecretKey::random(&mut thread_rng()));
+        let org = Org::new(OrgSecretKey::random(&mut thread_rng()));
+
+        let (mut u_channel, mut o_channel) = DuplexTransport::<Json>::pair();
+        let (nym, _) = block_on(try_join(
+            user.generate_nym(&mut u_channel),
+            org.generate_nym(&mut o_channel),
+        ))
+        .unwrap();
+
+        let res = block_on(try_join(
+            user.authenticate_nym(&mut u_channel, nym),
+            org.authenticate_nym(&mut o_channel, nym),
+        ));
+        assert_matches!(res, Ok(_));
+    }
+
+    #[test]
+    fn authenticated_ca_nym() {
+        let user = User::new(UserSecretKey::random(&mut thread_rng()));
+        let other = User::new(UserSecretKey::random(&mut thread_rng()));
+        let ca = Org::new(OrgSecretKey::random(&mut thread_rng()));
+
+        let (mut u_channel, mut o_channel) = DuplexTransport::<Json>::pair();
+        let (n1, n2) = block_on(try_join(
+            user.generate_nym_with_ca_authenticated(&mut u_channel),
+            ca.generate_nym_as_ca_authenticated(&mut o_channel, user.pk),
+        ))
+        .unwrap();
+        assert_eq!(n1, n2, "user and CA should compute the same nym");
+
+        let (mut u_channel, mut o_channel) = DuplexTransport::<Json>::pair();
+        let res = block_on(try_join(
+            user.generate_nym_with_ca_authenticated(&mut u_channel),
+            ca.generate_nym_as_ca_authenticated(&mut o_channel, other.pk),
+        ));
+        assert_matches!(res, Err(Error::BadProof { .. }));
+    }
+
+    #[test]
+    fn ca_nym_issuance() {
+        let user = User::new(UserSecretKey::random(&mut thread_rng()));
+        let other = User::new(UserSecretKey::random(&mut thread_rng()));
+        let ca = Org::new(OrgSecretKey::random(&mut thread_rng()));
+        let org = Org::new(OrgSecretKey::random(&mut thread_rng()));
+
+        let generate = |user: &User| {
+            let (mut u_channel, mut o_channel) = DuplexTransport::<Json>::pair();
+            block_on(try_join(
+                user.generate_nym_with_ca(&mut u_channel),
+                ca.generate_nym_as_ca(&mut o_channel, user.pk),
+            ))
+            .unwrap()
+            .0
+        };
+        let nym = generate(&user);
+        let other_nym = generate(&other);
+
+        let (mut u_channel, mut o_channel) = DuplexTransport::<Json>::pair();
+        let (cred, _) = block_on(try_join(
+            user.issue_credential_for_ca_nym(&mut u_channel, nym, org.public_key(), 0),
+            org.issue_credential_for_ca_nym(&mut o_channel, nym, user.pk, 0),
+        ))
+        .unwrap();
+        assert_eq!(cred.b * org.sk.epoch_exponent(0), cred.A);
+        assert_eq!((cred.a + cred.A) * org.sk.issuer_exponent(0), cred.B);
+
+        let (mut u_channel, mut o_channel) = DuplexTransport::<Json>::pair();
+        let res = block_on(try_join(
+            other.issue_credential_for_ca_nym(&mut u_channel, other_nym, org.public_key(), 0),
+            org.issue_credential_for_ca_nym(&mut o_channel, other_nym, user.pk, 0),
+        ));
+        assert_matches!(res, Err(Error::UnknownNym));
+    }
+
+    #[test]
+    fn nym_linking() {
+        let user = User::new(UserSecretKey::random(&mut thread_rng()));
+        let other = User::new(UserSecretKey::random(&mut thread_rng()));
+        let org1 = Org::new(OrgSecretKey::random(&mut thread_rng()));
+        let org2 = Org::new(OrgSecretKey::random(&mut thread_rng()));
+        let relying = Org::new(OrgSecretKey::random(&mut thread_rng()));
+
+        let generate = |user: &User, org: &Org| {
+            let (mut u_channel, mut o_channel) = DuplexTransport::<Json>::pair();
+            block_on(try_join(
+                user.generate_nym(&mut u_channel),
+                org.generate_nym(&mut o_channel),
+            ))
+            .unwrap()
+            .0
+        };
+        let nym1 = generate(&user, &org1);
+        let nym2 = generate(&user, &org2);
+        let foreign = generate(&other, &org2);
+
+        let (mut u_channel, mut o_channel) = DuplexTransport::<Json>::pair();
+        let res = block_on(try_join(
+            user.link_nyms(&mut u_channel, nym1, nym2),
+            relying.verify_link(&mut o_channel, nym1, nym2),
+        ));
+        assert_matches!(res, Ok(_));
+
+        let (mut u_channel, mut o_channel) = DuplexTransport::<Json>::pair();
+        let res = block_on(try_join(
+            user.link_nyms(&mut u_channel, nym1, foreign),
+            relying.verify_link(&mut o_channel, nym1, foreign),
+        ));
+        assert_matches!(res, Err(Error::BadProof { .. }));
+    }
+
+    #[test]
+    fn tagged_nyms() {
+        let user = User::new(UserSecretKey::random(&mut thread_rng()));
+        let org = Org::new(OrgSecretKey::random(&mut thread_rng()));
+        let other = Org::new(OrgSecretKey::random(&mut thread_rng()));
+
+        let (u_channel, mut o_channel) = DuplexTransport::<Json>::pair();
+        let mut u_channel = Recording::new(u_channel);
+        let mut generate = |org: &Org, domain: &'static [u8]| {
+            let start = u_channel.log().len();
+            let (n1, n2) = block_on(try_join(
+                user.generate_nym_tagged(&mut u_channel, &org.public_key(), domain),
+                org.generate_nym(&mut o_channel),
+            ))
+            .unwrap();
+            assert_eq!(n1, n2, "user and org should compute the same nym");
+            let point = u_channel.log()[start..]
+                .iter()
+                .find(|m| m.label == labels::nym::USER_POINT)
+                .unwrap()
+                .bytes
+                .clone();
+            (n1, point)
+        };
+        let (work, work_point) = generate(&org, b"work");
+        let (home, home_point) = generate(&org, b"home");
+        let (again, again_point) = generate(&org, b"work");
+        let (_, elsewhere_point) = generate(&other, b"work");
+        assert_ne!(work, home);
+        assert_ne!(work_point, home_point);
+        assert_eq!(work_point, again_point);
+        assert_ne!(
+            work_point, elsewhere_point,
+            "organizations should not see the same user point for the same domain"
+        );
+
+        for nym in [work, home, again] {
+            let res = block_on(try_join(
+                user.authenticate_nym(&mut u_channel, nym),
+                org.authenticate_nym(&mut o_channel, nym),
+            ));
+            assert_matches!(res, Ok(_));
+        }
+    }
+
+    #[test]
+    fn replayed_authentication() {
+        let user = User::new(UserSecretKey::random(&mut thread_rng()));
+        let org = Org::new(OrgSecretKey::random(&mut thread_rng()));
+        let other = Org::new(OrgSecretKey::random(&mut thread_rng()));
+
+        let (mut u_channel, mut o_channel) = DuplexTransport::<Json>::pair();
+        let (nym, _) = block_on(try_join(
+            user.generate_nym(&mut u_channel),
+            org.generate_nym(&mut o_channel),
+        ))
+        .unwrap();
+
+        let mut u_channel = Recording::new(u_channel);
+        block_on(try_join(
+            user.authenticate_nym(&mut u_channel, nym),
+            org.authenticate_nym(&mut o_channel, nym),
+        ))
+        .unwrap();
+        let log = u_channel.into_log();
+        assert_eq!(log[1].label, labels::auth::PROOF);
+        let proof: serde_json::Value = serde_json::from_slice(&log[1].bytes).unwrap();
+
+        let replay = |org: &Org| {
+            let (mut a_channel, mut o_channel) = DuplexTransport::<Json>::pair();
+            let attacker = async {
+                let _: [u8; 32] = a_channel.receive(labels::auth::NONCE).await?;
+                a_channel.send(labels::auth::PROOF, &proof).await?;
+                Ok(())
+            };
+            block_on(try_join(
+                attacker,
+                org.authenticate_nym(&mut o_channel, nym),
+            ))
+        };
+        assert_matches!(replay(&org), Err(Error::StaleNonce));
+        assert_matches!(
+            replay(&other),
+            Err(Error::BadProof {
+                stage: ProofStage::ChallengeMismatch
+            })
+        );
+    }
+
+    #[test]
+    fn cred_refresh() {
+        let user = User::new(UserSecretKey::random(&mut thread_rng()));
+        let org = Org::new(OrgSecretKey::random(&mut thread_rng()));
+        let other = Org::new(OrgSecretKey::random(&mut thread_rng()));
+
+        let (mut u_channel, mut o_channel) = DuplexTransport::<Json>::pair();
+        let (nym, _) = block_on(try_join(
+            user.generate_nym(&mut u_channel),
+            org.generate_nym(&mut o_channel),
+        ))
+        .unwrap();
+        let (cred, _) = block_on(try_join(
+            user.issue_credential(&mut u_channel, nym, org.public_key(), 0),
+            org.issue_credential(&mut o_channel, nym, 0),
+        ))
+        .unwrap();
+
+        let (refreshed, _) = block_on(try_join(
+            user.refresh_credential(&mut u_channel, cred, org.public_key(), 1),
+            org.accept_refresh(&mut o_channel, 0, 1),
+        ))
+        .unwrap();
+        assert_eq!(refreshed.epoch(), 1);
+        assert_ne!(refreshed.a, cred.a);
+
+        let (mut u_channel, mut o_channel) = DuplexTransport::<Json>::pair();
+        let (nym, _) = block_on(try_join(
+            user.generate_nym(&mut u_channel),
+            other.generate_nym(&mut o_channel),
+        ))
+        .unwrap();
+        let res = block_on(try_join(
+            user.transfer_credential(&mut u_channel, nym, refreshed),
+            other.transfer_credential(&mut o_channel, nym, refreshed, org.public_key(), 1),
+        ));
+        assert_matches!(res, Ok(_));
+        let res =
+            block_on(other.transfer_credential(&mut o_channel, nym, cred, org.public_key(), 1));
+        assert_matches!(res, Err(Error::Expired));
+
+        let (mut u_channel, mut o_channel) = DuplexTransport::<Json>::pair();
+        let res = block_on(try_join(
+            user.refresh_credential(&mut u_channel, cred, org.public_key(), 3),
+            org.accept_refresh(&mut o_channel, 2, 3),
+        ));
+        assert_matches!(res, Err(Error::Expired));
+    }
+
+    #[test]
+    fn cred_context() {
+        let user = User::new(UserSecretKey::random(&mut thread_rng()));
+        let org = Org::new(OrgSecretKey::random(&mut thread_rng()));
+        let other = Org::new(OrgSecretKey::random(&mut thread_rng()));
+
+        let (mut u_channel, mut o_channel) = DuplexTransport::<Json>::pair();
+        let (nym, _) = block_on(try_join(
+            user.generate_nym(&mut u_channel),
+            org.generate_nym(&mut o_channel),
+        ))
+        .unwrap();
+        let (cred, _) = block_on(try_join(
+            user.issue_credential_with_context(
+                &mut u_channel,
+                nym,
+                org.public_key(),
+                0,
+                b"policy/a",
+            ),
+            org.issue_credential_with_context(&mut o_channel, nym, 0, b"policy/a"),
+        ))
+        .unwrap();
+        let pk = org.public_key();
+        assert_matches!(cred.verify_against_with_context(&pk, b"policy/a"), Ok(()));
+        assert_matches!(
+            cred.verify_against_with_context(&pk, b"policy/b"),
+            Err(Error::ContextMismatch)
+        );
+        assert_matches!(cred.verify_against(&pk), Err(Error::BadProof { .. }));
+
+        let res = block_on(try_join(
+            user.issue_credential_with_context(&mut u_channel, nym, pk, 0, b"policy/a"),
+            org.issue_credential_with_context(&mut o_channel, nym, 0, b"policy/b"),
+        ));
+        assert_matches!(res, Err(Error::ContextMismatch));
+
+        let (mut u_channel, mut o_channel) = DuplexTransport::<Json>::pair();
+        let res = block_on(try_join(
+            async {
+                let _: Vec<u8> = u_channel.receive(labels::issuance::CONTEXT).await?;
+                user.issue(&mut u_channel, pk, &pk.epoch_point(0), 0).await
+            },
+            org.issue_credential_with_context(&mut o_channel, nym, 0, b"policy/a"),
+        ));
+        assert_matches!(res, Err(Error::BadProof { .. }));
+
+        let (mut u_channel, mut o_channel) = DuplexTransport::<Json>::pair();
+        let (nym, _) = block_on(try_join(
+            user.generate_nym(&mut u_channel),
+            other.generate_nym(&mut o_channel),
+        ))
+        .unwrap();
+        let res = block_on(try_join(
+            user.transfer_credential(&mut u_channel, nym, cred),
+            other.transfer_credential_with_context(&mut o_channel, nym, cred, pk, 0, b"policy/a"),
+        ));
+        assert_matches!(res, Ok(_));
+        let res = block_on(other.transfer_credential_with_context(
+            &mut o_channel,
+            nym,
+            cred,
+            pk,
+            0,
+            b"policy/b",
+        ));
+        assert_matches!(res, Err(Error::ContextMismatch));
+        let res = block_on(other.transfer_credential(&mut o_channel, nym, cred, pk, 0));
+        assert_matches!(res, Err(Error::BadProof { .. }));
+    }
+
+    #[test]
+    fn revoked_nym_authentication() {
+        let user = User::new(UserSecretKey::random(&mut thread_rng()));
+        let mut org = Org::new(OrgSecretKey::random(&mut thread_rng()));
+
+        let (mut u_channel, mut o_channel) = DuplexTransport::<Json>::pair();
+        let (nym, _) = block_on(try_join(
+            user.generate_nym(&mut u_channel),
+            org.generate_nym(&mut o_channel),
+        ))
+        .unwrap();
+
+        org.revoke(nym);
+        assert!(org.revocation_list().is_revoked(&nym));
+        let res = block_on(try_join(
+            user.authenticate_nym(&mut u_channel, nym),
+            org.authenticate_nym(&mut o_channel, nym),
+        ));
+        assert_matches!(res, Err(Error::Revoked));
+    }
+
+    #[test]
+    fn cred_issuance() {
+        let user = User::new(UserSecretKey::random(&mut thread_rng()));
+        let org = Org::new(OrgSecretKey::random(&mut thread_rng()));
+
+        let (mut u_channel, mut o_channel) = DuplexTransport::<Json>::pair();
+        let (nym, _) = block_on(try_join(
+            user.generate_nym(&mut u_channel),
+            org.generate_nym(&mut o_channel),
+        ))
+        .unwrap();
+
+        let (cred, _) = block_on(try_join(
+            user.issue_credential(&mut u_channel, nym, org.public_key(), 0),
+            org.issue_credential(&mut o_channel, nym, 0),
+        ))
+        .unwrap();
+
+        assert_eq!(cred.a * user.sk.key.exponent(), cred.b);
+        assert_eq!(cred.b * org.sk.epoch_exponent(0), cred.A);
+        assert_eq!((cred.a + cred.A) * org.sk.issuer_exponent(0), cred.B);
+    }
+
+    #[test]
+    fn binary_encoding() {
+        let user = User::new(UserSecretKey::random(&mut thread_rng()));
+        let org = Org::new(OrgSecretKey::random(&mut thread_rng()));
+
+        let (mut u_channel, mut o_channel) = DuplexTransport::<Json>::pair();
+        let (nym, _) = block_on(try_join(
+            user.generate_nym(&mut u_channel),
+            org.generate_nym(&mut o_channel),
+        ))
+        .unwrap();
+        let (cred, _) = block_on(try_join(
+            user.issue_credential(&mut u_channel, nym, org.public_key(), 0),
+            org.issue_credential(&mut o_channel, nym, 0),
+        ))
+        .unwrap();
+
+        let bytes = nym.to_bytes();
+        assert_matches!(Nym::from_bytes(&bytes), Ok(n) if n == nym);
+        assert_matches!(Nym::from_bytes(&bytes[1..]), Err(Error::InvalidEncoding));
+
+        let bytes = cred.to_bytes();
+        assert_eq!(bytes.len(), 4 * 32 + 2 * (2 * 32 + 2 * 32) + 8);
+        assert_matches!(Cred::from_bytes(&bytes), Ok(c) if c == cred);
+        assert_matches!(
+            Cred::from_bytes(&bytes[..bytes.len() - 1]),
+            Err(Error::InvalidEncoding)
+        );
+    }
+
+    #[test]
+    fn issuance_audit() {
+        let user = User::new(UserSecretKey::random(&mut thread_rng()));
+        let mut org = Org::new(OrgSecretKey::random(&mut thread_rng()));
+        let records = Arc::new(Mutex::new(Vec::new()));
+        org.set_issuance_audit({
+            let records = records.clone();
+            move |record| records.lock().unwrap().push(*record)
+        });
+
+        let (mut u_channel, mut o_channel) = DuplexTransport::<Json>::pair();
+        let (nym, _) = block_on(try_join(
+            user.generate_nym(&mut u_channel),
+            org.generate_nym(&mut o_channel),
+        ))
+        .unwrap();
+        let (cred, _) = block_on(try_join(
+            user.issue_credential(&mut u_channel, nym, org.public_key(), 3),
+            org.issue_credential(&mut o_channel, nym, 3),
+        ))
+        .unwrap();
+
+        let records = records.lock().unwrap();
+        assert_matches!(&records[..], [r] if r.nym == nym && r.epoch == 3);
+        assert_eq!(records[0].publics[1].h1, org.public_key().issuer_point(3));
+
+        let bytes = cred.issuance_proof_bytes();
+        assert_eq!(bytes.len(), 2 * dlog_eq::Transcript::LENGTH);
+        let (t1, t2) = bytes.split_at(dlog_eq::Transcript::LENGTH);
+        let t1 = dlog_eq::Transcript::from_bytes(t1).unwrap();
+        let t2 = dlog_eq::Transcript::from_bytes(t2).unwrap();
+        assert_matches!(
+            t1.verify(Publics {
+                g1: &RISTRETTO_BASEPOINT_POINT,
+                h1: &org.public_key().epoch_point(3),
+                g2: &cred.b,
+                h2: &cred.A,
+            }),
+            Ok(_)
+        );
+        assert_matches!(
+            t2.verify(Publics {
+                g1: &RISTRETTO_BASEPOINT_POINT,
+                h1: &org.public_key().issuer_point(3),
+                g2: &(cred.a + cred.A),
+                h2: &cred.B,
+            }),
+            Ok(_)
+        );
+    }
+
+    #[test]
+    fn issuance_blinding() {
+        let user = User::new(UserSecretKey::random(&mut thread_rng()));
+        let mut org = Org::new(OrgSecretKey::random(&mut thread_rng()));
+        let records = Arc::new(Mutex::new(Vec::new()));
+        org.set_issuance_audit({
+            let records = records.clone();
+            move |record| records.lock().unwrap().push(*record)
+        });
+
+        let (mut u_channel, mut o_channel) = DuplexTransport::<Json>::pair();
+        let (nym, _) = block_on(try_join(
+            user.generate_nym(&mut u_channel),
+            org.generate_nym(&mut o_channel),
+        ))
+        .unwrap();
+        let ((cred, γ), _) = block_on(try_join(
+            user.issue_credential_with_blinding(&mut u_channel, nym, org.public_key(), 0),
+            org.issue_credential(&mut o_channel, nym, 0),
+        ))
+        .unwrap();
+
+        let [p1, p2] = records.lock().unwrap()[0].publics;
+        assert_eq!(cred.b, γ * p1.g2);
+        assert_eq!(cred.A, γ * p1.h2);
+        assert_eq!(cred.a + cred.A, γ * p2.g2);
+        assert_eq!(cred.B, γ * p2.h2);
+    }
+
+    #[test]
+    fn cred_transfer() {
+        let user = User::new(UserSecretKey::random(&mut thread_rng()));
+        let org1 = Org::new(OrgSecretKey::random(&mut thread_rng()));
+        let org2 = Org::new(OrgSecretKey::random(&mut thread_rng()));
+
+        let (mut u_channel, mut o_channel) = DuplexTransport::<Json>::pair();
+        let (nym, _) = block_on(try_join(
+            user.generate_nym(&mut u_channel),
+            org1.generate_nym(&mut o_channel),
+        ))
+        .unwrap();
+
+        let (cred, _) = block_on(try_join(
+            user.issue_credential(&mut u_channel, nym, org1.public_key(), 0),
+            org1.issue_credential(&mut o_channel, nym, 0),
+        ))
+        .unwrap();
+
+        let res = block_on(try_join(
+            user.transfer_credential(&mut u_channel, nym, cred),
+            org2.transfer_credential(&mut o_channel, nym, cred, org1.public_key(), 0),
+        ));
+        assert_matches!(res, Ok(_));
+    }
+
+    #[cfg(feature = "rayon")]
+    #[test]
+    fn parallel_issuance() {
+        let user = User::new(UserSecretKey::random(&mut thread_rng()));
+        let other = User::new(UserSecretKey::random(&mut thread_rng()));
+        let org = Org::new(OrgSecretKey::random(&mut thread_rng()));
+
+        let (mut u_channel, mut o_channel) = DuplexTransport::<Json>::pair();
+        let mut generate = |user: &User| {
+            let (nym, _) = block_on(try_join(
+                user.generate_nym(&mut u_channel),
+                org.generate_nym(&mut o_channel),
+            ))
+            .unwrap();
+            nym
+        };
+        let nym = generate(&user);
+        let nyms = [nym, generate(&other), nym];
+
+        let prepared = org.issue_credentials_parallel(&nyms, 3);
+        let bases: Vec<_> = prepared.iter().map(|p| p.points().0).collect();
+        assert_ne!(
+            bases[0], bases[2],
+            "each credential should get a fresh base"
+        );
+        for (prepared, holder) in prepared.into_iter().zip([&user, &other, &user]) {
+            let nym = prepared.nym();
+            let (cred, _) = block_on(try_join(
+                holder.issue_credential(&mut u_channel, nym, org.public_key(), 3),
+                org.issue_prepared_credential(&mut o_channel, prepared),
+            ))
+            .unwrap();
+            assert_matches!(cred.verify_against(&org.public_key()), Ok(_));
+        }
+    }
+
+    #[test]
+    fn cred_precheck() {
+        let user = User::new(UserSecretKey::random(&mut thread_rng()));
+        let org1 = Org::new(OrgSecretKey::random(&mut thread_rng()));
+        let org2 = Org::new(OrgSecretKey::random(&mut thread_rng()));
+
+        let (mut u_channel, mut o_channel) = DuplexTransport::<Json>::pair();
+        let (nym, _) = block_on(try_join(
+            user.generate_nym(&mut u_channel),
+            org1.generate_nym(&mut o_channel),
+        ))
+        .unwrap();
+
+        let (cred, _) = block_on(try_join(
+            user.issue_credential(&mut u_channel, nym, org1.public_key(), 0),
+            org1.issue_credential(&mut o_channel, nym, 0),
+        ))
+        .unwrap();
+
+        assert_matches!(cred.verify_against(&org1.public_key()), Ok(_));
+        assert_matches!(
+            cred.verify_against(&org2.public_key()),
+            Err(Error::BadProof { .. })
+        );
+
+        let mut tampered = cred;
+        tampered.T1.y += Scalar::ONE;
+        assert_matches!(
+            tampered.verify_against(&org1.public_key()),
+            Err(Error::BadProof { .. })
+        );
+    }
+
+    #[test]
+    fn cred_show() {
+        let user = User::new(UserSecretKey::random(&mut thread_rng()));
+        let org1 = Org::new(OrgSecretKey::random(&mut thread_rng()));
+        let org2 = Org::new(OrgSecretKey::random(&mut thread_rng()));
+
+        let (mut u_channel, mut o_channel) = DuplexTransport::<Json>::pair();
+        let (nym1, _) = block_on(try_join(
+            user.generate_nym(&mut u_channel),
+            org1.generate_nym(&mut o_channel),
+        ))
+        .unwrap();
+        let (cred, _) = block_on(try_join(
+            user.issue_credential(&mut u_channel, nym1, org1.public_key(), 0),
+            org1.issue_credential(&mut o_channel, nym1, 0),
+        ))
+        .unwrap();
+
+        let (mut u_channel, mut o_channel) = DuplexTransport::<Json>::pair();
+        let (nym2, _) = block_on(try_join(
+            user.generate_nym(&mut u_channel),
+            org2.generate_nym(&mut o_channel),
+        ))
+        .unwrap();
+
+        let show = user.show_credential(nym2, cred);
+        assert_matches!(org2.verify_show(&show, org1.public_key()), Ok(_));
+        assert_ne!(
+            show.cred().a,
+            nym1.a,
+            "showing should not reveal the original nym"
+        );
+        assert_matches!(
+            org2.verify_show(&show, org2.public_key()),
+            Err(Error::BadProof { .. })
+        );
+
+        let other = User::new(UserSecretKey::random(&mut thread_rng()));
+        let stolen = other.show_credential(nym2, cred);
+        assert_matches!(
+            org2.verify_show(&stolen, org1.public_key()),
+            Err(Error::BadProof { .. })
+        );
+
+        let json = serde_json::to_vec(&show).unwrap();
+        let show2: CredShow = serde_json::from_slice(&json).unwrap();
+        assert_matches!(org2.verify_show(&show2, org1.public_key()), Ok(_));
     }
-}
 
-#[cfg(test)]
-mod test {
-    use std::assert_matches::assert_matches;
+    #[test]
+    fn cred_attributes() {
+        let user = User::new(UserSecretKey::random(&mut thread_rng()));
+        let org1 = Org::new(OrgSecretKey::random(&mut thread_rng()));
+        let org2 = Org::new(OrgSecretKey::random(&mut thread_rng()));
+        let attributes = [Scalar::from(18u64), Scalar::from(7u64), Scalar::from(42u64)];
 
-    use curve25519_dalek::RistrettoPoint;
-    use futures::{
-        channel::mpsc::{self, UnboundedReceiver, UnboundedSender},
-        executor::block_on,
-        future::try_join,
-        io,
-        sink::SinkExt as _,
-        stream::StreamExt as _,
-    };
-    use merlin::Transcript;
-    use rand::thread_rng;
-    use serde::{Deserialize, Serialize};
+        let (mut u_channel, mut o_channel) = DuplexTransport::<Json>::pair();
+        let (nym1, _) = block_on(try_join(
+            user.generate_nym(&mut u_channel),
+            org1.generate_nym(&mut o_channel),
+        ))
+        .unwrap();
+        let (cred, _): (AttrCred, _) = block_on(try_join(
+            user.issue_credential_with_attributes(
+                &mut u_channel,
+                nym1,
+                org1.public_key(),
+                0,
+                &attributes,
+            ),
+            org1.issue_credential_with_attributes(&mut o_channel, nym1, 0, &attributes),
+        ))
+        .unwrap();
+        assert!(cred.attributes().eq(&attributes));
 
-    use crate::{
-        key::{OrgSecretKey, UserSecretKey},
-        transport::LocalTransport,
-        Error, Nym,
-    };
+        let (mut u_channel, mut o_channel) = DuplexTransport::<Json>::pair();
+        let (nym2, _) = block_on(try_join(
+            user.generate_nym(&mut u_channel),
+            org2.generate_nym(&mut o_channel),
+        ))
+        .unwrap();
+
+        let show = user.show_attributes(nym2, &cred, &[0, 2]).unwrap();
+        assert_matches!(org2.verify_attributes(&show, org1.public_key()), Ok(_));
+        assert_eq!(
+            show.attributes().collect::<Vec<_>>(),
+            [(0, attributes[0]), (2, attributes[2])]
+        );
+        assert_eq!(show.attribute_count(), 3);
+
+        let show = user.show_attributes(nym2, &cred, &[]).unwrap();
+        assert_matches!(org2.verify_attributes(&show, org1.public_key()), Ok(_));
+        assert_eq!(show.attributes().count(), 0);
 
-    use super::{Org, User};
+        let mut forged = user.show_attributes(nym2, &cred, &[1]).unwrap();
+        let ShownAttribute::Disclosed { blinding, .. } = forged.attributes[1].0 else {
+            panic!("attribute 1 should be disclosed");
+        };
+        forged.attributes[1].0 = ShownAttribute::Disclosed {
+            value: Scalar::from(8u64),
+            blinding,
+        };
+        assert_matches!(
+            org2.verify_attributes(&forged, org1.public_key()),
+            Err(Error::BadProof { .. })
+        );
 
-    struct TestTransport(
-        UnboundedSender<(String, Vec<u8>)>,
-        UnboundedReceiver<(String, Vec<u8>)>,
-    );
+        let mut forged = user.show_attributes(nym2, &cred, &[1]).unwrap();
+        forged.attributes[0].0 = ShownAttribute::Hidden {
+            commitment: range::commit_scalar(&attributes[0], &Scalar::ONE),
+        };
+        assert_matches!(
+            org2.verify_attributes(&forged, org1.public_key()),
+            Err(Error::BadProof { .. })
+        );
 
-    impl TestTransport {
-        pub fn new() -> (Self, Self) {
-            let (s1, r2) = mpsc::unbounded();
-            let (s2, r1) = mpsc::unbounded();
-            (Self(s1, r1), Self(s2, r2))
-        }
+        let mut forged = user.show_attributes(nym2, &cred, &[1]).unwrap();
+        forged.attributes.remove(0);
+        assert_matches!(
+            org2.verify_attributes(&forged, org1.public_key()),
+            Err(Error::BadProof { .. })
+        );
+
+        assert_matches!(
+            user.show_attributes(nym2, &cred, &[0, 3]),
+            Err(Error::UnknownAttribute(3))
+        );
+
+        let other = User::new(UserSecretKey::random(&mut thread_rng()));
+        let stolen = other.show_attributes(nym2, &cred, &[0]).unwrap();
+        assert_matches!(
+            org2.verify_attributes(&stolen, org1.public_key()),
+            Err(Error::BadProof { .. })
+        );
     }
 
-    impl LocalTransport for TestTransport {
-        async fn receive<V: for<'a> Deserialize<'a>>(
-            &mut self,
-            label: &'static [u8],
-        ) -> Result<V, io::Error> {
-            let label_display = String::from_utf8_lossy(label);
-            let (recv_label, bytes) = self
-                .1
-                .next()
-                .await
-                .expect(&format!("expected `{label_display}`, got nothing"));
-            assert_eq!(
-                recv_label.as_bytes(),
-                label,
-                "expected `{label_display}`, got `{recv_label}`",
-            );
-            let value = serde_json::from_slice(&bytes).expect(&format!(
-                "expected valid JSON to deserialize `{label_display}`",
-            ));
-            Ok(value)
-        }
-
-        async fn send<V: Serialize>(
-            &mut self,
-            label: &'static [u8],
-            value: V,
-        ) -> Result<(), io::Error> {
-            let label_display = String::from_utf8_lossy(label);
-            self.0
-                .send((
-                    label_display.clone().into(),
-                    serde_json::to_vec(&value).expect(&format!(
-                        "expected serialization of `{label_display}` to succeed"
-                    )),
-                ))
-                .await
-                .expect(&format!("expected sending of `{label_display}` to succeed"));
-            Ok(())
+    #[test]
+    fn batch_cred_transfer() {
+        let user = User::new(UserSecretKey::random(&mut thread_rng()));
+        let org1 = Org::new(OrgSecretKey::random(&mut thread_rng()));
+        let org2 = Org::new(OrgSecretKey::random(&mut thread_rng()));
+
+        let (mut u_channel, mut o_channel) = DuplexTransport::<Json>::pair();
+        let (nym1, _) = block_on(try_join(
+            user.generate_nym(&mut u_channel),
+            org1.generate_nym(&mut o_channel),
+        ))
+        .unwrap();
+        let (nym2, _) = block_on(try_join(
+            user.generate_nym(&mut u_channel),
+            org2.generate_nym(&mut o_channel),
+        ))
+        .unwrap();
+
+        let mut creds = Vec::new();
+        for epoch in 0..3 {
+            let (cred, _) = block_on(try_join(
+                user.issue_credential(&mut u_channel, nym1, org1.public_key(), epoch),
+                org1.issue_credential(&mut o_channel, nym1, epoch),
+            ))
+            .unwrap();
+            creds.push((nym2, cred));
         }
+
+        let res = block_on(try_join(
+            user.transfer_credentials(&mut u_channel, &creds),
+            org2.transfer_credentials(&mut o_channel, &creds, org1.public_key(), 0),
+        ));
+        assert_matches!(res, Ok(_));
+
+        let (mut u_channel, mut o_channel) = DuplexTransport::<Json>::pair();
+        let res = block_on(try_join(
+            user.transfer_credentials(&mut u_channel, &creds),
+            org2.transfer_credentials(&mut o_channel, &creds, org1.public_key(), 1),
+        ));
+        assert_matches!(res, Err(Error::Expired));
+
+        let (mut u_channel, mut o_channel) = DuplexTransport::<Json>::pair();
+        let other = User::new(UserSecretKey::random(&mut thread_rng()));
+        let res = block_on(try_join(
+            other.transfer_credentials(&mut u_channel, &creds),
+            org2.transfer_credentials(&mut o_channel, &creds, org1.public_key(), 0),
+        ));
+        assert_matches!(res, Err(Error::BadProof { .. }));
     }
 
     #[test]
-    fn nym_generation() {
+    fn cred_conjunction() {
         let user = User::new(UserSecretKey::random(&mut thread_rng()));
-        let org = Org::new(OrgSecretKey::random(&mut thread_rng()));
+        let org_a = Org::new(OrgSecretKey::random(&mut thread_rng()));
+        let org_b = Org::new(OrgSecretKey::random(&mut thread_rng()));
+        let verifier = Org::new(OrgSecretKey::random(&mut thread_rng()));
 
-        let (mut u_channel, mut o_channel) = TestTransport::new();
-        let (n1, n2) = block_on(try_join(
+        let mut creds = Vec::new();
+        for org in [&org_a, &org_b] {
+            let (mut u_channel, mut o_channel) = DuplexTransport::<Json>::pair();
+            let (nym, _) = block_on(try_join(
+                user.generate_nym(&mut u_channel),
+                org.generate_nym(&mut o_channel),
+            ))
+            .unwrap();
+            let (cred, _) = block_on(try_join(
+                user.issue_credential(&mut u_channel, nym, org.public_key(), 0),
+                org.issue_credential(&mut o_channel, nym, 0),
+            ))
+            .unwrap();
+            creds.push(cred);
+        }
+        let (mut u_channel, mut o_channel) = DuplexTransport::<Json>::pair();
+        let (nym, _) = block_on(try_join(
             user.generate_nym(&mut u_channel),
-            org.generate_nym(&mut o_channel),
+            verifier.generate_nym(&mut o_channel),
         ))
         .unwrap();
-        assert_eq!(n1, n2, "user and org should compute the same nym");
-        assert_eq!(n1.a * user.sk.key.exponent(), n1.b, "nym should be valid");
+
+        let res = block_on(try_join(
+            user.prove_conjunction(&mut u_channel, nym, creds[0], creds[1]),
+            verifier.verify_conjunction(
+                &mut o_channel,
+                nym,
+                (creds[0], org_a.public_key()),
+                (creds[1], org_b.public_key()),
+                0,
+            ),
+        ));
+        assert_matches!(res, Ok(_));
+
+        let (mut u_channel, mut o_channel) = DuplexTransport::<Json>::pair();
+        let other = User::new(UserSecretKey::random(&mut thread_rng()));
+        let res = block_on(try_join(
+            other.prove_conjunction(&mut u_channel, nym, creds[0], creds[1]),
+            verifier.verify_conjunction(
+                &mut o_channel,
+                nym,
+                (creds[0], org_a.public_key()),
+                (creds[1], org_b.public_key()),
+                0,
+            ),
+        ));
+        assert_matches!(res, Err(Error::BadProof { .. }));
+
+        let res = block_on(verifier.verify_conjunction(
+            &mut o_channel,
+            nym,
+            (creds[0], org_b.public_key()),
+            (creds[1], org_a.public_key()),
+            0,
+        ));
+        assert_matches!(res, Err(Error::BadProof { .. }));
     }
 
     #[test]
-    fn nym_authentication() {
+    fn range_attributes() {
         let user = User::new(UserSecretKey::random(&mut thread_rng()));
         let org = Org::new(OrgSecretKey::random(&mut thread_rng()));
+        let verifier = Org::new(OrgSecretKey::random(&mut thread_rng()));
 
-        let (mut u_channel, mut o_channel) = TestTransport::new();
+        let (mut u_channel, mut o_channel) = DuplexTransport::<Json>::pair();
         let (nym, _) = block_on(try_join(
             user.generate_nym(&mut u_channel),
             org.generate_nym(&mut o_channel),
         ))
         .unwrap();
+        let pk = org.public_key();
+        let (cred, _) = block_on(try_join(
+            user.issue_credential_with_range_attributes(&mut u_channel, nym, pk, 0, &[30, 7]),
+            org.issue_credential_with_range_attributes(&mut o_channel, nym, 0, &[30, 7]),
+        ))
+        .unwrap();
+        assert_eq!(cred.attributes().collect::<Vec<_>>(), [30, 7]);
+
+        let (mut u_channel, mut o_channel) = DuplexTransport::<Json>::pair();
+        let (nym, _) = block_on(try_join(
+            user.generate_nym(&mut u_channel),
+            verifier.generate_nym(&mut o_channel),
+        ))
+        .unwrap();
+        let show = user.prove_attribute_range(nym, &cred, 0, 18, 150).unwrap();
+        assert_eq!(show.index(), 0);
+        assert_matches!(verifier.verify_attribute_range(&show, pk, 18, 150), Ok(()));
+        assert_matches!(
+            verifier.verify_attribute_range(&show, pk, 21, 150),
+            Err(Error::BadProof { .. })
+        );
+        assert_matches!(
+            user.prove_attribute_range(nym, &cred, 1, 18, 150),
+            Err(Error::OutOfRange)
+        );
+        let show = user.prove_attribute_range(nym, &cred, 1, 0, 17).unwrap();
+        assert_matches!(verifier.verify_attribute_range(&show, pk, 0, 17), Ok(()));
+
+        let mut forged = user.prove_attribute_range(nym, &cred, 0, 18, 150).unwrap();
+        let blinding = Scalar::random(&mut thread_rng());
+        forged.commitment = range::commit(40, &blinding);
+        forged.range = range::prove_noninteractive(
+            range::Publics {
+                commitment: &forged.commitment,
+                min: 31,
+                max: 150,
+            },
+            range::Secrets {
+                value: 40,
+                blinding: &blinding,
+            },
+        )
+        .unwrap();
+        assert_matches!(
+            verifier.verify_attribute_range(&forged, pk, 31, 150),
+            Err(Error::BadProof { .. })
+        );
 
+        let (mut u_channel, mut o_channel) = DuplexTransport::<Json>::pair();
         let res = block_on(try_join(
-            user.authenticate_nym(&mut u_channel, nym),
-            org.authenticate_nym(&mut o_channel, nym),
+            user.issue_credential_with_range_attributes(&mut u_channel, nym, pk, 0, &[30]),
+            org.issue_credential_with_range_attributes(&mut o_channel, nym, 0, &[17]),
         ));
-        assert_matches!(res, Ok(_));
+        assert_matches!(res, Err(Error::BadProof { .. }));
     }
 
     #[test]
-    fn cred_issuance() {
+    fn multi_show() {
         let user = User::new(UserSecretKey::random(&mut thread_rng()));
-        let org = Org::new(OrgSecretKey::random(&mut thread_rng()));
+        let other = User::new(UserSecretKey::random(&mut thread_rng()));
+        let orgs: Vec<_> = (0..3)
+            .map(|_| Org::new(OrgSecretKey::random(&mut thread_rng())))
+            .collect();
+        let verifier = Org::new(OrgSecretKey::random(&mut thread_rng()));
 
-        let (mut u_channel, mut o_channel) = TestTransport::new();
+        let issue = |user: &User, org: &Org| {
+            let (mut u_channel, mut o_channel) = DuplexTransport::<Json>::pair();
+            let (nym, _) = block_on(try_join(
+                user.generate_nym(&mut u_channel),
+                org.generate_nym(&mut o_channel),
+            ))
+            .unwrap();
+            let (cred, _) = block_on(try_join(
+                user.issue_credential(&mut u_channel, nym, org.public_key(), 0),
+                org.issue_credential(&mut o_channel, nym, 0),
+            ))
+            .unwrap();
+            (cred, org.public_key())
+        };
+        let creds: Vec<_> = orgs.iter().map(|org| issue(&user, org)).collect();
+        let keys: Vec<_> = creds.iter().map(|(_, key)| *key).collect();
+        let (mut u_channel, mut o_channel) = DuplexTransport::<Json>::pair();
         let (nym, _) = block_on(try_join(
             user.generate_nym(&mut u_channel),
-            org.generate_nym(&mut o_channel),
+            verifier.generate_nym(&mut o_channel),
         ))
         .unwrap();
 
-        let (cred, _) = block_on(try_join(
-            user.issue_credential(&mut u_channel, nym, org.public_key()),
-            org.issue_credential(&mut o_channel, nym),
+        let show = user.prove_multi(nym, &creds).unwrap();
+        assert_eq!(show.creds().len(), 3);
+        assert_matches!(verifier.verify_multi(&show, &keys), Ok(()));
+        assert_matches!(
+            verifier.verify_multi(&show, &keys[..2]),
+            Err(Error::LengthMismatch {
+                expected: 3,
+                got: 2
+            })
+        );
+
+        let (foreign, _) = issue(&other, &orgs[1]);
+        let mut swapped = show.clone();
+        swapped.creds[1] = foreign;
+        assert_matches!(
+            verifier.verify_multi(&swapped, &keys),
+            Err(Error::BadProof { .. })
+        );
+
+        let mut mixed = creds.clone();
+        mixed[1].0 = foreign;
+        let show = user.prove_multi(nym, &mixed).unwrap();
+        assert_matches!(
+            verifier.verify_multi(&show, &keys),
+            Err(Error::BadProof { .. })
+        );
+    }
+
+    #[test]
+    fn cred_commitment() {
+        let user = User::new(UserSecretKey::random(&mut thread_rng()));
+        let org = Org::new(OrgSecretKey::random(&mut thread_rng()));
+
+        let (mut u_channel, mut o_channel) = DuplexTransport::<Json>::pair();
+        let (nym, _) = block_on(try_join(
+            user.generate_nym(&mut u_channel),
+            org.generate_nym(&mut o_channel),
         ))
         .unwrap();
+        let mut issue = || {
+            block_on(try_join(
+                user.issue_credential(&mut u_channel, nym, org.public_key(), 0),
+                org.issue_credential(&mut o_channel, nym, 0),
+            ))
+            .unwrap()
+            .0
+        };
+        let (cred1, cred2) = (issue(), issue());
+        assert_eq!(cred1.commitment(), cred1.commitment());
+        assert_ne!(cred1.commitment(), cred2.commitment());
 
-        assert_eq!(cred.a * user.sk.key.exponent(), cred.b);
-        assert_eq!(cred.b * org.sk.key2.exponent(), cred.A);
-        assert_eq!((cred.a + cred.A) * org.sk.key1.exponent(), cred.B);
+        let challenge = |cred: &Cred| {
+            let mut t = Transcript::new(b"statement");
+            t.commit(b"cred", cred);
+            t.challenge::<Scalar>(b"c")
+        };
+        assert_eq!(challenge(&cred1), challenge(&cred1));
+        assert_ne!(challenge(&cred1), challenge(&cred2));
     }
 
     #[test]
-    fn cred_transfer() {
+    fn cred_expiration() {
         let user = User::new(UserSecretKey::random(&mut thread_rng()));
         let org1 = Org::new(OrgSecretKey::random(&mut thread_rng()));
         let org2 = Org::new(OrgSecretKey::random(&mut thread_rng()));
 
-        let (mut u_channel, mut o_channel) = TestTransport::new();
+        let (mut u_channel, mut o_channel) = DuplexTransport::<Json>::pair();
         let (nym, _) = block_on(try_join(
             user.generate_nym(&mut u_channel),
             org1.generate_nym(&mut o_channel),
@@ -527,14 +3563,35 @@ mod test {
         .unwrap();
 
         let (cred, _) = block_on(try_join(
-            user.issue_credential(&mut u_channel, nym, org1.public_key()),
-            org1.issue_credential(&mut o_channel, nym),
+            user.issue_credential(&mut u_channel, nym, org1.public_key(), 5),
+            org1.issue_credential(&mut o_channel, nym, 5),
         ))
         .unwrap();
+        assert_eq!(cred.epoch(), 5);
+        assert_eq!((cred.a + cred.A) * org1.sk.issuer_exponent(5), cred.B);
+        assert_ne!(
+            org1.public_key().issuer_point(5),
+            org1.public_key().issuer_point(6)
+        );
+
+        let res = block_on(try_join(
+            user.transfer_credential(&mut u_channel, nym, cred),
+            org2.transfer_credential(&mut o_channel, nym, cred, org1.public_key(), 6),
+        ));
+        assert_matches!(res, Err(Error::Expired));
+
+        let (mut u_channel, mut o_channel) = DuplexTransport::<Json>::pair();
+        let forged = Cred { epoch: 6, ..cred };
+        let res = block_on(try_join(
+            user.transfer_credential(&mut u_channel, nym, forged),
+            org2.transfer_credential(&mut o_channel, nym, forged, org1.public_key(), 6),
+        ));
+        assert_matches!(res, Err(Error::BadProof { .. }));
 
+        let (mut u_channel, mut o_channel) = DuplexTransport::<Json>::pair();
         let res = block_on(try_join(
             user.transfer_credential(&mut u_channel, nym, cred),
-            org2.transfer_credential(&mut o_channel, nym, cred, org1.public_key()),
+            org2.transfer_credential(&mut o_channel, nym, cred, org1.public_key(), 5),
         ));
         assert_matches!(res, Ok(_));
     }
@@ -544,7 +3601,7 @@ mod test {
         let user = User::new(UserSecretKey::random(&mut thread_rng()));
         let org = Org::new(OrgSecretKey::random(&mut thread_rng()));
 
-        let (mut u_channel, mut o_channel) = TestTransport::new();
+        let (mut u_channel, mut o_channel) = DuplexTransport::<Json>::pair();
         let (n1, n2) = block_on(try_join(
             user.generate_nym(&mut u_channel),
             org.generate_nym(&mut o_channel),
@@ -573,4 +3630,204 @@ mod test {
         let res = n2.verify(make_t(), &sig);
         assert_matches!(res, Err(Error::BadSignature));
     }
+
+    #[test]
+    fn verify_compressed() {
+        let user = User::new(UserSecretKey::random(&mut thread_rng()));
+        let org = Org::new(OrgSecretKey::random(&mut thread_rng()));
+
+        let (mut u_channel, mut o_channel) = DuplexTransport::<Json>::pair();
+        let (nym, _) = block_on(try_join(
+            user.generate_nym(&mut u_channel),
+            org.generate_nym(&mut o_channel),
+        ))
+        .unwrap();
+        let make_t = || {
+            let mut t = Transcript::new(b"test-transcript");
+            t.append_message(b"test", b"please sign this!");
+            t
+        };
+        let sig = user.sk.sign(make_t(), &nym);
+        let (a, b) = nym.compressed();
+        let res = Nym::verify_compressed(&a, &b, make_t(), &sig);
+        assert_matches!(res, Ok(_));
+
+        let res = Nym::verify_compressed(&b, &a, make_t(), &sig);
+        assert_matches!(res, Err(Error::BadSignature));
+
+        let bad = CompressedRistretto([0xff; 32]);
+        let res = Nym::verify_compressed(&a, &bad, make_t(), &sig);
+        assert_matches!(res, Err(Error::BadSignature));
+        let res = Nym::verify_compressed(&bad, &b, make_t(), &sig);
+        assert_matches!(res, Err(Error::BadSignature));
+
+        let mut flipped = a.to_bytes();
+        flipped[0] ^= 1;
+        let res = Nym::verify_compressed(&CompressedRistretto(flipped), &b, make_t(), &sig);
+        assert_matches!(res, Err(Error::BadSignature));
+    }
+
+    #[test]
+    fn batch_verify_signatures() {
+        let users: Vec<_> = (0..4)
+            .map(|_| User::new(UserSecretKey::random(&mut thread_rng())))
+            .collect();
+        let org = Org::new(OrgSecretKey::random(&mut thread_rng()));
+
+        let nyms: Vec<_> = users
+            .iter()
+            .map(|user| {
+                let (mut u_channel, mut o_channel) = DuplexTransport::<Json>::pair();
+                block_on(try_join(
+                    user.generate_nym(&mut u_channel),
+                    org.generate_nym(&mut o_channel),
+                ))
+                .unwrap()
+                .1
+            })
+            .collect();
+        let make_t = |i: usize| {
+            let mut t = Transcript::new(b"test-transcript");
+            t.append_message(b"request", &i.to_be_bytes());
+            t
+        };
+        let sigs: Vec<_> = users
+            .iter()
+            .zip(&nyms)
+            .enumerate()
+            .map(|(i, (user, nym))| user.sk.sign(make_t(i), nym))
+            .collect();
+
+        let items: Vec<_> = (0..4).map(|i| (&nyms[i], make_t(i), &sigs[i])).collect();
+        for (nym, t, sig) in &items {
+            assert_matches!(nym.verify(t.clone(), sig), Ok(_));
+        }
+        assert_matches!(Nym::batch_verify(&items), Ok(_));
+        assert_matches!(Nym::batch_verify(&[]), Ok(_));
+
+        let mut bad = items.clone();
+        bad[2].1 = make_t(7);
+        assert_matches!(Nym::batch_verify(&bad), Err(Error::BadSignature));
+
+        let mut bad = items.clone();
+        bad[1].2 = &sigs[3];
+        assert_matches!(Nym::batch_verify(&bad), Err(Error::BadSignature));
+
+        let mut forged = sigs[0].to_bytes();
+        forged[32] ^= 1;
+        let forged = Signature::from_bytes(&forged).unwrap();
+        let mut bad = items.clone();
+        bad[0].2 = &forged;
+        assert_matches!(Nym::batch_verify(&bad), Err(Error::BadSignature));
+
+        let mut bad = items;
+        bad[3].0 = &nyms[0];
+        assert_matches!(Nym::batch_verify(&bad), Err(Error::BadSignature));
+    }
+
+    #[test]
+    fn sign_with_context() {
+        let user = User::new(UserSecretKey::random(&mut thread_rng()));
+        let org = Org::new(OrgSecretKey::random(&mut thread_rng()));
+
+        let (mut u_channel, mut o_channel) = DuplexTransport::<Json>::pair();
+        let (nym, _) = block_on(try_join(
+            user.generate_nym(&mut u_channel),
+            org.generate_nym(&mut o_channel),
+        ))
+        .unwrap();
+
+        let sig = user
+            .sk
+            .sign_with_context(b"ctx", b"please sign this!", &nym);
+        let res = nym.verify_with_context(b"ctx", b"please sign this!", &sig);
+        assert_matches!(res, Ok(_));
+
+        let res = nym.verify_with_context(b"ctx", b"please sign that!", &sig);
+        assert_matches!(res, Err(Error::BadSignature));
+
+        let res = nym.verify_with_context(b"other", b"please sign this!", &sig);
+        assert_matches!(res, Err(Error::BadSignature));
+
+        let res = nym.verify_with_context(b"ctxplease", b" sign this!", &sig);
+        assert_matches!(res, Err(Error::BadSignature));
+    }
+
+    #[test]
+    fn rerandomized_nym() {
+        let user = User::new(UserSecretKey::random(&mut thread_rng()));
+        let other = User::new(UserSecretKey::random(&mut thread_rng()));
+        let org = Org::new(OrgSecretKey::random(&mut thread_rng()));
+
+        let (mut u_channel, mut o_channel) = DuplexTransport::<Json>::pair();
+        let (nym, _) = block_on(try_join(
+            user.generate_nym(&mut u_channel),
+            org.generate_nym(&mut o_channel),
+        ))
+        .unwrap();
+
+        let (nym2, γ) = user.rerandomize_nym(&nym);
+        assert_ne!(nym, nym2);
+        assert_eq!(nym2.a, γ * nym.a);
+        assert_eq!(nym2.b, γ * nym.b);
+
+        let res = block_on(try_join(
+            user.authenticate_nym(&mut u_channel, nym2),
+            org.authenticate_nym(&mut o_channel, nym2),
+        ));
+        assert_matches!(res, Ok(_));
+
+        let proof = user.prove_same_holder(&nym, &nym2);
+        assert_matches!(nym2.verify_same_holder(&nym, &proof), Ok(_));
+        let proof = other.prove_same_holder(&nym, &nym2);
+        assert_matches!(
+            nym2.verify_same_holder(&nym, &proof),
+            Err(Error::BadProof { .. })
+        );
+    }
+
+    #[test]
+    fn deterministic_nym() {
+        let user = User::new(UserSecretKey::from_seed(&[7; 32]));
+        let org_pk = OrgSecretKey::random(&mut thread_rng()).to_public();
+        let other_pk = OrgSecretKey::random(&mut thread_rng()).to_public();
+
+        let (γ, a_, b_) = user.deterministic_nym(&org_pk);
+        assert_eq!(a_, γ * RISTRETTO_BASEPOINT_POINT);
+        assert_eq!(b_, γ * user.public_key().point());
+        let again = User::new(UserSecretKey::from_seed(&[7; 32]));
+        assert_eq!(again.deterministic_nym(&org_pk), (γ, a_, b_));
+        assert_ne!(user.deterministic_nym(&other_pk).1, a_);
+        let other = User::new(UserSecretKey::random(&mut thread_rng()));
+        assert_ne!(other.deterministic_nym(&org_pk).1, a_);
+    }
+
+    #[test]
+    fn recovered_nym() {
+        let user = User::new(UserSecretKey::from_seed(&[7; 32]));
+        let org = Org::new(OrgSecretKey::random(&mut thread_rng()));
+        let other = Org::new(OrgSecretKey::random(&mut thread_rng()));
+
+        let generate = |user: &User, org: &Org| {
+            let (mut u_channel, mut o_channel) = DuplexTransport::<Json>::pair();
+            let (n1, n2) = block_on(try_join(
+                user.generate_deterministic_nym(&mut u_channel, &org.public_key()),
+                org.generate_nym(&mut o_channel),
+            ))
+            .unwrap();
+            assert_eq!(n1, n2, "user and org should compute the same nym");
+            n1
+        };
+        let nym = generate(&user, &org);
+        let recovered = User::new(UserSecretKey::from_seed(&[7; 32]));
+        assert_eq!(generate(&recovered, &org), nym);
+        assert_ne!(generate(&user, &other), nym);
+
+        let (mut u_channel, mut o_channel) = DuplexTransport::<Json>::pair();
+        let res = block_on(try_join(
+            recovered.authenticate_nym(&mut u_channel, nym),
+            org.authenticate_nym(&mut o_channel, nym),
+        ));
+        assert_matches!(res, Ok(_));
+    }
 }
@@ -1,13 +1,17 @@
 //! Blinded zero-knowledge proof of equality of discrete logarithms (aka protocol Γ)
 
 use crate::{
-    error::{Error, Result},
+    error::{Error, ProofStage, Result},
+    group::{Group, Ristretto},
+    labels::dlog_eq::{CHALLENGE, COMMITMENTS, RESPONSE},
     transport::LocalTransport,
 };
-use curve25519_dalek::{RistrettoPoint, Scalar};
-use rand::thread_rng;
+use rand::{CryptoRng, RngCore};
 
-use super::dlog_eq::{self, Transcript};
+use super::{
+    dlog_eq::{self, PublicsOf, SecretsOf, TranscriptOf},
+    ensure, rng,
+};
 
 /// Public parameters
 pub type Publics<'a> = dlog_eq::Publics<'a>;
@@ -15,66 +19,235 @@ pub type Publics<'a> = dlog_eq::Publics<'a>;
 /// Secret parameters for the prover
 pub type ProverSecrets<'a> = dlog_eq::Secrets<'a>;
 
-/// Secret parameters for the verifier
-#[derive(Copy, Clone)]
-pub struct VerifierSecrets<'a> {
+/// Secret parameters for the verifier, in a given group
+pub struct VerifierSecretsOf<'a, G: Group> {
     /// Blinding factor
-    pub γ: &'a Scalar,
+    pub γ: &'a G::Scalar,
+}
+
+impl<G: Group> Clone for VerifierSecretsOf<'_, G> {
+    fn clone(&self) -> Self {
+        *self
+    }
 }
 
+impl<G: Group> Copy for VerifierSecretsOf<'_, G> {}
+
+/// Secret parameters for the verifier
+pub type VerifierSecrets<'a> = VerifierSecretsOf<'a, Ristretto>;
+
 /// Performs the protocol for proving equality of discrete logarithms as the prover
-pub async fn prove<T: LocalTransport>(
+pub async fn prove<G: Group, T: LocalTransport>(
     t: &mut T,
-    publics: Publics<'_>,
-    secrets: ProverSecrets<'_>,
+    publics: PublicsOf<'_, G>,
+    secrets: SecretsOf<'_, G>,
 ) -> Result<(), Error> {
-    let r = Scalar::random(&mut thread_rng());
-    let a = r * publics.g1;
-    let b = r * publics.g2;
-    t.send(b"a", a).await?;
-    t.send(b"b", b).await?;
-    let c: Scalar = t.receive(b"c").await?;
-    let y = r + c * secrets.x;
-    t.send(b"y", y).await?;
+    prove_with_rng(t, publics, secrets, &mut rng()).await
+}
+
+/// Performs the protocol for proving equality of discrete logarithms as the prover, with a given
+/// source of randomness
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(name = "blind_dlog_eq::prove", skip_all)
+)]
+pub async fn prove_with_rng<G: Group, T: LocalTransport, R: CryptoRng + RngCore>(
+    t: &mut T,
+    publics: PublicsOf<'_, G>,
+    secrets: SecretsOf<'_, G>,
+    rng: &mut R,
+) -> Result<(), Error> {
+    let r = G::random_scalar(rng);
+    let a = G::mul(&r, publics.g1);
+    let b = G::mul(&r, publics.g2);
+    t.send(COMMITMENTS, (a, b)).await?;
+    let c: G::Scalar = t.receive(CHALLENGE).await?;
+    let y = r + c * *secrets.x;
+    t.send(RESPONSE, y).await?;
+    t.flush().await?;
     Ok(())
 }
 
 /// Performs the protocol for proving equality of discrete logarithms as the verifier
-pub async fn verify<T: LocalTransport>(
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(name = "blind_dlog_eq::verify", skip_all)
+)]
+pub async fn verify<G: Group, T: LocalTransport>(
     t: &mut T,
-    publics: Publics<'_>,
-    secrets: VerifierSecrets<'_>,
-) -> Result<Transcript, Error> {
-    let a: RistrettoPoint = t.receive(b"a").await?;
-    let b: RistrettoPoint = t.receive(b"b").await?;
-
-    let α = Scalar::random(&mut thread_rng());
-    let β = Scalar::random(&mut thread_rng());
-    let a1 = a + α * publics.g1 + β * publics.h1; // g*r + g*α * g*xβ = g*(r + α + xβ)
-    let b1 = secrets.γ * (b + α * publics.g2 + β * publics.h2); // g*γr + g*γα * g*γxβ = g*γ*(r + α * xβ)
+    publics: PublicsOf<'_, G>,
+    secrets: VerifierSecretsOf<'_, G>,
+) -> Result<TranscriptOf<G>, Error> {
+    let (a, b): (G::Point, G::Point) = t.receive(COMMITMENTS).await?;
+    ensure(
+        !G::is_identity(&a) && !G::is_identity(&b),
+        ProofStage::IdentityPoint,
+    )?;
+
+    let α = G::random_scalar(&mut rng());
+    let β = G::random_scalar(&mut rng());
+    let γ = *secrets.γ;
+    let a1 = a + G::mul(&α, publics.g1) + *publics.h1 * β; // g*r + g*α * g*xβ = g*(r + α + xβ)
+    let b1 = (b + *publics.g2 * α + *publics.h2 * β) * γ; // g*γr + g*γα * g*γxβ = g*γ*(r + α * xβ)
     let c_minus_β = dlog_eq::non_interactive_challenge_for(
-        Publics {
-            g2: &(secrets.γ * publics.g2),
-            h2: &(secrets.γ * publics.h2),
+        PublicsOf {
+            g2: &(*publics.g2 * γ),
+            h2: &(*publics.h2 * γ),
             ..publics
         },
         a1,
         b1,
     ); // c
     let c = c_minus_β + β;
-    t.send(b"c", c).await?;
-    let y: Scalar = t.receive(b"y").await?; // r + (c+β)x + α = r + α + xβ + cx
-
-    let a_ok = y * publics.g1 == a + c * publics.h1;
-    let b_ok = y * publics.g2 == b + c * publics.h2;
-    if a_ok & b_ok {
-        Ok(Transcript {
-            a: a1,
-            b: b1,
-            c: c_minus_β,
-            y: y + α,
-        })
-    } else {
-        Err(Error::BadProof)
+    t.send(CHALLENGE, c).await?;
+    let y: G::Scalar = t.receive(RESPONSE).await?; // r + (c+β)x + α = r + α + xβ + cx
+
+    let a_ok = G::points_eq(&G::mul(&y, publics.g1), &(a + *publics.h1 * c));
+    let b_ok = G::points_eq(&G::mul(&y, publics.g2), &(b + *publics.h2 * c));
+    ensure(a_ok, ProofStage::FirstEquation)?;
+    ensure(b_ok, ProofStage::SecondEquation)?;
+    Ok(TranscriptOf {
+        a: a1,
+        b: b1,
+        c: c_minus_β,
+        y: y + α,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use std::assert_matches::assert_matches;
+
+    use curve25519_dalek::{constants::RISTRETTO_BASEPOINT_POINT, RistrettoPoint, Scalar};
+    use futures::{executor::block_on, future::try_join};
+    use rand::thread_rng;
+
+    use crate::{
+        labels::dlog_eq::{CHALLENGE, COMMITMENTS, RESPONSE},
+        transport::{codec::Json, memory::DuplexTransport, LocalTransport as _, Recording},
+        Error, ProofStage,
+    };
+
+    use super::{dlog_eq::Transcript, prove, verify, ProverSecrets, Publics, VerifierSecrets};
+
+    /// Random parameters for a proof
+    struct Params {
+        x: Scalar,
+        h1: RistrettoPoint,
+        g2: RistrettoPoint,
+        h2: RistrettoPoint,
+    }
+
+    impl Params {
+        fn random() -> Self {
+            let x = Scalar::random(&mut thread_rng());
+            let g2 = RistrettoPoint::random(&mut thread_rng());
+            Self {
+                x,
+                h1: x * RISTRETTO_BASEPOINT_POINT,
+                g2,
+                h2: x * g2,
+            }
+        }
+
+        fn publics(&self) -> Publics<'_> {
+            Publics {
+                g1: &RISTRETTO_BASEPOINT_POINT,
+                h1: &self.h1,
+                g2: &self.g2,
+                h2: &self.h2,
+            }
+        }
+
+        fn run(&self, γ: &Scalar) -> (Transcript, Scalar) {
+            let (p_channel, mut v_channel) = DuplexTransport::<Json>::pair();
+            let mut p_channel = Recording::new(p_channel);
+            let (_, t) = block_on(try_join(
+                prove(&mut p_channel, self.publics(), ProverSecrets { x: &self.x }),
+                verify(&mut v_channel, self.publics(), VerifierSecrets { γ }),
+            ))
+            .unwrap();
+            let log = p_channel.into_log();
+            let c = serde_json::from_slice(&log[1].bytes).unwrap();
+            (t, c)
+        }
+    }
+
+    #[test]
+    fn transcript_verifies() {
+        let params = Params::random();
+
+        let (t, _) = params.run(&Scalar::ONE);
+        assert_matches!(t.verify(params.publics()), Ok(_));
+
+        let γ = Scalar::random(&mut thread_rng());
+        let (t, _) = params.run(&γ);
+        let blinded = Publics {
+            g2: &(γ * params.g2),
+            h2: &(γ * params.h2),
+            ..params.publics()
+        };
+        assert_matches!(t.verify(blinded), Ok(_));
+        assert_matches!(t.verify(params.publics()), Err(Error::BadProof { .. }));
+
+        let mut tampered = t;
+        tampered.y += Scalar::ONE;
+        assert_matches!(tampered.verify(blinded), Err(Error::BadProof { .. }));
+    }
+
+    #[test]
+    fn tampered_response() {
+        let params = Params::random();
+        let γ = Scalar::random(&mut thread_rng());
+        let (mut p_channel, mut v_channel) = DuplexTransport::<Json>::pair();
+        let prover = async {
+            let r = Scalar::random(&mut thread_rng());
+            let commitments = (r * RISTRETTO_BASEPOINT_POINT, r * params.g2);
+            p_channel.send(COMMITMENTS, commitments).await?;
+            let c: Scalar = p_channel.receive(CHALLENGE).await?;
+            p_channel
+                .send(RESPONSE, r + c * params.x + Scalar::ONE)
+                .await?;
+            Ok(())
+        };
+        let res = block_on(try_join(
+            prover,
+            verify(&mut v_channel, params.publics(), VerifierSecrets { γ: &γ }),
+        ));
+        assert_matches!(
+            res,
+            Err(Error::BadProof {
+                stage: ProofStage::FirstEquation
+            })
+        );
+    }
+
+    #[test]
+    fn transcript_is_unlinkable() {
+        const RUNS: u32 = 128;
+
+        let params = Params::random();
+        let γ = Scalar::random(&mut thread_rng());
+        let mut ones = [0; 8];
+        let mut seen = Vec::new();
+        for _ in 0..RUNS {
+            let (t, c) = params.run(&γ);
+            assert_ne!(
+                t.c, c,
+                "the transcript should not reveal the challenge sent"
+            );
+            assert!(!seen.contains(&t.c), "challenges should not repeat");
+            seen.push(t.c);
+            for (bit, count) in ones.iter_mut().enumerate() {
+                *count += u32::from(t.c.as_bytes()[0] >> bit & 1);
+            }
+        }
+        // Each bit is set in about half the runs, give or take seven standard deviations
+        for count in ones {
+            assert!(
+                (RUNS / 2 - 40..=RUNS / 2 + 40).contains(&count),
+                "challenge bits should be uniform, got {ones:?} ones in {RUNS} runs"
+            );
+        }
     }
 }
@@ -0,0 +1,155 @@
+//! Zero-knowledge proof of knowledge of a discrete logarithm (aka Schnorr's protocol)
+
+#[cfg(feature = "std")]
+use crate::{
+    error::Error,
+    labels::dlog::{CHALLENGE, COMMITMENT, RESPONSE},
+    transport::LocalTransport,
+};
+use crate::{
+    error::{ProofStage, Result},
+    hash::TranscriptProtocol as _,
+};
+use curve25519_dalek::{RistrettoPoint, Scalar};
+use rand::{CryptoRng, RngCore};
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "getrandom")]
+use super::rng;
+use super::{ensure, mul};
+
+/// Public parameters
+#[derive(Copy, Clone)]
+pub struct Publics<'a> {
+    /// Base
+    pub g: &'a RistrettoPoint,
+    /// Point
+    pub h: &'a RistrettoPoint,
+}
+
+/// Secret parameters
+#[derive(Copy, Clone)]
+pub struct Secrets<'a> {
+    /// Discrete logarithm
+    pub x: &'a Scalar,
+}
+
+/// Performs the protocol for proving knowledge of a discrete logarithm as the prover
+#[cfg(feature = "std")]
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(name = "dlog::prove", skip_all)
+)]
+pub async fn prove<T: LocalTransport>(
+    t: &mut T,
+    publics: Publics<'_>,
+    secrets: Secrets<'_>,
+) -> Result<(), Error> {
+    let r = Scalar::random(&mut rng());
+    let a = mul(&r, publics.g);
+    t.send(COMMITMENT, a).await?;
+    let c: Scalar = t.receive(CHALLENGE).await?;
+    let y = r + c * secrets.x;
+    t.send(RESPONSE, y).await?;
+    t.flush().await?;
+    Ok(())
+}
+
+/// Performs the protocol for proving knowledge of a discrete logarithm as the verifier
+#[cfg(feature = "std")]
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(name = "dlog::verify", skip_all)
+)]
+pub async fn verify<T: LocalTransport>(t: &mut T, publics: Publics<'_>) -> Result<(), Error> {
+    let a: RistrettoPoint = t.receive(COMMITMENT).await?;
+    let c = Scalar::random(&mut rng());
+    t.send(CHALLENGE, c).await?;
+    let y: Scalar = t.receive(RESPONSE).await?;
+    ensure(
+        mul(&y, publics.g) == a + c * publics.h,
+        ProofStage::FirstEquation,
+    )
+}
+
+/// Performs the protocol for proving knowledge of a discrete logarithm non-interactively
+#[cfg(feature = "getrandom")]
+pub fn prove_noninteractive(publics: Publics, secrets: Secrets) -> Transcript {
+    prove_noninteractive_with_rng(publics, secrets, &mut rng())
+}
+
+/// Performs the protocol for proving knowledge of a discrete logarithm non-interactively, with a
+/// given source of randomness
+pub fn prove_noninteractive_with_rng<R: CryptoRng + RngCore>(
+    publics: Publics,
+    secrets: Secrets,
+    rng: &mut R,
+) -> Transcript {
+    let r = Scalar::random(rng);
+    let a = mul(&r, publics.g);
+    let c = non_interactive_challenge_for(publics, a);
+    let y = r + c * secrets.x;
+    Transcript { a, c, y }
+}
+
+/// A transcript of the non-interactive protocol
+#[derive(PartialEq, Eq, Debug, Copy, Clone, Serialize, Deserialize)]
+pub struct Transcript {
+    /// Commitment
+    pub a: RistrettoPoint,
+    /// Challenge
+    pub c: Scalar,
+    /// Response
+    pub y: Scalar,
+}
+
+impl Transcript {
+    /// Verifies this transcript
+    pub fn verify(&self, publics: Publics) -> Result {
+        let c_ok = self.c == non_interactive_challenge_for(publics, self.a);
+        let a_ok = mul(&self.y, publics.g) == self.a + self.c * publics.h;
+        ensure(c_ok, ProofStage::ChallengeMismatch)?;
+        ensure(a_ok, ProofStage::FirstEquation)
+    }
+}
+
+/// Generates a non-interactive challenge for a proof of knowledge of a discrete logarithm
+pub fn non_interactive_challenge_for(publics: Publics, a: RistrettoPoint) -> Scalar {
+    let mut h = merlin::Transcript::new(b"nym/0.1/dlog-proof/non-interactive-challenge");
+    h.commit(b"g", &publics.g);
+    h.commit(b"h", &publics.h);
+    h.commit(b"a", &a);
+    h.challenge(b"c")
+}
+
+#[cfg(test)]
+mod test {
+    use std::assert_matches::assert_matches;
+
+    use curve25519_dalek::{constants::RISTRETTO_BASEPOINT_POINT, Scalar};
+    use rand::thread_rng;
+
+    use crate::{Error, ProofStage};
+
+    use super::{prove_noninteractive, Publics, Secrets};
+
+    #[test]
+    fn noninteractive_proof() {
+        let x = Scalar::random(&mut thread_rng());
+        let publics = Publics {
+            g: &RISTRETTO_BASEPOINT_POINT,
+            h: &(x * RISTRETTO_BASEPOINT_POINT),
+        };
+        let t = prove_noninteractive(publics, Secrets { x: &x });
+        assert_matches!(t.verify(publics), Ok(_));
+
+        let y = Scalar::random(&mut thread_rng());
+        let t = prove_noninteractive(publics, Secrets { x: &y });
+        assert_matches!(
+            t.verify(publics),
+            Err(Error::BadProof {
+                stage: ProofStage::FirstEquation
+            })
+        );
+    }
+}
@@ -1,97 +1,664 @@
 //! Zero-knowledge proof of equality of discrete logarithms aka protocol Π
 
 use crate::{
-    error::{Error, Result},
-    hash::TranscriptProtocol as _,
+    encoding::{Reader, Writer, PUBLICS_LENGTH, TRANSCRIPT_LENGTH},
+    error::{Error, ProofStage, Result},
+    group::{Group, Ristretto},
+    hash::{append_length, AppendMessage, Transcribe, TranscriptProtocol as _},
+};
+#[cfg(feature = "std")]
+use crate::{
+    labels::dlog_eq::{CHALLENGE, COMMITMENTS, RESPONSE},
     transport::LocalTransport,
 };
-use curve25519_dalek::{RistrettoPoint, Scalar};
-use rand::thread_rng;
+
+#[cfg(feature = "getrandom")]
+use super::rng;
+use super::{derive_generator, ensure};
+#[cfg(feature = "std")]
+use super::{ensure_not_identity, mul, ChallengeLength};
+use alloc::vec::Vec;
+use curve25519_dalek::{
+    traits::{IsIdentity as _, MultiscalarMul as _},
+    RistrettoPoint, Scalar,
+};
+use rand::{CryptoRng, RngCore};
 use serde::{Deserialize, Serialize};
 
+/// Public parameters, in a given group
+pub struct PublicsOf<'a, G: Group> {
+    /// First point's base
+    pub g1: &'a G::Point,
+    /// First point
+    pub h1: &'a G::Point,
+    /// Second point's base
+    pub g2: &'a G::Point,
+    /// Second point
+    pub h2: &'a G::Point,
+}
+
+impl<G: Group> Clone for PublicsOf<'_, G> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<G: Group> Copy for PublicsOf<'_, G> {}
+
 /// Public parameters
-#[derive(Copy, Clone)]
-pub struct Publics<'a> {
+pub type Publics<'a> = PublicsOf<'a, Ristretto>;
+
+impl Publics<'_> {
+    /// Length of the binary encoding of public parameters
+    pub const LENGTH: usize = PUBLICS_LENGTH;
+
+    /// Encodes these parameters as compressed points, in `g1`, `h1`, `g2`, `h2` order
+    ///
+    /// This is the order in which they are committed to the non-interactive challenge.
+    pub fn to_bytes(&self) -> [u8; PUBLICS_LENGTH] {
+        let mut bytes = [0; PUBLICS_LENGTH];
+        Writer::new(&mut bytes).publics(*self);
+        bytes
+    }
+}
+
+/// Public parameters that own their points, for storage alongside a transcript
+#[derive(PartialEq, Eq, Debug, Copy, Clone, Serialize, Deserialize)]
+pub struct OwnedPublics {
     /// First point's base
-    pub g1: &'a RistrettoPoint,
+    pub g1: RistrettoPoint,
     /// First point
-    pub h1: &'a RistrettoPoint,
+    pub h1: RistrettoPoint,
     /// Second point's base
-    pub g2: &'a RistrettoPoint,
+    pub g2: RistrettoPoint,
     /// Second point
-    pub h2: &'a RistrettoPoint,
+    pub h2: RistrettoPoint,
 }
 
-/// Secret parameters
-#[derive(Copy, Clone)]
-pub struct Secrets<'a> {
+impl OwnedPublics {
+    /// Borrows these parameters
+    pub fn as_ref(&self) -> Publics<'_> {
+        Publics {
+            g1: &self.g1,
+            h1: &self.h1,
+            g2: &self.g2,
+            h2: &self.h2,
+        }
+    }
+
+    /// Length of the binary encoding of public parameters
+    pub const LENGTH: usize = PUBLICS_LENGTH;
+
+    /// Encodes these parameters as compressed points, in `g1`, `h1`, `g2`, `h2` order
+    pub fn to_bytes(&self) -> [u8; Self::LENGTH] {
+        self.as_ref().to_bytes()
+    }
+
+    /// Decodes parameters from compressed points, in `g1`, `h1`, `g2`, `h2` order
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        let mut r = Reader::new(bytes);
+        let publics = r.publics()?;
+        r.finish()?;
+        Ok(publics)
+    }
+}
+
+impl<'a> From<&'a OwnedPublics> for Publics<'a> {
+    fn from(publics: &'a OwnedPublics) -> Self {
+        publics.as_ref()
+    }
+}
+
+impl From<Publics<'_>> for OwnedPublics {
+    fn from(publics: Publics) -> Self {
+        Self {
+            g1: *publics.g1,
+            h1: *publics.h1,
+            g2: *publics.g2,
+            h2: *publics.h2,
+        }
+    }
+}
+
+/// Public parameters whose second base is derived from a seed, rather than chosen
+///
+/// The second base is [`derive_generator`] over a domain and seed that both sides agree on, so
+/// that neither can know its discrete logarithm to the first base. These borrow as [`Publics`],
+/// to run any of the proofs in this module.
+#[derive(PartialEq, Eq, Debug, Copy, Clone)]
+pub struct DerivedPublics<'a> {
+    g1: &'a RistrettoPoint,
+    h1: &'a RistrettoPoint,
+    g2: RistrettoPoint,
+    h2: &'a RistrettoPoint,
+}
+
+impl<'a> DerivedPublics<'a> {
+    /// Builds the parameters, deriving the second base from a domain and a seed
+    pub fn new(
+        g1: &'a RistrettoPoint,
+        h1: &'a RistrettoPoint,
+        domain: &'static [u8],
+        seed: &[u8],
+        h2: &'a RistrettoPoint,
+    ) -> Self {
+        Self {
+            g1,
+            h1,
+            g2: derive_generator(domain, seed),
+            h2,
+        }
+    }
+
+    /// Gets the derived second base
+    pub fn g2(&self) -> &RistrettoPoint {
+        &self.g2
+    }
+
+    /// Borrows these parameters
+    pub fn as_ref(&self) -> Publics<'_> {
+        Publics {
+            g1: self.g1,
+            h1: self.h1,
+            g2: &self.g2,
+            h2: self.h2,
+        }
+    }
+}
+
+impl<'a> From<&'a DerivedPublics<'_>> for Publics<'a> {
+    fn from(publics: &'a DerivedPublics<'_>) -> Self {
+        publics.as_ref()
+    }
+}
+
+/// Secret parameters, in a given group
+pub struct SecretsOf<'a, G: Group> {
     /// Discrete logarithm
-    pub x: &'a Scalar,
+    pub x: &'a G::Scalar,
+}
+
+impl<G: Group> Clone for SecretsOf<'_, G> {
+    fn clone(&self) -> Self {
+        *self
+    }
 }
 
+impl<G: Group> Copy for SecretsOf<'_, G> {}
+
+/// Secret parameters
+pub type Secrets<'a> = SecretsOf<'a, Ristretto>;
+
 /// Performs the protocol for proving equality of discrete logarithms as the prover
-pub async fn prove<T: LocalTransport>(
+#[cfg(feature = "std")]
+pub async fn prove<G: Group, T: LocalTransport>(
     t: &mut T,
-    publics: Publics<'_>,
-    secrets: Secrets<'_>,
+    publics: PublicsOf<'_, G>,
+    secrets: SecretsOf<'_, G>,
+) -> Result<(), Error> {
+    prove_with_rng(t, publics, secrets, &mut rng()).await
+}
+
+/// Performs the protocol for proving equality of discrete logarithms as the prover, with a given
+/// source of randomness
+#[cfg(feature = "std")]
+pub async fn prove_with_rng<G: Group, T: LocalTransport, R: CryptoRng + RngCore>(
+    t: &mut T,
+    publics: PublicsOf<'_, G>,
+    secrets: SecretsOf<'_, G>,
+    rng: &mut R,
 ) -> Result<(), Error> {
-    let r = Scalar::random(&mut thread_rng());
-    let a = r * publics.g1;
-    let b = r * publics.g2;
-    t.send(b"a", a).await?;
-    t.send(b"b", b).await?;
-    let c: Scalar = t.receive(b"c").await?;
-    let y = r + c * secrets.x;
-    t.send(b"y", y).await?;
+    respond(t, publics, secrets, G::random_scalar(rng)).await
+}
+
+/// Performs the protocol for proving equality of discrete logarithms as the prover, with a given
+/// nonce for the commitments
+///
+/// Along with [`verify_with_challenge`], this makes every message exchanged reproducible, so that
+/// test harnesses can compare them byte for byte with another implementation. This is only
+/// available with the `hazmat` feature.
+///
+/// **Never reuse a nonce.** Two proofs with the same nonce and different challenges reveal the
+/// secret, so this is only for known test secrets.
+#[cfg(all(feature = "std", any(test, feature = "hazmat")))]
+pub async fn prove_with_nonce<G: Group, T: LocalTransport>(
+    t: &mut T,
+    publics: PublicsOf<'_, G>,
+    secrets: SecretsOf<'_, G>,
+    r: G::Scalar,
+) -> Result<(), Error> {
+    respond(t, publics, secrets, r).await
+}
+
+/// Performs the protocol as the prover, with a given nonce for the commitments
+#[cfg(feature = "std")]
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(name = "dlog_eq::prove", skip_all)
+)]
+async fn respond<G: Group, T: LocalTransport>(
+    t: &mut T,
+    publics: PublicsOf<'_, G>,
+    secrets: SecretsOf<'_, G>,
+    r: G::Scalar,
+) -> Result<(), Error> {
+    let a = G::mul(&r, publics.g1);
+    let b = G::mul(&r, publics.g2);
+    t.send(COMMITMENTS, (a, b)).await?;
+    let c: G::Scalar = t.receive(CHALLENGE).await?;
+    let y = r + c * *secrets.x;
+    t.send(RESPONSE, y).await?;
+    t.flush().await?;
     Ok(())
 }
 
 /// Performs the protocol for proving equality of discrete logarithms as the verifier
-pub async fn verify<T: LocalTransport>(t: &mut T, publics: Publics<'_>) -> Result<(), Error> {
-    let a: RistrettoPoint = t.receive(b"a").await?;
-    let b: RistrettoPoint = t.receive(b"b").await?;
-    let c = Scalar::random(&mut thread_rng());
-    t.send(b"c", c).await?;
-    let y: Scalar = t.receive(b"y").await?;
-    let a_ok = y * publics.g1 == a + c * publics.h1;
-    let b_ok = y * publics.g2 == b + c * publics.h2;
-    if a_ok & b_ok {
-        Ok(())
-    } else {
-        Err(Error::BadProof)
+#[cfg(feature = "std")]
+pub async fn verify<G: Group, T: LocalTransport>(
+    t: &mut T,
+    publics: PublicsOf<'_, G>,
+) -> Result<(), Error> {
+    record(t, publics, || G::random_scalar(&mut rng())).await?;
+    Ok(())
+}
+
+/// Performs the protocol for proving equality of discrete logarithms as the verifier, with
+/// challenges of a given length
+///
+/// The prover's side is the same for any challenge length.
+#[cfg(feature = "std")]
+pub async fn verify_with_challenge_length<T: LocalTransport>(
+    t: &mut T,
+    publics: Publics<'_>,
+    length: ChallengeLength,
+) -> Result<(), Error> {
+    record(t, publics, || length.random(&mut rng())).await?;
+    Ok(())
+}
+
+/// Performs the protocol for proving equality of discrete logarithms as the verifier, with a given
+/// source of randomness for the challenge
+///
+/// With a seeded RNG, the verifier's messages are reproducible, e.g. to replay a recorded session
+/// against it.
+#[cfg(feature = "std")]
+pub async fn verify_with_rng<G: Group, T: LocalTransport, R: CryptoRng + RngCore>(
+    t: &mut T,
+    publics: PublicsOf<'_, G>,
+    rng: &mut R,
+) -> Result<(), Error> {
+    record(t, publics, || G::random_scalar(rng)).await?;
+    Ok(())
+}
+
+/// Performs the protocol for proving equality of discrete logarithms as the verifier, with a given
+/// challenge
+///
+/// The challenge is sent as is instead of being drawn at random, which is only sound if the prover
+/// can't predict it. This is for test harnesses that pin the challenge, e.g. to compare the
+/// messages with another implementation, and is only available with the `hazmat` feature. The
+/// prover's side is the same, or [`prove_with_nonce`] to pin its messages too.
+#[cfg(all(feature = "std", any(test, feature = "hazmat")))]
+pub async fn verify_with_challenge<G: Group, T: LocalTransport>(
+    t: &mut T,
+    publics: PublicsOf<'_, G>,
+    c: G::Scalar,
+) -> Result<(), Error> {
+    record(t, publics, || c).await?;
+    Ok(())
+}
+
+/// Performs the protocol for proving equality of discrete logarithms as the verifier, keeping the
+/// messages exchanged as a transcript
+///
+/// This allows archiving an interactive session. The challenge in the transcript was drawn at
+/// random rather than derived from the commitments, so [`Transcript::verify`] rejects it with
+/// [`ProofStage::ChallengeMismatch`], and only the verification equations can be checked again.
+/// Such a transcript convinces whoever trusts the verifier to have drawn the challenge after
+/// receiving the commitments, but nobody else.
+#[cfg(feature = "std")]
+pub async fn verify_recorded<G: Group, T: LocalTransport>(
+    t: &mut T,
+    publics: PublicsOf<'_, G>,
+) -> Result<TranscriptOf<G>, Error> {
+    record(t, publics, || G::random_scalar(&mut rng())).await
+}
+
+/// Performs the protocol as the verifier, and returns the transcript if the proof holds
+#[cfg(feature = "std")]
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(name = "dlog_eq::verify", skip_all)
+)]
+async fn record<G: Group, T: LocalTransport>(
+    t: &mut T,
+    publics: PublicsOf<'_, G>,
+    challenge: impl FnOnce() -> G::Scalar,
+) -> Result<TranscriptOf<G>, Error> {
+    let (a, b): (G::Point, G::Point) = t.receive(COMMITMENTS).await?;
+    ensure(
+        !G::is_identity(&a) && !G::is_identity(&b),
+        ProofStage::IdentityPoint,
+    )?;
+    let c = challenge();
+    t.send(CHALLENGE, c).await?;
+    let y: G::Scalar = t.receive(RESPONSE).await?;
+    let a_ok = G::points_eq(&G::mul(&y, publics.g1), &(a + *publics.h1 * c));
+    let b_ok = G::points_eq(&G::mul(&y, publics.g2), &(b + *publics.h2 * c));
+    ensure(a_ok, ProofStage::FirstEquation)?;
+    ensure(b_ok, ProofStage::SecondEquation)?;
+    Ok(TranscriptOf { a, b, c, y })
+}
+
+/// Performs the protocol for proving several equalities of discrete logarithms as the prover,
+/// with a single challenge for all of them
+#[cfg(feature = "std")]
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(name = "dlog_eq::prove_all", skip_all)
+)]
+pub async fn prove_all<T: LocalTransport>(
+    t: &mut T,
+    items: &[(Publics<'_>, Secrets<'_>)],
+) -> Result<(), Error> {
+    prove_all_with_rng(t, items, &mut rng()).await
+}
+
+/// Performs the protocol for proving several equalities of discrete logarithms as the prover,
+/// with a single challenge for all of them and a given source of randomness
+#[cfg(feature = "std")]
+pub async fn prove_all_with_rng<T: LocalTransport, R: CryptoRng + RngCore>(
+    t: &mut T,
+    items: &[(Publics<'_>, Secrets<'_>)],
+    rng: &mut R,
+) -> Result<(), Error> {
+    let r: Vec<_> = items.iter().map(|_| Scalar::random(rng)).collect();
+    let ab: Vec<_> = items
+        .iter()
+        .zip(&r)
+        .map(|((publics, _), r)| (mul(r, publics.g1), r * publics.g2))
+        .collect();
+    t.send(COMMITMENTS, ab).await?;
+    let c: Scalar = t.receive(CHALLENGE).await?;
+    let y: Vec<_> = items
+        .iter()
+        .zip(&r)
+        .map(|((_, secrets), r)| r + c * secrets.x)
+        .collect();
+    t.send(RESPONSE, y).await?;
+    t.flush().await?;
+    Ok(())
+}
+
+/// Performs the protocol for proving several equalities of discrete logarithms as the verifier,
+/// with a single challenge for all of them
+#[cfg(feature = "std")]
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(name = "dlog_eq::verify_all", skip_all)
+)]
+pub async fn verify_all<T: LocalTransport>(
+    t: &mut T,
+    publics: &[Publics<'_>],
+) -> Result<(), Error> {
+    let ab: Vec<(RistrettoPoint, RistrettoPoint)> = t.receive(COMMITMENTS).await?;
+    ensure(ab.len() == publics.len(), ProofStage::CommitmentCount)?;
+    for (a, b) in &ab {
+        ensure_not_identity(&[a, b])?;
     }
+    let c = Scalar::random(&mut rng());
+    t.send(CHALLENGE, c).await?;
+    let y: Vec<Scalar> = t.receive(RESPONSE).await?;
+    ensure(y.len() == publics.len(), ProofStage::CommitmentCount)?;
+    for ((publics, (a, b)), y) in publics.iter().zip(ab).zip(y) {
+        let a_ok = Ristretto::points_eq(&mul(&y, publics.g1), &(a + c * publics.h1));
+        let b_ok = Ristretto::points_eq(&(y * publics.g2), &(b + c * publics.h2));
+        ensure(a_ok, ProofStage::FirstEquation)?;
+        ensure(b_ok, ProofStage::SecondEquation)?;
+    }
+    Ok(())
 }
 
-/// A transcript of protocol Π_NI
+/// Performs the protocol for proving equality of discrete logarithms non-interactively
+#[cfg(feature = "getrandom")]
+pub fn prove_noninteractive<G: Group>(
+    publics: PublicsOf<G>,
+    secrets: SecretsOf<G>,
+) -> TranscriptOf<G> {
+    prove_noninteractive_with_rng(publics, secrets, &mut rng())
+}
+
+/// Performs the protocol for proving equality of discrete logarithms non-interactively, with a
+/// given source of randomness
+pub fn prove_noninteractive_with_rng<G: Group, R: CryptoRng + RngCore>(
+    publics: PublicsOf<G>,
+    secrets: SecretsOf<G>,
+    rng: &mut R,
+) -> TranscriptOf<G> {
+    let r = G::random_scalar(rng);
+    let a = G::mul(&r, publics.g1);
+    let b = G::mul(&r, publics.g2);
+    let c = non_interactive_challenge_for(publics, a, b);
+    let y = r + c * *secrets.x;
+    TranscriptOf { a, b, c, y }
+}
+
+/// Performs the protocol for proving equality of discrete logarithms non-interactively, bound to a
+/// given context
+///
+/// The context, e.g. a nonce from the verifier, is committed into the challenge, so the transcript
+/// only verifies with [`Transcript::verify_in_context`] for the same context.
+#[cfg(feature = "getrandom")]
+pub fn prove_noninteractive_in_context<G: Group>(
+    publics: PublicsOf<G>,
+    secrets: SecretsOf<G>,
+    context: &[u8],
+) -> TranscriptOf<G> {
+    prove_noninteractive_in_context_with_rng(publics, secrets, context, &mut rng())
+}
+
+/// Performs the protocol for proving equality of discrete logarithms non-interactively, bound to a
+/// given context, with a given source of randomness
+pub fn prove_noninteractive_in_context_with_rng<G: Group, R: CryptoRng + RngCore>(
+    publics: PublicsOf<G>,
+    secrets: SecretsOf<G>,
+    context: &[u8],
+    rng: &mut R,
+) -> TranscriptOf<G> {
+    let r = G::random_scalar(rng);
+    let a = G::mul(&r, publics.g1);
+    let b = G::mul(&r, publics.g2);
+    let c = non_interactive_challenge_in_context(publics, a, b, context);
+    let y = r + c * *secrets.x;
+    TranscriptOf { a, b, c, y }
+}
+
+/// A transcript of protocol Π_NI, in a given group
+///
+/// Both [`Transcript::from_bytes`] and deserialization reject scalars that aren't canonically
+/// encoded, so a valid transcript can't be re-encoded into a different one that also verifies.
 #[derive(PartialEq, Eq, Debug, Copy, Clone, Serialize, Deserialize)]
-pub struct Transcript {
-    pub a: RistrettoPoint,
-    pub b: RistrettoPoint,
-    pub c: Scalar,
-    pub y: Scalar,
+#[serde(bound = "")]
+pub struct TranscriptOf<G: Group> {
+    /// Commitment for the first point
+    pub a: G::Point,
+    /// Commitment for the second point
+    pub b: G::Point,
+    /// Challenge
+    pub c: G::Scalar,
+    /// Response
+    pub y: G::Scalar,
 }
 
-impl Transcript {
+/// A transcript of protocol Π_NI
+pub type Transcript = TranscriptOf<Ristretto>;
+
+impl<G: Group> TranscriptOf<G> {
     /// Verifies this transcript
-    pub fn verify(&self, publics: Publics) -> Result {
-        let c_ok = self.c == non_interactive_challenge_for(publics, self.a, self.b);
-        let a_ok = self.y * publics.g1 == self.a + self.c * publics.h1;
-        let b_ok = self.y * publics.g2 == self.b + self.c * publics.h2;
-        if c_ok && a_ok && b_ok {
-            Ok(())
-        } else {
-            Err(Error::BadProof)
-        }
+    pub fn verify<'a>(&self, publics: impl Into<PublicsOf<'a, G>>) -> Result {
+        let publics = publics.into();
+        let c = non_interactive_challenge_for(publics, self.a, self.b);
+        self.verify_with(publics, c)
+    }
+
+    /// Verifies this transcript, for a proof bound to a given context
+    pub fn verify_in_context<'a>(
+        &self,
+        publics: impl Into<PublicsOf<'a, G>>,
+        context: &[u8],
+    ) -> Result {
+        let publics = publics.into();
+        let c = non_interactive_challenge_in_context(publics, self.a, self.b, context);
+        self.verify_with(publics, c)
+    }
+
+    fn verify_with(&self, publics: PublicsOf<G>, c: G::Scalar) -> Result {
+        let c_ok = self.c == c;
+        let a_ok = G::points_eq(
+            &G::mul(&self.y, publics.g1),
+            &(self.a + *publics.h1 * self.c),
+        );
+        let b_ok = G::points_eq(
+            &G::mul(&self.y, publics.g2),
+            &(self.b + *publics.h2 * self.c),
+        );
+        ensure(c_ok, ProofStage::ChallengeMismatch)?;
+        ensure(a_ok, ProofStage::FirstEquation)?;
+        ensure(b_ok, ProofStage::SecondEquation)
     }
 }
 
+impl<G: Group> Transcribe for TranscriptOf<G> {
+    fn append_to<S: AppendMessage + ?Sized>(&self, t: &mut S, label: &'static [u8]) {
+        append_length(t, label, 4);
+        self.a.append_to(t, b"a");
+        self.b.append_to(t, b"b");
+        self.c.append_to(t, b"c");
+        self.y.append_to(t, b"y");
+    }
+}
+
+impl Transcript {
+    /// Length of the binary encoding of a transcript
+    pub const LENGTH: usize = TRANSCRIPT_LENGTH;
+
+    /// Encodes this transcript as compressed points and canonical scalars
+    pub fn to_bytes(&self) -> [u8; Self::LENGTH] {
+        let mut bytes = [0; Self::LENGTH];
+        Writer::new(&mut bytes).transcript(self);
+        bytes
+    }
+
+    /// Decodes a transcript from compressed points and canonical scalars
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        let mut r = Reader::new(bytes);
+        let t = r.transcript()?;
+        r.finish()?;
+        Ok(t)
+    }
+
+    /// Decodes a transcript received from an untrusted peer
+    ///
+    /// This is [`Transcript::from_bytes`], except that invalid encodings are reported as a failed
+    /// proof, with [`ProofStage::Encoding`]. Neither this nor [`Transcript::verify`] panics on any
+    /// input, so untrusted bytes can go straight through both.
+    pub fn try_from_bytes(bytes: &[u8]) -> Result<Self> {
+        Self::from_bytes(bytes).map_err(|_| Error::BadProof {
+            stage: ProofStage::Encoding,
+        })
+    }
+}
+
+/// Verifies several transcripts at once
+///
+/// All the verification equations are checked together as a random linear combination, with a
+/// single multiscalar multiplication. The whole batch fails if any one transcript is invalid.
+#[cfg(feature = "getrandom")]
+pub fn batch_verify(items: &[(Transcript, Publics)]) -> Result {
+    batch_verify_with_rng(items, &mut rng())
+}
+
+/// Verifies several transcripts at once, with a given source of randomness for the linear
+/// combination
+pub fn batch_verify_with_rng<R: CryptoRng + RngCore>(
+    items: &[(Transcript, Publics)],
+    rng: &mut R,
+) -> Result {
+    let mut scalars = Vec::with_capacity(items.len() * 6);
+    let mut points = Vec::with_capacity(items.len() * 6);
+    for (t, publics) in items {
+        ensure(
+            t.c == non_interactive_challenge_for(*publics, t.a, t.b),
+            ProofStage::ChallengeMismatch,
+        )?;
+        let z1 = Scalar::random(rng);
+        let z2 = Scalar::random(rng);
+        scalars.extend([z1 * t.y, -z1, -z1 * t.c, z2 * t.y, -z2, -z2 * t.c]);
+        points.extend([*publics.g1, t.a, *publics.h1, *publics.g2, t.b, *publics.h2]);
+    }
+    ensure(
+        RistrettoPoint::multiscalar_mul(scalars, points).is_identity(),
+        ProofStage::Batch,
+    )
+}
+
+/// Domain separator for the non-interactive challenges
+pub const DOMAIN: &[u8] = b"nym/dlog-eq-proof/non-interactive-challenge";
+
+/// Protocol version committed into the non-interactive challenges by default
+///
+/// This is the crate's major and minor version, so that transcripts from incompatible versions
+/// don't verify.
+pub const VERSION: &str = concat!(
+    env!("CARGO_PKG_VERSION_MAJOR"),
+    ".",
+    env!("CARGO_PKG_VERSION_MINOR")
+);
+
 /// Generates a non-interactive challenge for a proof of equality of discrete logarithms
-pub fn non_interactive_challenge_for(
-    publics: Publics,
-    a: RistrettoPoint,
-    b: RistrettoPoint,
-) -> Scalar {
-    let mut h = merlin::Transcript::new(b"nym/0.1/dlog-eq-proof/non-interactive-challenge");
+pub fn non_interactive_challenge_for<G: Group>(
+    publics: PublicsOf<G>,
+    a: G::Point,
+    b: G::Point,
+) -> G::Scalar {
+    non_interactive_challenge_for_versioned(VERSION, publics, a, b)
+}
+
+/// Generates a non-interactive challenge for a proof of equality of discrete logarithms, for a
+/// given protocol version
+pub fn non_interactive_challenge_for_versioned<G: Group>(
+    version: &str,
+    publics: PublicsOf<G>,
+    a: G::Point,
+    b: G::Point,
+) -> G::Scalar {
+    challenge_with(version, None, publics, a, b)
+}
+
+/// Generates a non-interactive challenge for a proof of equality of discrete logarithms bound to a
+/// given context
+pub fn non_interactive_challenge_in_context<G: Group>(
+    publics: PublicsOf<G>,
+    a: G::Point,
+    b: G::Point,
+    context: &[u8],
+) -> G::Scalar {
+    challenge_with(VERSION, Some(context), publics, a, b)
+}
+
+/// Derives a non-interactive challenge, committing the context, if any, right after the version
+fn challenge_with<G: Group>(
+    version: &str,
+    context: Option<&[u8]>,
+    publics: PublicsOf<G>,
+    a: G::Point,
+    b: G::Point,
+) -> G::Scalar {
+    let mut h = merlin::Transcript::new(DOMAIN);
+    h.commit(b"version", version);
+    if let Some(context) = context {
+        h.commit(b"context", context);
+    }
     h.commit(b"g1", &publics.g1);
     h.commit(b"h1", &publics.h1);
     h.commit(b"g2", &publics.g2);
@@ -100,3 +667,460 @@ pub fn non_interactive_challenge_for(
     h.commit(b"b", &b);
     h.challenge(b"c")
 }
+
+#[cfg(test)]
+mod test {
+    use std::assert_matches::assert_matches;
+
+    use curve25519_dalek::{constants::RISTRETTO_BASEPOINT_POINT, RistrettoPoint, Scalar};
+    use futures::{executor::block_on, future::try_join};
+    use rand::thread_rng;
+
+    use crate::{
+        group::{Group as _, Ristretto},
+        labels::dlog_eq::{CHALLENGE, COMMITMENTS, RESPONSE},
+        proof::{derive_generator, ChallengeLength},
+        transport::{codec::Json, memory::DuplexTransport, LocalTransport as _, Recording},
+        Error, ProofStage,
+    };
+
+    use super::{
+        batch_verify, non_interactive_challenge_for, non_interactive_challenge_for_versioned,
+        prove, prove_noninteractive, prove_with_nonce, verify, verify_recorded,
+        verify_with_challenge, verify_with_challenge_length, DerivedPublics, OwnedPublics, Publics,
+        Secrets, Transcript, VERSION,
+    };
+
+    #[test]
+    fn points_eq_matches_equality() {
+        for _ in 0..10 {
+            let p = RistrettoPoint::random(&mut thread_rng());
+            let q = RistrettoPoint::random(&mut thread_rng());
+            let s = Scalar::random(&mut thread_rng());
+            assert_eq!(Ristretto::points_eq(&p, &q), p == q);
+            assert!(Ristretto::points_eq(&(s * p + q), &(q + p * s)));
+        }
+
+        for _ in 0..10 {
+            let x = Scalar::random(&mut thread_rng());
+            let g2 = RistrettoPoint::random(&mut thread_rng());
+            let publics = Publics {
+                g1: &RISTRETTO_BASEPOINT_POINT,
+                h1: &(x * RISTRETTO_BASEPOINT_POINT),
+                g2: &g2,
+                h2: &(x * g2),
+            };
+            let t = prove_noninteractive(publics, Secrets { x: &x });
+            assert_matches!(t.verify(publics), Ok(_));
+            let tampered = Transcript {
+                y: t.y + Scalar::ONE,
+                ..t
+            };
+            assert_matches!(tampered.verify(publics), Err(Error::BadProof { .. }));
+        }
+    }
+
+    #[test]
+    fn derived_base() {
+        let x = Scalar::random(&mut thread_rng());
+        let g2 = derive_generator(b"test-domain", b"seed");
+        let (h1, h2) = (x * RISTRETTO_BASEPOINT_POINT, x * g2);
+        let publics = DerivedPublics::new(
+            &RISTRETTO_BASEPOINT_POINT,
+            &h1,
+            b"test-domain",
+            b"seed",
+            &h2,
+        );
+        assert_eq!(*publics.g2(), g2);
+
+        let (mut p_channel, mut v_channel) = DuplexTransport::<Json>::pair();
+        let res = block_on(try_join(
+            prove(&mut p_channel, publics.as_ref(), Secrets { x: &x }),
+            verify(&mut v_channel, publics.as_ref()),
+        ));
+        assert_matches!(res, Ok(_));
+
+        let t = prove_noninteractive(publics.as_ref(), Secrets { x: &x });
+        assert_matches!(t.verify(&publics), Ok(_));
+        let other = DerivedPublics::new(
+            &RISTRETTO_BASEPOINT_POINT,
+            &h1,
+            b"test-domain",
+            b"other",
+            &h2,
+        );
+        assert_matches!(t.verify(&other), Err(Error::BadProof { .. }));
+    }
+
+    #[test]
+    fn interactive_proof() {
+        let x = Scalar::random(&mut thread_rng());
+        let g2 = RistrettoPoint::random(&mut thread_rng());
+        let publics = Publics {
+            g1: &RISTRETTO_BASEPOINT_POINT,
+            h1: &(x * RISTRETTO_BASEPOINT_POINT),
+            g2: &g2,
+            h2: &(x * g2),
+        };
+
+        let (mut p_channel, mut v_channel) = DuplexTransport::<Json>::pair();
+        let res = block_on(try_join(
+            prove(&mut p_channel, publics, Secrets { x: &x }),
+            verify(&mut v_channel, publics),
+        ));
+        assert_matches!(res, Ok(_));
+
+        let (mut p_channel, mut v_channel) = DuplexTransport::<Json>::pair();
+        let verifier = async {
+            let (a, b): (RistrettoPoint, RistrettoPoint) = v_channel.receive(COMMITMENTS).await?;
+            v_channel.send(CHALLENGE, Scalar::ONE).await?;
+            let y: Scalar = v_channel.receive(RESPONSE).await?;
+            Ok((a, b, y))
+        };
+        let (_, (a, b, y)) = block_on(try_join(
+            prove(&mut p_channel, publics, Secrets { x: &x }),
+            verifier,
+        ))
+        .unwrap();
+        assert_eq!(
+            y * publics.g1,
+            a + publics.h1,
+            "the response to a unit challenge should match the first commitment"
+        );
+        assert_eq!(
+            y * publics.g2,
+            b + publics.h2,
+            "the response to a unit challenge should match the second commitment"
+        );
+    }
+
+    #[test]
+    fn fixed_challenge() {
+        let x = Scalar::from(7u64);
+        let r = Scalar::from(3u64);
+        let c = Scalar::from(42u64);
+        let g2 = Scalar::from(5u64) * RISTRETTO_BASEPOINT_POINT;
+        let publics = Publics {
+            g1: &RISTRETTO_BASEPOINT_POINT,
+            h1: &(x * RISTRETTO_BASEPOINT_POINT),
+            g2: &g2,
+            h2: &(x * g2),
+        };
+
+        let (p_channel, mut v_channel) = DuplexTransport::<Json>::pair();
+        let mut p_channel = Recording::new(p_channel);
+        let res = block_on(try_join(
+            prove_with_nonce(&mut p_channel, publics, Secrets { x: &x }, r),
+            verify_with_challenge(&mut v_channel, publics, c),
+        ));
+        assert_matches!(res, Ok(_));
+
+        let log = p_channel.into_log();
+        let labels: Vec<_> = log.iter().map(|m| m.label.as_slice()).collect();
+        assert_eq!(labels, [COMMITMENTS, CHALLENGE, RESPONSE]);
+        let (a, b): (RistrettoPoint, RistrettoPoint) =
+            serde_json::from_slice(&log[0].bytes).unwrap();
+        assert_eq!(a, r * RISTRETTO_BASEPOINT_POINT);
+        assert_eq!(b, r * g2);
+        assert_eq!(serde_json::from_slice::<Scalar>(&log[1].bytes).unwrap(), c);
+        assert_eq!(
+            serde_json::from_slice::<Scalar>(&log[2].bytes).unwrap(),
+            Scalar::from(297u64),
+            "the response should be r + c·x"
+        );
+
+        let (mut p_channel, mut v_channel) = DuplexTransport::<Json>::pair();
+        let res = block_on(try_join(
+            prove_with_nonce(&mut p_channel, publics, Secrets { x: &r }, r),
+            verify_with_challenge(&mut v_channel, publics, c),
+        ));
+        assert_matches!(
+            res,
+            Err(Error::BadProof {
+                stage: ProofStage::FirstEquation
+            })
+        );
+    }
+
+    #[test]
+    fn noninteractive_proof() {
+        let x = Scalar::random(&mut thread_rng());
+        let g2 = RistrettoPoint::random(&mut thread_rng());
+        let publics = Publics {
+            g1: &RISTRETTO_BASEPOINT_POINT,
+            h1: &(x * RISTRETTO_BASEPOINT_POINT),
+            g2: &g2,
+            h2: &(x * g2),
+        };
+        let t = prove_noninteractive(publics, Secrets { x: &x });
+        assert_matches!(t.verify(publics), Ok(_));
+
+        let y = Scalar::random(&mut thread_rng());
+        let t = prove_noninteractive(publics, Secrets { x: &y });
+        assert_matches!(
+            t.verify(publics),
+            Err(Error::BadProof {
+                stage: ProofStage::FirstEquation
+            })
+        );
+
+        let t = super::Transcript {
+            c: t.c + Scalar::ONE,
+            ..t
+        };
+        assert_matches!(
+            t.verify(publics),
+            Err(Error::BadProof {
+                stage: ProofStage::ChallengeMismatch
+            })
+        );
+    }
+
+    #[test]
+    fn short_challenge() {
+        let x = Scalar::random(&mut thread_rng());
+        let g2 = RistrettoPoint::random(&mut thread_rng());
+        let publics = Publics {
+            g1: &RISTRETTO_BASEPOINT_POINT,
+            h1: &(x * RISTRETTO_BASEPOINT_POINT),
+            g2: &g2,
+            h2: &(x * g2),
+        };
+        let length = ChallengeLength::bits(128);
+
+        let (mut p_channel, mut v_channel) = DuplexTransport::<Json>::pair();
+        let res = block_on(try_join(
+            prove(&mut p_channel, publics, Secrets { x: &x }),
+            verify_with_challenge_length(&mut v_channel, publics, length),
+        ));
+        assert_matches!(res, Ok(_));
+
+        let y = Scalar::random(&mut thread_rng());
+        let (mut p_channel, mut v_channel) = DuplexTransport::<Json>::pair();
+        let res = block_on(try_join(
+            prove(&mut p_channel, publics, Secrets { x: &y }),
+            verify_with_challenge_length(&mut v_channel, publics, length),
+        ));
+        assert_matches!(res, Err(Error::BadProof { .. }));
+    }
+
+    #[test]
+    fn owned_publics() {
+        let x = Scalar::random(&mut thread_rng());
+        let g2 = RistrettoPoint::random(&mut thread_rng());
+        let publics = OwnedPublics {
+            g1: RISTRETTO_BASEPOINT_POINT,
+            h1: x * RISTRETTO_BASEPOINT_POINT,
+            g2,
+            h2: x * g2,
+        };
+        let t = prove_noninteractive(publics.as_ref(), Secrets { x: &x });
+
+        let stored = serde_json::to_vec(&(publics, t)).unwrap();
+        let (publics, t): (OwnedPublics, super::Transcript) =
+            serde_json::from_slice(&stored).unwrap();
+        assert_matches!(t.verify(&publics), Ok(_));
+        assert_eq!(OwnedPublics::from(publics.as_ref()), publics);
+    }
+
+    #[test]
+    fn versioned_challenge() {
+        let x = Scalar::random(&mut thread_rng());
+        let publics = Publics {
+            g1: &RISTRETTO_BASEPOINT_POINT,
+            h1: &(x * RISTRETTO_BASEPOINT_POINT),
+            g2: &RISTRETTO_BASEPOINT_POINT,
+            h2: &(x * RISTRETTO_BASEPOINT_POINT),
+        };
+        let a = RistrettoPoint::random(&mut thread_rng());
+        let b = RistrettoPoint::random(&mut thread_rng());
+        assert_eq!(
+            non_interactive_challenge_for(publics, a, b),
+            non_interactive_challenge_for_versioned(VERSION, publics, a, b)
+        );
+        assert_ne!(
+            non_interactive_challenge_for_versioned("0.1", publics, a, b),
+            non_interactive_challenge_for_versioned("0.2", publics, a, b),
+            "different versions should give different challenges"
+        );
+    }
+
+    /// A test vector pinning the non-interactive challenge
+    ///
+    /// The points are the multiples 1·G to 6·G of the Ristretto basepoint from RFC 9496, appendix
+    /// A.1. The expected challenge was computed independently of this crate, with a from-scratch
+    /// implementation of merlin's STROBE-128 transcript (checked against merlin's own test vector)
+    /// reducing the 64 challenge bytes modulo the group order. If this test fails, the
+    /// Fiat-Shamir transform changed and proofs from other implementations no longer verify.
+    #[test]
+    fn challenge_test_vector() {
+        let [g1, h1, g2, h2, a, b] =
+            core::array::from_fn(|i| Scalar::from(i as u64 + 1) * RISTRETTO_BASEPOINT_POINT);
+        let publics = Publics {
+            g1: &g1,
+            h1: &h1,
+            g2: &g2,
+            h2: &h2,
+        };
+
+        assert_eq!(
+            hex(&publics.to_bytes()),
+            concat!(
+                "e2f2ae0a6abc4e71a884a961c500515f58e30b6aa582dd8db6a65945e08d2d76",
+                "6a493210f7499cd17fecb510ae0cea23a110e8d5b901f8acadd3095c73a3b919",
+                "94741f5d5d52755ece4f23f044ee27d5d1ea1e2bd196b462166b16152a9d0259",
+                "da80862773358b466ffadfe0b3293ab3d9fd53c5ea6c955358f568322daf6a57",
+            )
+        );
+        assert_eq!(
+            OwnedPublics::from_bytes(&publics.to_bytes()).unwrap(),
+            OwnedPublics::from(publics)
+        );
+        assert_matches!(
+            OwnedPublics::from_bytes(&publics.to_bytes()[1..]),
+            Err(Error::InvalidEncoding)
+        );
+
+        let c = non_interactive_challenge_for_versioned("0.1", publics, a, b);
+        assert_eq!(
+            hex(c.as_bytes()),
+            "2d07afcab884a6b8dcd266576327847674b69fb2728948f073372a6667c82a0a"
+        );
+    }
+
+    fn hex(bytes: &[u8]) -> String {
+        bytes.iter().map(|b| format!("{b:02x}")).collect()
+    }
+
+    #[test]
+    fn batch_verification() {
+        let xs: Vec<_> = (0..10).map(|_| Scalar::random(&mut thread_rng())).collect();
+        let points: Vec<_> = xs
+            .iter()
+            .map(|x| {
+                let g2 = RistrettoPoint::random(&mut thread_rng());
+                (x * RISTRETTO_BASEPOINT_POINT, g2, x * g2)
+            })
+            .collect();
+        let publics: Vec<_> = points
+            .iter()
+            .map(|(h1, g2, h2)| Publics {
+                g1: &RISTRETTO_BASEPOINT_POINT,
+                h1,
+                g2,
+                h2,
+            })
+            .collect();
+        let mut items: Vec<_> = xs
+            .iter()
+            .zip(&publics)
+            .map(|(x, p)| (prove_noninteractive(*p, Secrets { x }), *p))
+            .collect();
+        assert_matches!(batch_verify(&items), Ok(_));
+
+        items[3].0.y += Scalar::ONE;
+        assert_matches!(batch_verify(&items), Err(Error::BadProof { .. }));
+    }
+
+    #[test]
+    fn untrusted_bytes() {
+        let x = Scalar::random(&mut thread_rng());
+        let publics = Publics {
+            g1: &RISTRETTO_BASEPOINT_POINT,
+            h1: &(x * RISTRETTO_BASEPOINT_POINT),
+            g2: &RISTRETTO_BASEPOINT_POINT,
+            h2: &(x * RISTRETTO_BASEPOINT_POINT),
+        };
+        let bytes = prove_noninteractive(publics, Secrets { x: &x }).to_bytes();
+        assert_matches!(
+            Transcript::try_from_bytes(&bytes).map(|t| t.verify(publics)),
+            Ok(Ok(_))
+        );
+
+        let mut bad_point = bytes;
+        bad_point[..32].fill(0xff);
+        let mut bad_scalar = bytes;
+        bad_scalar[95] = 0xff;
+        for bytes in [&bytes[1..], &bad_point, &bad_scalar] {
+            assert_matches!(
+                Transcript::try_from_bytes(bytes),
+                Err(Error::BadProof {
+                    stage: ProofStage::Encoding
+                })
+            );
+        }
+    }
+
+    #[test]
+    fn non_canonical_scalars() {
+        let x = Scalar::random(&mut thread_rng());
+        let publics = Publics {
+            g1: &RISTRETTO_BASEPOINT_POINT,
+            h1: &(x * RISTRETTO_BASEPOINT_POINT),
+            g2: &RISTRETTO_BASEPOINT_POINT,
+            h2: &(x * RISTRETTO_BASEPOINT_POINT),
+        };
+        let t = prove_noninteractive(publics, Secrets { x: &x });
+
+        /// Adds the group order to a scalar, which reduces back to the same scalar
+        fn unreduced(s: &Scalar) -> [u8; 32] {
+            const ORDER: [u8; 32] = [
+                0xed, 0xd3, 0xf5, 0x5c, 0x1a, 0x63, 0x12, 0x58, 0xd6, 0x9c, 0xf7, 0xa2, 0xde, 0xf9,
+                0xde, 0x14, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0x10,
+            ];
+            let mut bytes = [0; 32];
+            let mut carry = 0;
+            for (i, byte) in bytes.iter_mut().enumerate() {
+                let sum = s.as_bytes()[i] as u16 + ORDER[i] as u16 + carry;
+                *byte = sum as u8;
+                carry = sum >> 8;
+            }
+            assert_eq!(Scalar::from_bytes_mod_order(bytes), *s);
+            bytes
+        }
+
+        for (field, offset) in [("c", 64), ("y", 96)] {
+            let scalar = if field == "c" { &t.c } else { &t.y };
+            let mut bytes = t.to_bytes();
+            bytes[offset..offset + 32].copy_from_slice(&unreduced(scalar));
+            assert_matches!(
+                Transcript::try_from_bytes(&bytes),
+                Err(Error::BadProof {
+                    stage: ProofStage::Encoding
+                })
+            );
+
+            let mut json = serde_json::to_value(t).unwrap();
+            json[field] = serde_json::to_value(unreduced(scalar)).unwrap();
+            assert_matches!(serde_json::from_value::<Transcript>(json), Err(_));
+        }
+    }
+
+    #[test]
+    fn recorded_proof() {
+        let x = Scalar::random(&mut thread_rng());
+        let g2 = RistrettoPoint::random(&mut thread_rng());
+        let publics = Publics {
+            g1: &RISTRETTO_BASEPOINT_POINT,
+            h1: &(x * RISTRETTO_BASEPOINT_POINT),
+            g2: &g2,
+            h2: &(x * g2),
+        };
+
+        let (mut p_channel, mut v_channel) = DuplexTransport::<Json>::pair();
+        let (_, t) = block_on(try_join(
+            prove(&mut p_channel, publics, Secrets { x: &x }),
+            verify_recorded(&mut v_channel, publics),
+        ))
+        .unwrap();
+        assert_eq!(t.y * publics.g1, t.a + t.c * publics.h1);
+        assert_eq!(t.y * publics.g2, t.b + t.c * publics.h2);
+        assert_matches!(
+            t.verify(publics),
+            Err(Error::BadProof {
+                stage: ProofStage::ChallengeMismatch
+            })
+        );
+    }
+}
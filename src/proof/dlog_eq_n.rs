@@ -0,0 +1,218 @@
+//! Zero-knowledge proof of equality of any number of discrete logarithms
+
+use crate::{
+    error::{Error, ProofStage, Result},
+    hash::TranscriptProtocol as _,
+};
+#[cfg(feature = "std")]
+use crate::{
+    labels::dlog_eq_n::{CHALLENGE, COMMITMENTS, RESPONSE},
+    transport::LocalTransport,
+};
+use alloc::vec::Vec;
+use curve25519_dalek::{RistrettoPoint, Scalar};
+use rand::{CryptoRng, RngCore};
+use serde::{Deserialize, Serialize};
+
+use super::ensure;
+#[cfg(feature = "getrandom")]
+use super::rng;
+
+/// Public parameters
+///
+/// The bases and the points must have the same length, or the proofs fail with
+/// [`Error::LengthMismatch`].
+#[derive(Copy, Clone)]
+pub struct Publics<'a> {
+    /// Bases
+    pub g: &'a [RistrettoPoint],
+    /// Points
+    pub h: &'a [RistrettoPoint],
+}
+
+impl Publics<'_> {
+    /// Checks that there are as many points as bases
+    fn check_lengths(&self) -> Result {
+        if self.g.len() == self.h.len() {
+            Ok(())
+        } else {
+            Err(Error::LengthMismatch {
+                expected: self.g.len(),
+                got: self.h.len(),
+            })
+        }
+    }
+}
+
+/// Secret parameters
+#[derive(Copy, Clone)]
+pub struct Secrets<'a> {
+    /// Discrete logarithm
+    pub x: &'a Scalar,
+}
+
+/// Performs the protocol for proving equality of discrete logarithms as the prover
+#[cfg(feature = "std")]
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(name = "dlog_eq_n::prove", skip_all)
+)]
+pub async fn prove<T: LocalTransport>(
+    t: &mut T,
+    publics: Publics<'_>,
+    secrets: Secrets<'_>,
+) -> Result<(), Error> {
+    publics.check_lengths()?;
+    let r = Scalar::random(&mut rng());
+    let a: Vec<_> = publics.g.iter().map(|g| r * g).collect();
+    t.send(COMMITMENTS, a).await?;
+    let c: Scalar = t.receive(CHALLENGE).await?;
+    let y = r + c * secrets.x;
+    t.send(RESPONSE, y).await?;
+    t.flush().await?;
+    Ok(())
+}
+
+/// Performs the protocol for proving equality of discrete logarithms as the verifier
+#[cfg(feature = "std")]
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(name = "dlog_eq_n::verify", skip_all)
+)]
+pub async fn verify<T: LocalTransport>(t: &mut T, publics: Publics<'_>) -> Result<(), Error> {
+    publics.check_lengths()?;
+    let a: Vec<RistrettoPoint> = t.receive(COMMITMENTS).await?;
+    let c = Scalar::random(&mut rng());
+    t.send(CHALLENGE, c).await?;
+    let y: Scalar = t.receive(RESPONSE).await?;
+    check(publics, &a, c, y)
+}
+
+/// Performs the protocol for proving equality of discrete logarithms non-interactively
+#[cfg(feature = "getrandom")]
+pub fn prove_noninteractive(publics: Publics, secrets: Secrets) -> Result<Transcript> {
+    prove_noninteractive_with_rng(publics, secrets, &mut rng())
+}
+
+/// Performs the protocol for proving equality of discrete logarithms non-interactively, with a
+/// given source of randomness
+pub fn prove_noninteractive_with_rng<R: CryptoRng + RngCore>(
+    publics: Publics,
+    secrets: Secrets,
+    rng: &mut R,
+) -> Result<Transcript> {
+    publics.check_lengths()?;
+    let r = Scalar::random(rng);
+    let a: Vec<_> = publics.g.iter().map(|g| r * g).collect();
+    let c = non_interactive_challenge_for(publics, &a);
+    let y = r + c * secrets.x;
+    Ok(Transcript { a, c, y })
+}
+
+/// A transcript of the non-interactive protocol
+#[derive(PartialEq, Eq, Debug, Clone, Serialize, Deserialize)]
+pub struct Transcript {
+    /// Commitments, one per base
+    pub a: Vec<RistrettoPoint>,
+    /// Challenge
+    pub c: Scalar,
+    /// Response
+    pub y: Scalar,
+}
+
+impl Transcript {
+    /// Verifies this transcript
+    pub fn verify(&self, publics: Publics) -> Result {
+        publics.check_lengths()?;
+        let c_ok = self.c == non_interactive_challenge_for(publics, &self.a);
+        ensure(c_ok, ProofStage::ChallengeMismatch)?;
+        check(publics, &self.a, self.c, self.y)
+    }
+}
+
+/// Generates a non-interactive challenge for a proof of equality of discrete logarithms
+pub fn non_interactive_challenge_for(publics: Publics, a: &[RistrettoPoint]) -> Scalar {
+    let mut h = merlin::Transcript::new(b"nym/0.1/dlog-eq-n-proof/non-interactive-challenge");
+    h.commit(b"g", publics.g);
+    h.commit(b"h", publics.h);
+    h.commit(b"a", a);
+    h.challenge(b"c")
+}
+
+/// Checks the verification equations
+fn check(publics: Publics, a: &[RistrettoPoint], c: Scalar, y: Scalar) -> Result {
+    ensure(a.len() == publics.g.len(), ProofStage::CommitmentCount)?;
+    for (i, ((g, h), a)) in publics.g.iter().zip(publics.h).zip(a).enumerate() {
+        ensure(y * g == a + c * h, ProofStage::Equation(i))?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use std::assert_matches::assert_matches;
+
+    use curve25519_dalek::{RistrettoPoint, Scalar};
+    use futures::executor::block_on;
+    use rand::thread_rng;
+
+    use crate::{
+        transport::{codec::Json, memory::DuplexTransport},
+        Error,
+    };
+
+    use super::{prove, prove_noninteractive, verify, Publics, Secrets};
+
+    #[test]
+    fn noninteractive_proof() {
+        let x = Scalar::random(&mut thread_rng());
+        let g: Vec<_> = (0..5)
+            .map(|_| RistrettoPoint::random(&mut thread_rng()))
+            .collect();
+        let mut h: Vec<_> = g.iter().map(|g| x * g).collect();
+        let publics = Publics { g: &g, h: &h };
+        let t = prove_noninteractive(publics, Secrets { x: &x }).unwrap();
+        assert_matches!(t.verify(publics), Ok(_));
+
+        h[2] = RistrettoPoint::random(&mut thread_rng());
+        let publics = Publics { g: &g, h: &h };
+        let t = prove_noninteractive(publics, Secrets { x: &x }).unwrap();
+        assert_matches!(t.verify(publics), Err(Error::BadProof { .. }));
+    }
+
+    #[test]
+    fn length_mismatch() {
+        let x = Scalar::random(&mut thread_rng());
+        let g: Vec<_> = (0..3)
+            .map(|_| RistrettoPoint::random(&mut thread_rng()))
+            .collect();
+        let h: Vec<_> = g.iter().map(|g| x * g).collect();
+        let t = prove_noninteractive(Publics { g: &g, h: &h }, Secrets { x: &x }).unwrap();
+
+        let short = Publics { g: &g, h: &h[..2] };
+        assert_matches!(
+            prove_noninteractive(short, Secrets { x: &x }),
+            Err(Error::LengthMismatch {
+                expected: 3,
+                got: 2
+            })
+        );
+        assert_matches!(
+            t.verify(short),
+            Err(Error::LengthMismatch {
+                expected: 3,
+                got: 2
+            })
+        );
+
+        let (mut u_channel, mut o_channel) = DuplexTransport::<Json>::pair();
+        assert_matches!(
+            block_on(prove(&mut u_channel, short, Secrets { x: &x })),
+            Err(Error::LengthMismatch { .. })
+        );
+        assert_matches!(
+            block_on(verify(&mut o_channel, short)),
+            Err(Error::LengthMismatch { .. })
+        );
+    }
+}
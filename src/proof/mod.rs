@@ -1,4 +1,185 @@
 //! Zero-knowledge proofs used in nyms
 
+#[cfg(feature = "std")]
 pub mod blind_dlog_eq;
+pub mod dlog;
 pub mod dlog_eq;
+pub mod dlog_eq_n;
+pub mod range;
+
+/// Version of the messages exchanged by the interactive proofs
+///
+/// This changes whenever the messages change in a way that breaks compatibility with peers running
+/// an older version. Since version 2, the two commitments of [`dlog_eq`] and [`blind_dlog_eq`]
+/// are sent together in a single message. Since version 3, nym generation starts by exchanging
+/// this version. Since version 4, nym authentication is a non-interactive proof bound to a nonce
+/// from the organization.
+///
+/// Peers check it on the wire when generating a nym, and fail with
+/// [`Error::VersionMismatch`](crate::Error::VersionMismatch) if they speak different versions. The
+/// proofs in this module do not exchange it themselves.
+pub const WIRE_VERSION: u32 = 4;
+
+#[cfg(feature = "std")]
+use curve25519_dalek::traits::IsIdentity as _;
+use curve25519_dalek::{
+    constants::{RISTRETTO_BASEPOINT_POINT, RISTRETTO_BASEPOINT_TABLE},
+    RistrettoPoint, Scalar,
+};
+#[cfg(feature = "getrandom")]
+use rand::{CryptoRng, RngCore};
+
+use crate::{
+    error::{Error, ProofStage, Result},
+    hash::TranscriptProtocol as _,
+};
+
+/// Length of the challenges drawn by the verifier of an interactive proof
+///
+/// A prover that doesn't know the secret can only answer one challenge for each commitment, so it
+/// cheats successfully with probability 2<sup>-bits</sup>. Shorter challenges are cheaper to
+/// multiply by, but 128 bits is about as short as they can get while keeping that probability
+/// negligible.
+#[derive(PartialEq, Eq, Debug, Copy, Clone)]
+pub struct ChallengeLength(u32);
+
+impl ChallengeLength {
+    /// Full-width challenges, uniform over all scalars
+    pub const FULL: Self = Self(256);
+
+    /// Challenges of a given number of bits
+    ///
+    /// # Panics
+    ///
+    /// Panics unless the number of bits is between 1 and 252, as wider challenges would not fit
+    /// below the group order.
+    pub const fn bits(bits: u32) -> Self {
+        assert!(
+            bits > 0 && bits <= 252,
+            "challenge length should be 1 to 252 bits"
+        );
+        Self(bits)
+    }
+
+    /// Draws a random challenge of this length
+    #[cfg(feature = "std")]
+    pub(crate) fn random<R: CryptoRng + RngCore>(self, rng: &mut R) -> Scalar {
+        if self == Self::FULL {
+            return Scalar::random(rng);
+        }
+        let mut bytes = [0; 32];
+        rng.fill_bytes(&mut bytes);
+        for (i, byte) in bytes.iter_mut().enumerate() {
+            let low = 8 * i as u32;
+            if low >= self.0 {
+                *byte = 0;
+            } else if self.0 - low < 8 {
+                *byte &= (1 << (self.0 - low)) - 1;
+            }
+        }
+        Scalar::from_bytes_mod_order(bytes)
+    }
+}
+
+impl Default for ChallengeLength {
+    fn default() -> Self {
+        Self::FULL
+    }
+}
+
+/// Derives a generator from a seed, such that nobody knows its discrete logarithm
+///
+/// The generator is [`RistrettoPoint::from_uniform_bytes`] over challenge bytes from a transcript
+/// with `domain` as its label and `seed` committed to it, so anyone can recompute it from the
+/// seed. Whoever chooses a base for a proof may know its discrete logarithm to another base, which
+/// is a trapdoor: with `g2 = k·g1`, they can relate `x·g2` to `x·g1` without knowing `x`, e.g. to
+/// link the points or to open commitments both ways. A derived base has no such trapdoor, as
+/// finding its discrete logarithm would mean inverting the hash.
+pub fn derive_generator(domain: &'static [u8], seed: &[u8]) -> RistrettoPoint {
+    let mut t = merlin::Transcript::new(domain);
+    t.commit(b"seed", seed);
+    t.challenge(b"generator")
+}
+
+/// Gets the source of randomness for the protocols
+///
+/// This is the operating system's generator, through `getrandom`, which needs neither `std` nor
+/// `rand`'s thread-local machinery.
+#[cfg(feature = "getrandom")]
+pub(crate) fn rng() -> impl CryptoRng + RngCore {
+    rand_core::OsRng
+}
+
+/// Rejects identity points received from a peer, as they make the protocol equations degenerate
+#[cfg(feature = "std")]
+pub(crate) fn ensure_not_identity(points: &[&RistrettoPoint]) -> Result {
+    ensure(
+        !points.iter().any(|p| p.is_identity()),
+        ProofStage::IdentityPoint,
+    )
+}
+
+/// Fails with a proof error at the given stage unless a check passed
+pub(crate) fn ensure(ok: bool, stage: ProofStage) -> Result {
+    if ok {
+        Ok(())
+    } else {
+        Err(Error::BadProof { stage })
+    }
+}
+
+/// Multiplies a point by a scalar, using a precomputed table when the point is the base point
+pub(crate) fn mul(scalar: &Scalar, point: &RistrettoPoint) -> RistrettoPoint {
+    if *point == RISTRETTO_BASEPOINT_POINT {
+        scalar * RISTRETTO_BASEPOINT_TABLE
+    } else {
+        scalar * point
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use curve25519_dalek::{constants::RISTRETTO_BASEPOINT_POINT, RistrettoPoint, Scalar};
+    use rand::thread_rng;
+
+    use super::{derive_generator, mul, ChallengeLength};
+
+    #[test]
+    fn basepoint_table() {
+        for _ in 0..10 {
+            let s = Scalar::random(&mut thread_rng());
+            assert_eq!(
+                mul(&s, &RISTRETTO_BASEPOINT_POINT),
+                s * RISTRETTO_BASEPOINT_POINT
+            );
+            let p = RistrettoPoint::random(&mut thread_rng());
+            assert_eq!(mul(&s, &p), s * p);
+        }
+    }
+
+    #[test]
+    fn derived_generator() {
+        let g = derive_generator(b"test-domain", b"seed");
+        assert_eq!(g, derive_generator(b"test-domain", b"seed"));
+        assert_ne!(g, RISTRETTO_BASEPOINT_POINT);
+        assert_ne!(g, derive_generator(b"test-domain", b"other seed"));
+        assert_ne!(g, derive_generator(b"other-domain", b"seed"));
+    }
+
+    #[test]
+    fn short_challenges() {
+        for bits in [1, 7, 64, 128, 252] {
+            for _ in 0..10 {
+                let c = ChallengeLength::bits(bits).random(&mut thread_rng());
+                let bytes = c.as_bytes();
+                let top = bits as usize / 8;
+                assert!(bytes[top + 1..].iter().all(|&b| b == 0));
+                assert_eq!(
+                    bytes[top] >> (bits % 8),
+                    0,
+                    "challenge should fit in {bits} bits"
+                );
+            }
+        }
+    }
+}
@@ -0,0 +1,374 @@
+//! Zero-knowledge proof that a committed value lies in a range
+//!
+//! Values are committed to as `v·G + r·H`, where `H` is [`blinding_generator`]. To show that `v`
+//! lies in `[min, max]`, both `v - min` and `max - v` are split into as many bits as `max - min`
+//! has, and each bit is committed to separately, with a proof that it is either 0 or 1. The bit
+//! commitments add up to the commitment to the value, so neither difference can be negative.
+
+use crate::{
+    error::{Error, ProofStage, Result},
+    hash::TranscriptProtocol as _,
+};
+use alloc::vec::Vec;
+use curve25519_dalek::{
+    constants::{RISTRETTO_BASEPOINT_POINT, RISTRETTO_BASEPOINT_TABLE},
+    traits::Identity as _,
+    RistrettoPoint, Scalar,
+};
+use rand::{CryptoRng, RngCore};
+use serde::{Deserialize, Serialize};
+
+use super::ensure;
+#[cfg(feature = "getrandom")]
+use super::rng;
+
+/// Gets the generator that blinds commitments
+///
+/// It is derived by hashing, so nobody knows its discrete logarithm to the basepoint.
+pub fn blinding_generator() -> RistrettoPoint {
+    let mut t = merlin::Transcript::new(b"nym/0.1/range-proof/blinding-generator");
+    let mut bytes = [0; 64];
+    t.challenge_bytes(b"H", &mut bytes);
+    RistrettoPoint::from_uniform_bytes(&bytes)
+}
+
+/// Commits to a value with a given blinding factor
+pub fn commit(value: u64, blinding: &Scalar) -> RistrettoPoint {
+    commit_scalar(&Scalar::from(value), blinding)
+}
+
+/// Commits to a scalar with a given blinding factor
+///
+/// Only commitments to integers can be shown to lie in a range.
+pub fn commit_scalar(value: &Scalar, blinding: &Scalar) -> RistrettoPoint {
+    value * RISTRETTO_BASEPOINT_TABLE + blinding * blinding_generator()
+}
+
+/// Public parameters
+#[derive(Copy, Clone)]
+pub struct Publics<'a> {
+    /// Commitment to the value
+    pub commitment: &'a RistrettoPoint,
+    /// Smallest value in the range
+    pub min: u64,
+    /// Largest value in the range
+    pub max: u64,
+}
+
+/// Secret parameters
+#[derive(Copy, Clone)]
+pub struct Secrets<'a> {
+    /// Committed value
+    pub value: u64,
+    /// Blinding factor of the commitment
+    pub blinding: &'a Scalar,
+}
+
+/// Performs the protocol for proving that a committed value lies in a range non-interactively
+///
+/// This fails with [`Error::OutOfRange`] if the value is not in the range.
+#[cfg(feature = "getrandom")]
+pub fn prove_noninteractive(publics: Publics, secrets: Secrets) -> Result<Transcript> {
+    prove_noninteractive_with_rng(publics, secrets, &mut rng())
+}
+
+/// Performs the protocol for proving that a committed value lies in a range non-interactively,
+/// with a given source of randomness
+pub fn prove_noninteractive_with_rng<R: CryptoRng + RngCore>(
+    publics: Publics,
+    secrets: Secrets,
+    rng: &mut R,
+) -> Result<Transcript> {
+    let Publics { min, max, .. } = publics;
+    if !(min..=max).contains(&secrets.value) {
+        return Err(Error::OutOfRange);
+    }
+    let bits = bit_length(max - min);
+    let lower = commit_bits(secrets.value - min, secrets.blinding, bits, rng);
+    let upper = commit_bits(max - secrets.value, &-secrets.blinding, bits, rng);
+    let c = non_interactive_challenge_for(
+        publics,
+        lower
+            .iter()
+            .chain(&upper)
+            .map(|bit| (&bit.commitment, &bit.t)),
+    );
+    Ok(Transcript {
+        lower: lower.into_iter().map(|bit| bit.respond(c)).collect(),
+        upper: upper.into_iter().map(|bit| bit.respond(c)).collect(),
+        c,
+    })
+}
+
+/// A proof that a committed bit is either 0 or 1
+#[derive(PartialEq, Eq, Debug, Copy, Clone, Serialize, Deserialize)]
+pub struct BitProof {
+    /// Commitment to the bit
+    pub commitment: RistrettoPoint,
+    /// Challenge for the bit being 0; the one for it being 1 makes up the rest of the challenge
+    pub c0: Scalar,
+    /// Response for the bit being 0
+    pub y0: Scalar,
+    /// Response for the bit being 1
+    pub y1: Scalar,
+}
+
+/// A transcript of the non-interactive protocol
+#[derive(PartialEq, Eq, Debug, Clone, Serialize, Deserialize)]
+pub struct Transcript {
+    /// Bits of the value above the minimum, least significant first
+    pub lower: Vec<BitProof>,
+    /// Bits of the value below the maximum, least significant first
+    pub upper: Vec<BitProof>,
+    /// Challenge
+    pub c: Scalar,
+}
+
+impl Transcript {
+    /// Verifies this transcript
+    pub fn verify(&self, publics: Publics) -> Result {
+        let Publics {
+            commitment,
+            min,
+            max,
+        } = publics;
+        ensure(min <= max, ProofStage::Decomposition)?;
+        let bits = bit_length(max - min);
+        ensure(
+            self.lower.len() == bits && self.upper.len() == bits,
+            ProofStage::CommitmentCount,
+        )?;
+        let g = RISTRETTO_BASEPOINT_POINT;
+        let lower_ok = sum_bits(&self.lower) == commitment - Scalar::from(min) * g;
+        let upper_ok = sum_bits(&self.upper) == Scalar::from(max) * g - commitment;
+        ensure(lower_ok && upper_ok, ProofStage::Decomposition)?;
+
+        let h = blinding_generator();
+        let t: Vec<_> = self
+            .lower
+            .iter()
+            .chain(&self.upper)
+            .map(|bit| {
+                let c1 = self.c - bit.c0;
+                (
+                    bit.y0 * h - bit.c0 * bit.commitment,
+                    bit.y1 * h - c1 * (bit.commitment - g),
+                )
+            })
+            .collect();
+        let commitments = self
+            .lower
+            .iter()
+            .chain(&self.upper)
+            .map(|bit| &bit.commitment);
+        let c = non_interactive_challenge_for(publics, commitments.zip(&t));
+        ensure(self.c == c, ProofStage::ChallengeMismatch)
+    }
+}
+
+/// A committed bit, with the proof that it is 0 or 1 before the challenge is known
+struct BitWitness {
+    bit: bool,
+    blinding: Scalar,
+    nonce: Scalar,
+    simulated: (Scalar, Scalar),
+    commitment: RistrettoPoint,
+    t: (RistrettoPoint, RistrettoPoint),
+}
+
+impl BitWitness {
+    /// Commits to a bit, running the proof for its value and simulating the other one
+    fn new<R: CryptoRng + RngCore>(
+        bit: bool,
+        blinding: Scalar,
+        h: RistrettoPoint,
+        rng: &mut R,
+    ) -> Self {
+        let g = RISTRETTO_BASEPOINT_POINT;
+        let commitment = if bit { g } else { RistrettoPoint::identity() } + blinding * h;
+        let nonce = Scalar::random(rng);
+        let (c, y) = (Scalar::random(rng), Scalar::random(rng));
+        let t = if bit {
+            (y * h - c * commitment, nonce * h)
+        } else {
+            (nonce * h, y * h - c * (commitment - g))
+        };
+        Self {
+            bit,
+            blinding,
+            nonce,
+            simulated: (c, y),
+            commitment,
+            t,
+        }
+    }
+
+    /// Answers the challenge, splitting it between the real and the simulated proof
+    fn respond(self, c: Scalar) -> BitProof {
+        let (c_sim, y_sim) = self.simulated;
+        let c_real = c - c_sim;
+        let y_real = self.nonce + c_real * self.blinding;
+        let (c0, y0, y1) = if self.bit {
+            (c_sim, y_sim, y_real)
+        } else {
+            (c_real, y_real, y_sim)
+        };
+        BitProof {
+            commitment: self.commitment,
+            c0,
+            y0,
+            y1,
+        }
+    }
+}
+
+/// Commits to the lowest bits of a value, with blinding factors that add up to a given one
+fn commit_bits<R: CryptoRng + RngCore>(
+    value: u64,
+    blinding: &Scalar,
+    bits: usize,
+    rng: &mut R,
+) -> Vec<BitWitness> {
+    let mut blindings: Vec<_> = (1..bits).map(|_| Scalar::random(rng)).collect();
+    let rest = blindings
+        .iter()
+        .enumerate()
+        .fold(*blinding, |acc, (i, r)| acc - power_of_two(i) * r);
+    blindings.push(rest * power_of_two(bits - 1).invert());
+    let h = blinding_generator();
+    blindings
+        .into_iter()
+        .enumerate()
+        .map(|(i, r)| BitWitness::new(value >> i & 1 == 1, r, h, rng))
+        .collect()
+}
+
+/// Adds up bit commitments, each weighted by its place
+fn sum_bits(bits: &[BitProof]) -> RistrettoPoint {
+    bits.iter()
+        .enumerate()
+        .map(|(i, bit)| power_of_two(i) * bit.commitment)
+        .sum()
+}
+
+/// Gets 2<sup>i</sup> as a scalar
+fn power_of_two(i: usize) -> Scalar {
+    Scalar::from(1u128 << i)
+}
+
+/// Gets the number of bits needed to write a value, and at least one
+fn bit_length(value: u64) -> usize {
+    (u64::BITS - value.leading_zeros()).max(1) as usize
+}
+
+/// Generates a non-interactive challenge for a range proof
+pub fn non_interactive_challenge_for<'a>(
+    publics: Publics,
+    bits: impl IntoIterator<Item = (&'a RistrettoPoint, &'a (RistrettoPoint, RistrettoPoint))>,
+) -> Scalar {
+    let mut h = merlin::Transcript::new(b"nym/0.1/range-proof/non-interactive-challenge");
+    h.commit(b"commitment", publics.commitment);
+    h.commit(b"min", &publics.min);
+    h.commit(b"max", &publics.max);
+    for (commitment, (t0, t1)) in bits {
+        h.commit(b"bit", commitment);
+        h.commit(b"t0", t0);
+        h.commit(b"t1", t1);
+    }
+    h.challenge(b"c")
+}
+
+#[cfg(test)]
+mod test {
+    use std::assert_matches::assert_matches;
+
+    use curve25519_dalek::Scalar;
+    use rand::thread_rng;
+
+    use crate::{Error, ProofStage};
+
+    use super::{commit, prove_noninteractive, Publics, Secrets};
+
+    fn prove(value: u64, min: u64, max: u64) -> Result<(), Error> {
+        let blinding = Scalar::random(&mut thread_rng());
+        let commitment = commit(value, &blinding);
+        let publics = Publics {
+            commitment: &commitment,
+            min,
+            max,
+        };
+        let t = prove_noninteractive(
+            publics,
+            Secrets {
+                value,
+                blinding: &blinding,
+            },
+        )?;
+        t.verify(publics)
+    }
+
+    #[test]
+    fn in_range() {
+        assert_matches!(prove(30, 18, 150), Ok(()));
+        assert_matches!(prove(18, 18, 150), Ok(()));
+        assert_matches!(prove(150, 18, 150), Ok(()));
+        assert_matches!(prove(7, 7, 7), Ok(()));
+        assert_matches!(prove(u64::MAX - 1, u64::MAX - 3, u64::MAX), Ok(()));
+    }
+
+    #[test]
+    fn out_of_range() {
+        assert_matches!(prove(17, 18, 150), Err(Error::OutOfRange));
+        assert_matches!(prove(151, 18, 150), Err(Error::OutOfRange));
+        assert_matches!(prove(20, 30, 10), Err(Error::OutOfRange));
+    }
+
+    #[test]
+    fn wrong_range() {
+        let blinding = Scalar::random(&mut thread_rng());
+        let commitment = commit(30, &blinding);
+        let publics = Publics {
+            commitment: &commitment,
+            min: 18,
+            max: 150,
+        };
+        let secrets = Secrets {
+            value: 30,
+            blinding: &blinding,
+        };
+        let t = prove_noninteractive(publics, secrets).unwrap();
+
+        let res = t.verify(Publics { min: 19, ..publics });
+        assert_matches!(
+            res,
+            Err(Error::BadProof {
+                stage: ProofStage::Decomposition
+            })
+        );
+        let res = t.verify(Publics {
+            max: 200,
+            ..publics
+        });
+        assert_matches!(
+            res,
+            Err(Error::BadProof {
+                stage: ProofStage::Decomposition
+            })
+        );
+        let other = commit(31, &blinding);
+        let res = t.verify(Publics {
+            commitment: &other,
+            ..publics
+        });
+        assert_matches!(res, Err(Error::BadProof { .. }));
+
+        let mut tampered = t;
+        tampered.lower[0].y1 += Scalar::ONE;
+        assert_matches!(
+            tampered.verify(publics),
+            Err(Error::BadProof {
+                stage: ProofStage::ChallengeMismatch
+            })
+        );
+    }
+}
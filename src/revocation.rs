@@ -0,0 +1,32 @@
+//! Nym revocation
+
+use alloc::collections::BTreeSet;
+
+use crate::Nym;
+
+/// A list of revoked pseudonyms
+#[derive(Default, Debug, Clone)]
+pub struct RevocationList {
+    revoked: BTreeSet<[[u8; 32]; 2]>,
+}
+
+impl RevocationList {
+    /// Creates an empty revocation list
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Revokes a pseudonym
+    pub fn revoke(&mut self, nym: Nym) {
+        self.revoked.insert(key_for(&nym));
+    }
+
+    /// Checks whether a pseudonym was revoked
+    pub fn is_revoked(&self, nym: &Nym) -> bool {
+        self.revoked.contains(&key_for(nym))
+    }
+}
+
+fn key_for(nym: &Nym) -> [[u8; 32]; 2] {
+    [nym.a.compress().to_bytes(), nym.b.compress().to_bytes()]
+}
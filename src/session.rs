@@ -0,0 +1,435 @@
+//! Sessions wrapping the protocol choreography
+//!
+//! A session holds a transport to a single peer and exposes each protocol as one method call.
+//! Both sides must run the matching session calls in the same order: each [`UserSession`] method
+//! pairs with the [`OrgSession`] method of the same name.
+//!
+//! The typed sessions, [`TypedUserSession`] and [`TypedOrgSession`], also enforce the order of the
+//! calls: a credential can only be issued or transferred once a pseudonym has been established,
+//! and then only for that pseudonym. Establishing the pseudonym consumes the session and returns
+//! it in the next state. The untyped sessions remain available, and the two convert into each
+//! other with [`UserSession::typed`] and [`TypedUserSession::into_untyped`].
+
+use curve25519_dalek::Scalar;
+
+use crate::{
+    error::Result,
+    key::{OrgPublicKey, UserPublicKey},
+    nym::{AttrCred, Cred, Nym, Org, User},
+    transport::LocalTransport,
+};
+
+/// Session state before a pseudonym has been established
+pub struct NeedsNym;
+
+/// Session state once a pseudonym has been established
+pub struct HasNym(Nym);
+
+/// A user's session with an organization
+pub struct UserSession<'u, T> {
+    user: &'u User,
+    org: T,
+}
+
+/// An organization's session with a user
+pub struct OrgSession<'o, T> {
+    org: &'o Org,
+    user: T,
+}
+
+/// A user's session with an organization, in a given state
+pub struct TypedUserSession<'u, T, S> {
+    session: UserSession<'u, T>,
+    state: S,
+}
+
+/// An organization's session with a user, in a given state
+pub struct TypedOrgSession<'o, T, S> {
+    session: OrgSession<'o, T>,
+    state: S,
+}
+
+impl<'u, T: LocalTransport> UserSession<'u, T> {
+    /// Starts a session with an organization over a given transport
+    pub fn new(user: &'u User, org: T) -> Self {
+        Self { user, org }
+    }
+
+    /// Gets the transport back
+    pub fn into_transport(self) -> T {
+        self.org
+    }
+
+    /// Turns this into a typed session, before any pseudonym has been established
+    pub fn typed(self) -> TypedUserSession<'u, T, NeedsNym> {
+        TypedUserSession {
+            session: self,
+            state: NeedsNym,
+        }
+    }
+
+    /// Generates a pseudonym, paired with [`OrgSession::generate_nym`]
+    pub async fn generate_nym(&mut self) -> Result<Nym> {
+        self.user.generate_nym(&mut self.org).await
+    }
+
+    /// Generates a pseudonym with a CA, paired with [`OrgSession::generate_nym_as_ca`]
+    pub async fn generate_nym_with_ca(&mut self) -> Result<Nym> {
+        self.user.generate_nym_with_ca(&mut self.org).await
+    }
+
+    /// Authenticates as the holder of a pseudonym, paired with [`OrgSession::authenticate_nym`]
+    pub async fn authenticate_nym(&mut self, nym: Nym) -> Result {
+        self.user.authenticate_nym(&mut self.org, nym).await
+    }
+
+    /// Obtains a credential for a pseudonym, paired with [`OrgSession::issue_credential`]
+    pub async fn issue_credential(
+        &mut self,
+        nym: Nym,
+        source_key: OrgPublicKey,
+        epoch: u64,
+    ) -> Result<Cred> {
+        self.user
+            .issue_credential(&mut self.org, nym, source_key, epoch)
+            .await
+    }
+
+    /// Obtains a credential with attributes for a pseudonym, paired with
+    /// [`OrgSession::issue_credential_with_attributes`]
+    pub async fn issue_credential_with_attributes(
+        &mut self,
+        nym: Nym,
+        source_key: OrgPublicKey,
+        epoch: u64,
+        attributes: &[Scalar],
+    ) -> Result<AttrCred> {
+        self.user
+            .issue_credential_with_attributes(&mut self.org, nym, source_key, epoch, attributes)
+            .await
+    }
+
+    /// Transfers a credential to this organization, paired with
+    /// [`OrgSession::transfer_credential`]
+    pub async fn transfer_credential(&mut self, nym: Nym, cred: Cred) -> Result {
+        self.user
+            .transfer_credential(&mut self.org, nym, cred)
+            .await
+    }
+}
+
+impl<'o, T: LocalTransport> OrgSession<'o, T> {
+    /// Starts a session with a user over a given transport
+    pub fn new(org: &'o Org, user: T) -> Self {
+        Self { org, user }
+    }
+
+    /// Gets the transport back
+    pub fn into_transport(self) -> T {
+        self.user
+    }
+
+    /// Turns this into a typed session, before any pseudonym has been established
+    pub fn typed(self) -> TypedOrgSession<'o, T, NeedsNym> {
+        TypedOrgSession {
+            session: self,
+            state: NeedsNym,
+        }
+    }
+
+    /// Generates a pseudonym, paired with [`UserSession::generate_nym`]
+    pub async fn generate_nym(&mut self) -> Result<Nym> {
+        self.org.generate_nym(&mut self.user).await
+    }
+
+    /// Generates a pseudonym as the CA, paired with [`UserSession::generate_nym_with_ca`]
+    pub async fn generate_nym_as_ca(&mut self, user_key: UserPublicKey) -> Result<Nym> {
+        self.org.generate_nym_as_ca(&mut self.user, user_key).await
+    }
+
+    /// Authenticates the user as the holder of a pseudonym, paired with
+    /// [`UserSession::authenticate_nym`]
+    pub async fn authenticate_nym(&mut self, nym: Nym) -> Result {
+        self.org.authenticate_nym(&mut self.user, nym).await
+    }
+
+    /// Issues a credential for a pseudonym, paired with [`UserSession::issue_credential`]
+    pub async fn issue_credential(&mut self, nym: Nym, epoch: u64) -> Result {
+        self.org.issue_credential(&mut self.user, nym, epoch).await
+    }
+
+    /// Issues a credential with attributes for a pseudonym, paired with
+    /// [`UserSession::issue_credential_with_attributes`]
+    pub async fn issue_credential_with_attributes(
+        &mut self,
+        nym: Nym,
+        epoch: u64,
+        attributes: &[Scalar],
+    ) -> Result {
+        self.org
+            .issue_credential_with_attributes(&mut self.user, nym, epoch, attributes)
+            .await
+    }
+
+    /// Accepts a credential transferred from another organization, paired with
+    /// [`UserSession::transfer_credential`]
+    pub async fn transfer_credential(
+        &mut self,
+        nym: Nym,
+        cred: Cred,
+        source_key: OrgPublicKey,
+        epoch: u64,
+    ) -> Result {
+        self.org
+            .transfer_credential(&mut self.user, nym, cred, source_key, epoch)
+            .await
+    }
+}
+
+impl<'u, T: LocalTransport, S> TypedUserSession<'u, T, S> {
+    /// Gets the untyped session back
+    pub fn into_untyped(self) -> UserSession<'u, T> {
+        self.session
+    }
+
+    fn with_nym(self, nym: Nym) -> TypedUserSession<'u, T, HasNym> {
+        TypedUserSession {
+            session: self.session,
+            state: HasNym(nym),
+        }
+    }
+}
+
+impl<'u, T: LocalTransport> TypedUserSession<'u, T, NeedsNym> {
+    /// Starts a session with an organization over a given transport
+    pub fn new(user: &'u User, org: T) -> Self {
+        UserSession::new(user, org).typed()
+    }
+
+    /// Generates a pseudonym, paired with [`TypedOrgSession::generate_nym`]
+    pub async fn generate_nym(mut self) -> Result<TypedUserSession<'u, T, HasNym>> {
+        let nym = self.session.generate_nym().await?;
+        Ok(self.with_nym(nym))
+    }
+
+    /// Generates a pseudonym with a CA, paired with [`TypedOrgSession::generate_nym_as_ca`]
+    pub async fn generate_nym_with_ca(mut self) -> Result<TypedUserSession<'u, T, HasNym>> {
+        let nym = self.session.generate_nym_with_ca().await?;
+        Ok(self.with_nym(nym))
+    }
+
+    /// Authenticates as the holder of a pseudonym generated in an earlier session, paired with
+    /// [`TypedOrgSession::authenticate_nym`]
+    pub async fn authenticate_nym(mut self, nym: Nym) -> Result<TypedUserSession<'u, T, HasNym>> {
+        self.session.authenticate_nym(nym).await?;
+        Ok(self.with_nym(nym))
+    }
+}
+
+impl<'u, T: LocalTransport> TypedUserSession<'u, T, HasNym> {
+    /// Gets the pseudonym established in this session
+    pub fn nym(&self) -> Nym {
+        self.state.0
+    }
+
+    /// Authenticates again as the holder of the pseudonym, paired with
+    /// [`TypedOrgSession::authenticate`]
+    pub async fn authenticate(&mut self) -> Result {
+        self.session.authenticate_nym(self.state.0).await
+    }
+
+    /// Obtains a credential for the pseudonym, paired with [`TypedOrgSession::issue_credential`]
+    pub async fn issue_credential(&mut self, source_key: OrgPublicKey, epoch: u64) -> Result<Cred> {
+        self.session
+            .issue_credential(self.state.0, source_key, epoch)
+            .await
+    }
+
+    /// Obtains a credential with attributes for the pseudonym, paired with
+    /// [`TypedOrgSession::issue_credential_with_attributes`]
+    pub async fn issue_credential_with_attributes(
+        &mut self,
+        source_key: OrgPublicKey,
+        epoch: u64,
+        attributes: &[Scalar],
+    ) -> Result<AttrCred> {
+        self.session
+            .issue_credential_with_attributes(self.state.0, source_key, epoch, attributes)
+            .await
+    }
+
+    /// Transfers a credential to this organization, paired with
+    /// [`TypedOrgSession::transfer_credential`]
+    pub async fn transfer_credential(&mut self, cred: Cred) -> Result {
+        self.session.transfer_credential(self.state.0, cred).await
+    }
+}
+
+impl<'o, T: LocalTransport, S> TypedOrgSession<'o, T, S> {
+    /// Gets the untyped session back
+    pub fn into_untyped(self) -> OrgSession<'o, T> {
+        self.session
+    }
+
+    fn with_nym(self, nym: Nym) -> TypedOrgSession<'o, T, HasNym> {
+        TypedOrgSession {
+            session: self.session,
+            state: HasNym(nym),
+        }
+    }
+}
+
+impl<'o, T: LocalTransport> TypedOrgSession<'o, T, NeedsNym> {
+    /// Starts a session with a user over a given transport
+    pub fn new(org: &'o Org, user: T) -> Self {
+        OrgSession::new(org, user).typed()
+    }
+
+    /// Generates a pseudonym, paired with [`TypedUserSession::generate_nym`]
+    pub async fn generate_nym(mut self) -> Result<TypedOrgSession<'o, T, HasNym>> {
+        let nym = self.session.generate_nym().await?;
+        Ok(self.with_nym(nym))
+    }
+
+    /// Generates a pseudonym as the CA, paired with [`TypedUserSession::generate_nym_with_ca`]
+    pub async fn generate_nym_as_ca(
+        mut self,
+        user_key: UserPublicKey,
+    ) -> Result<TypedOrgSession<'o, T, HasNym>> {
+        let nym = self.session.generate_nym_as_ca(user_key).await?;
+        Ok(self.with_nym(nym))
+    }
+
+    /// Authenticates the user as the holder of a pseudonym generated in an earlier session,
+    /// paired with [`TypedUserSession::authenticate_nym`]
+    pub async fn authenticate_nym(mut self, nym: Nym) -> Result<TypedOrgSession<'o, T, HasNym>> {
+        self.session.authenticate_nym(nym).await?;
+        Ok(self.with_nym(nym))
+    }
+}
+
+impl<'o, T: LocalTransport> TypedOrgSession<'o, T, HasNym> {
+    /// Gets the pseudonym established in this session
+    pub fn nym(&self) -> Nym {
+        self.state.0
+    }
+
+    /// Authenticates the user again as the holder of the pseudonym, paired with
+    /// [`TypedUserSession::authenticate`]
+    pub async fn authenticate(&mut self) -> Result {
+        self.session.authenticate_nym(self.state.0).await
+    }
+
+    /// Issues a credential for the pseudonym, paired with [`TypedUserSession::issue_credential`]
+    pub async fn issue_credential(&mut self, epoch: u64) -> Result {
+        self.session.issue_credential(self.state.0, epoch).await
+    }
+
+    /// Issues a credential with attributes for the pseudonym, paired with
+    /// [`TypedUserSession::issue_credential_with_attributes`]
+    pub async fn issue_credential_with_attributes(
+        &mut self,
+        epoch: u64,
+        attributes: &[Scalar],
+    ) -> Result {
+        self.session
+            .issue_credential_with_attributes(self.state.0, epoch, attributes)
+            .await
+    }
+
+    /// Accepts a credential transferred from another organization, paired with
+    /// [`TypedUserSession::transfer_credential`]
+    pub async fn transfer_credential(
+        &mut self,
+        cred: Cred,
+        source_key: OrgPublicKey,
+        epoch: u64,
+    ) -> Result {
+        self.session
+            .transfer_credential(self.state.0, cred, source_key, epoch)
+            .await
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use futures::{executor::block_on, future::try_join};
+    use rand::thread_rng;
+
+    use crate::{
+        transport::{codec::Json, memory::DuplexTransport},
+        Error, Org, OrgSecretKey, User, UserSecretKey,
+    };
+
+    use super::{OrgSession, TypedOrgSession, TypedUserSession, UserSession};
+
+    #[test]
+    fn full_session() {
+        let user = User::new(UserSecretKey::random(&mut thread_rng()));
+        let org = Org::new(OrgSecretKey::random(&mut thread_rng()));
+
+        let (u_channel, o_channel) = DuplexTransport::<Json>::pair();
+        let user_side = async {
+            let mut session = UserSession::new(&user, u_channel);
+            let nym = session.generate_nym().await?;
+            session.authenticate_nym(nym).await?;
+            session.issue_credential(nym, org.public_key(), 0).await
+        };
+        let org_side = async {
+            let mut session = OrgSession::new(&org, o_channel);
+            let nym = session.generate_nym().await?;
+            session.authenticate_nym(nym).await?;
+            session.issue_credential(nym, 0).await
+        };
+        block_on(try_join(user_side, org_side)).unwrap();
+    }
+
+    #[test]
+    fn typed_session() {
+        let user = User::new(UserSecretKey::random(&mut thread_rng()));
+        let org = Org::new(OrgSecretKey::random(&mut thread_rng()));
+
+        let (u_channel, o_channel) = DuplexTransport::<Json>::pair();
+        let user_side = async {
+            let mut session = TypedUserSession::new(&user, u_channel)
+                .generate_nym()
+                .await?;
+            session.authenticate().await?;
+            session.issue_credential(org.public_key(), 0).await?;
+            Ok::<_, Error>(session.nym())
+        };
+        let org_side = async {
+            let mut session = TypedOrgSession::new(&org, o_channel).generate_nym().await?;
+            session.authenticate().await?;
+            session.issue_credential(0).await?;
+            Ok::<_, Error>(session.nym())
+        };
+        let (n1, n2) = block_on(try_join(user_side, org_side)).unwrap();
+        assert_eq!(n1, n2);
+    }
+
+    #[test]
+    fn typed_session_resumes_untyped() {
+        let user = User::new(UserSecretKey::random(&mut thread_rng()));
+        let org = Org::new(OrgSecretKey::random(&mut thread_rng()));
+
+        let (u_channel, o_channel) = DuplexTransport::<Json>::pair();
+        let user_side = async {
+            let mut session = UserSession::new(&user, u_channel);
+            let nym = session.generate_nym().await?;
+            let mut session = session.typed().authenticate_nym(nym).await?;
+            session.issue_credential(org.public_key(), 0).await?;
+            let mut session = session.into_untyped();
+            session.authenticate_nym(nym).await
+        };
+        let org_side = async {
+            let mut session = OrgSession::new(&org, o_channel);
+            let nym = session.generate_nym().await?;
+            let mut session = session.typed().authenticate_nym(nym).await?;
+            session.issue_credential(0).await?;
+            let mut session = session.into_untyped();
+            session.authenticate_nym(nym).await
+        };
+        block_on(try_join(user_side, org_side)).unwrap();
+    }
+}
@@ -0,0 +1,169 @@
+//! Serialization formats for transports
+
+use core::fmt;
+
+use bincode::Options as _;
+use futures::io;
+use serde::{Deserialize, Serialize};
+
+use super::framed::MAX_FRAME_LENGTH;
+
+/// A serialization format for values sent over a transport
+pub trait Codec {
+    /// Serializes a value
+    fn encode<V: Serialize>(value: &V) -> Result<Vec<u8>, io::Error>;
+
+    /// Deserializes a value
+    fn decode<V: for<'a> Deserialize<'a>>(bytes: &[u8]) -> Result<V, io::Error>;
+}
+
+/// A value could not be serialized or deserialized
+///
+/// Codecs report this wrapped in an [`io::Error`], of kind [`io::ErrorKind::InvalidInput`] when
+/// encoding and [`io::ErrorKind::InvalidData`] when decoding. It converts into
+/// [`Error::Unserializable`](crate::Error::Unserializable) or
+/// [`Error::Serialization`](crate::Error::Serialization) respectively, so that a peer sending
+/// garbage can be told apart from a broken connection, and from a value of ours that can't be
+/// sent.
+#[derive(thiserror::Error, Debug, Clone, PartialEq, Eq)]
+#[error("{0}")]
+pub struct CodecError(pub String);
+
+/// JSON serialization, via `serde_json`
+pub struct Json;
+
+/// Compact binary serialization, via `bincode`
+pub struct Bincode;
+
+/// Canonical binary serialization, for interoperating with other implementations
+///
+/// Every protocol value has exactly one encoding, so that serialized messages can be hashed or
+/// compared byte for byte. Values are encoded as follows, with no padding or self-description:
+///
+/// - points are compressed Ristretto encodings, and scalars canonical little-endian encodings, of
+///   32 bytes each; anything else is rejected when decoding;
+/// - integers are fixed-width and little-endian, and booleans are a single `0` or `1` byte;
+/// - sequences, byte strings and strings are prefixed with their length as a `u64`;
+/// - tuples and structs are the concatenation of their fields, in order;
+/// - options are a `0` byte for `None`, or a `1` byte followed by the value.
+///
+/// Trailing bytes are rejected, as are values longer than a frame. This is the wire format of
+/// [`Bincode`], with those checks; it doesn't depend on `serde_json`.
+pub struct Canonical;
+
+impl Codec for Json {
+    fn encode<V: Serialize>(value: &V) -> Result<Vec<u8>, io::Error> {
+        serde_json::to_vec(value).map_err(encode_error)
+    }
+
+    fn decode<V: for<'a> Deserialize<'a>>(bytes: &[u8]) -> Result<V, io::Error> {
+        serde_json::from_slice(bytes).map_err(decode_error)
+    }
+}
+
+impl Codec for Bincode {
+    fn encode<V: Serialize>(value: &V) -> Result<Vec<u8>, io::Error> {
+        bincode::serialize(value).map_err(encode_error)
+    }
+
+    fn decode<V: for<'a> Deserialize<'a>>(bytes: &[u8]) -> Result<V, io::Error> {
+        bincode::deserialize(bytes).map_err(decode_error)
+    }
+}
+
+impl Codec for Canonical {
+    fn encode<V: Serialize>(value: &V) -> Result<Vec<u8>, io::Error> {
+        canonical().serialize(value).map_err(encode_error)
+    }
+
+    fn decode<V: for<'a> Deserialize<'a>>(bytes: &[u8]) -> Result<V, io::Error> {
+        canonical().deserialize(bytes).map_err(decode_error)
+    }
+}
+
+/// Reports a failure to serialize a value
+pub(crate) fn encode_error(e: impl fmt::Display) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, CodecError(e.to_string()))
+}
+
+/// Reports a failure to deserialize a value
+pub(crate) fn decode_error(e: impl fmt::Display) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, CodecError(e.to_string()))
+}
+
+fn canonical() -> impl bincode::Options {
+    bincode::DefaultOptions::new()
+        .with_fixint_encoding()
+        .with_little_endian()
+        .reject_trailing_bytes()
+        .with_limit(MAX_FRAME_LENGTH as u64)
+}
+
+#[cfg(test)]
+mod test {
+    use std::assert_matches::assert_matches;
+
+    use curve25519_dalek::{constants::RISTRETTO_BASEPOINT_POINT, RistrettoPoint, Scalar};
+
+    use crate::{proof::WIRE_VERSION, Nym};
+
+    use super::{Bincode, Canonical, Codec};
+
+    fn hex(s: &str) -> Vec<u8> {
+        (0..s.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&s[i..i + 2], 16).unwrap())
+            .collect()
+    }
+
+    // Multiples of the generator, from RFC 9496, appendix A.1
+    const G: &str = "e2f2ae0a6abc4e71a884a961c500515f58e30b6aa582dd8db6a65945e08d2d76";
+    const G2: &str = "6a493210f7499cd17fecb510ae0cea23a110e8d5b901f8acadd3095c73a3b919";
+
+    #[test]
+    fn canonical_vectors() {
+        let nym = Nym::from_points(
+            RISTRETTO_BASEPOINT_POINT,
+            RISTRETTO_BASEPOINT_POINT * Scalar::from(2u8),
+        );
+        let bytes = Canonical::encode(&nym).unwrap();
+        assert_eq!(bytes, hex(&format!("{G}{G2}")));
+        assert_eq!(
+            Canonical::decode::<Nym>(&bytes).unwrap().points(),
+            nym.points()
+        );
+
+        let scalars = vec![Scalar::ONE, -Scalar::ONE];
+        assert_eq!(
+            Canonical::encode(&scalars).unwrap(),
+            hex(concat!(
+                "0200000000000000",
+                "0100000000000000000000000000000000000000000000000000000000000000",
+                "ecd3f55c1a631258d69cf7a2def9de1400000000000000000000000000000010"
+            ))
+        );
+        assert_eq!(
+            Canonical::encode(&WIRE_VERSION).unwrap(),
+            WIRE_VERSION.to_le_bytes()
+        );
+        assert_eq!(
+            Canonical::encode(&nym).unwrap(),
+            Bincode::encode(&nym).unwrap()
+        );
+    }
+
+    #[test]
+    fn canonical_rejects() {
+        let mut bytes = hex(&format!("{G}{G2}"));
+        bytes.push(0);
+        assert_matches!(Canonical::decode::<Nym>(&bytes), Err(e) if e.kind() == std::io::ErrorKind::InvalidData);
+
+        // The group order, which is only canonical when reduced to zero
+        let order = hex("edd3f55c1a631258d69cf7a2def9de1400000000000000000000000000000010");
+        assert_matches!(Canonical::decode::<Scalar>(&order), Err(e) if e.kind() == std::io::ErrorKind::InvalidData);
+
+        let mut point = hex(G);
+        point[31] |= 0x80;
+        assert_matches!(Canonical::decode::<RistrettoPoint>(&point), Err(e) if e.kind() == std::io::ErrorKind::InvalidData);
+    }
+}
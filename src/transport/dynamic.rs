@@ -0,0 +1,142 @@
+//! Object-safe transport, for choosing transports at runtime
+
+use std::future::Future;
+
+use futures::{future::BoxFuture, io};
+use serde::{Deserialize, Serialize};
+
+use super::{
+    codec::{Codec, Json},
+    Transport,
+};
+
+/// A transport for values already encoded with the codec `C`, usable as a trait object
+///
+/// [`LocalTransport`](super::LocalTransport) has generic methods, so it cannot be boxed. This
+/// trait moves bytes instead, and `Box<dyn DynTransport<C>>` is a [`Transport`] itself, which
+/// encodes and decodes values with `C` on the way. Implementations report errors and check labels
+/// as other transports do.
+///
+/// Every [`Transport`] is a `DynTransport<Json>`: it receives and sends [`serde_json::Value`]s,
+/// which the bridge encodes and decodes as JSON. As with [`Recording`](super::Recording), the
+/// wrapped transport must then use a self-describing codec, such as [`Json`].
+pub trait DynTransport<C = Json>: Send {
+    /// Receives an encoded value with a given label
+    fn recv_bytes(&mut self, label: &'static [u8]) -> BoxFuture<'_, Result<Vec<u8>, io::Error>>;
+
+    /// Sends an encoded value with a given label
+    fn send_bytes<'a>(
+        &'a mut self,
+        label: &'static [u8],
+        bytes: &'a [u8],
+    ) -> BoxFuture<'a, Result<(), io::Error>>;
+
+    /// Ensures that every value sent so far has been delivered to the peer
+    ///
+    /// This follows the same contract as [`LocalTransport::flush`](super::LocalTransport::flush),
+    /// and the default implementation does nothing.
+    fn flush_bytes(&mut self) -> BoxFuture<'_, Result<(), io::Error>> {
+        Box::pin(async { Ok(()) })
+    }
+}
+
+impl<T: Transport + Send> DynTransport<Json> for T {
+    fn recv_bytes(&mut self, label: &'static [u8]) -> BoxFuture<'_, Result<Vec<u8>, io::Error>> {
+        Box::pin(async move {
+            let value: serde_json::Value = self.receive(label).await?;
+            Json::encode(&value)
+        })
+    }
+
+    fn send_bytes<'a>(
+        &'a mut self,
+        label: &'static [u8],
+        bytes: &'a [u8],
+    ) -> BoxFuture<'a, Result<(), io::Error>> {
+        Box::pin(async move {
+            let value: serde_json::Value = Json::decode(bytes)?;
+            self.send(label, value).await
+        })
+    }
+
+    fn flush_bytes(&mut self) -> BoxFuture<'_, Result<(), io::Error>> {
+        Box::pin(self.flush())
+    }
+}
+
+impl<C: Codec> Transport for Box<dyn DynTransport<C> + '_> {
+    async fn receive<V: for<'a> Deserialize<'a>>(
+        &mut self,
+        label: &'static [u8],
+    ) -> Result<V, io::Error> {
+        C::decode(&self.recv_bytes(label).await?)
+    }
+
+    fn send<V: Serialize>(
+        &mut self,
+        label: &'static [u8],
+        value: V,
+    ) -> impl Future<Output = Result<(), io::Error>> + Send {
+        let bytes = C::encode(&value);
+        async move { self.send_bytes(label, &bytes?).await }
+    }
+
+    fn flush(&mut self) -> impl Future<Output = Result<(), io::Error>> + Send {
+        self.flush_bytes()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::assert_matches::assert_matches;
+
+    use futures::{executor::block_on, future::try_join};
+    use rand::thread_rng;
+
+    use crate::{
+        transport::{
+            codec::{Bincode, Json},
+            memory::DuplexTransport,
+            LabelMismatch, LocalTransport as _,
+        },
+        Error, Org, OrgSecretKey, User, UserSecretKey,
+    };
+
+    use super::DynTransport;
+
+    #[test]
+    fn boxed_transport() {
+        let user = User::new(UserSecretKey::random(&mut thread_rng()));
+        let org = Org::new(OrgSecretKey::random(&mut thread_rng()));
+
+        let (u_channel, mut o_channel) = DuplexTransport::<Json>::pair();
+        let mut u_channel: Box<dyn DynTransport> = Box::new(u_channel);
+        let res = block_on(try_join(
+            user.generate_nym(&mut u_channel),
+            org.generate_nym(&mut o_channel),
+        ));
+        assert_matches!(res, Ok((n1, n2)) if n1 == n2);
+
+        block_on(o_channel.send(b"a~", 42u32)).unwrap();
+        let res = block_on(u_channel.receive::<u32>(b"a")).map_err(Error::from);
+        assert_matches!(
+            res,
+            Err(Error::LabelMismatch(LabelMismatch { expected, got }))
+                if expected == b"a" && got == b"a~"
+        );
+    }
+
+    #[test]
+    fn chosen_at_runtime() {
+        let transports: Vec<(Box<dyn DynTransport<Bincode>>, _)> = (0..2)
+            .map(|_| {
+                let (t1, t2) = DuplexTransport::<Bincode>::pair();
+                (Box::new(t1) as Box<dyn DynTransport<Bincode>>, t2)
+            })
+            .collect();
+        for (mut t1, mut t2) in transports {
+            block_on(t1.send(b"x", String::from("hello"))).unwrap();
+            assert_matches!(block_on(t2.receive::<String>(b"x")), Ok(s) if s == "hello");
+        }
+    }
+}
@@ -0,0 +1,344 @@
+//! Transport over any byte stream
+
+use std::{future::Future, marker::PhantomData};
+
+use futures::io::{self, AsyncRead, AsyncReadExt as _, AsyncWrite, AsyncWriteExt as _};
+use serde::{Deserialize, Serialize};
+
+use super::{
+    codec::{Bincode, Codec},
+    trace_message, Direction, Transport,
+};
+
+/// Largest frame accepted from the peer
+pub(crate) const MAX_FRAME_LENGTH: usize = 1 << 20;
+
+/// Most bytes read from a stream at once
+const READ_CHUNK_LENGTH: usize = 4096;
+
+/// A transport over a byte stream, with length-prefixed frames
+///
+/// Each frame is a 4-byte big-endian length, followed by a 4-byte big-endian label length, the
+/// label bytes, and the value serialized with the codec `C`. This is the framing `TcpTransport`
+/// uses, over anything that implements [`AsyncRead`] and [`AsyncWrite`], such as TLS streams or
+/// pipes.
+///
+/// Receiving is cancellation-safe: bytes are buffered as they arrive, and only taken off the
+/// buffer once a whole frame is in, so dropping a pending [`receive`](Transport::receive) leaves
+/// the next one to pick up where it stopped. Sending is not, as a dropped send may have written
+/// part of a frame.
+pub struct FramedTransport<S, C = Bincode> {
+    stream: S,
+    buffer: Vec<u8>,
+    codec: PhantomData<fn() -> C>,
+}
+
+impl<S, C> FramedTransport<S, C> {
+    /// Creates a new transport over a connected stream
+    pub fn new(stream: S) -> Self {
+        Self {
+            stream,
+            buffer: Vec::new(),
+            codec: PhantomData,
+        }
+    }
+
+    /// Gets the underlying stream back
+    ///
+    /// Bytes already read from the stream but not received as a whole frame are lost.
+    pub fn into_inner(self) -> S {
+        self.stream
+    }
+}
+
+impl<S: AsyncRead + Unpin, C> FramedTransport<S, C> {
+    async fn read_frame(&mut self) -> Result<Vec<u8>, io::Error> {
+        loop {
+            if let Some(frame) = take_frame(&mut self.buffer)? {
+                return Ok(frame);
+            }
+            let mut chunk = [0; READ_CHUNK_LENGTH];
+            let n = self.stream.read(&mut chunk).await?;
+            if n == 0 {
+                return Err(io::ErrorKind::UnexpectedEof.into());
+            }
+            self.buffer.extend_from_slice(&chunk[..n]);
+        }
+    }
+}
+
+impl<S: AsyncRead + AsyncWrite + Unpin + Send, C: Codec> Transport for FramedTransport<S, C> {
+    async fn receive<V: for<'a> Deserialize<'a>>(
+        &mut self,
+        label: &'static [u8],
+    ) -> Result<V, io::Error> {
+        let (_, value) = self.receive_labeled(label).await?;
+        Ok(value)
+    }
+
+    fn send<V: Serialize>(
+        &mut self,
+        label: &'static [u8],
+        value: V,
+    ) -> impl Future<Output = Result<(), io::Error>> + Send {
+        let frame = C::encode(&value).and_then(|bytes| {
+            trace_message(Direction::Sent, label, &bytes);
+            encode_frame(label, &bytes)
+        });
+        async move {
+            self.stream.write_all(&frame?).await?;
+            self.stream.flush().await
+        }
+    }
+
+    async fn receive_labeled<V: for<'a> Deserialize<'a>>(
+        &mut self,
+        _label: &'static [u8],
+    ) -> Result<(Option<Vec<u8>>, V), io::Error> {
+        let frame = self.read_frame().await?;
+        let (label, bytes) = decode_frame(&frame)?;
+        trace_message(Direction::Received, &label, &bytes);
+        Ok((Some(label), C::decode(&bytes)?))
+    }
+}
+
+/// Builds a frame for a labeled value, including its length prefix
+pub(crate) fn encode_frame(label: &[u8], value: &[u8]) -> Result<Vec<u8>, io::Error> {
+    let len = check_frame_length(4 + label.len() + value.len())?;
+    let mut frame = Vec::with_capacity(4 + len);
+    frame.extend_from_slice(&(len as u32).to_be_bytes());
+    frame.extend_from_slice(&(label.len() as u32).to_be_bytes());
+    frame.extend_from_slice(label);
+    frame.extend_from_slice(value);
+    Ok(frame)
+}
+
+/// Checks the length of a frame against [`MAX_FRAME_LENGTH`]
+pub(crate) fn check_frame_length(len: usize) -> Result<usize, io::Error> {
+    if len > MAX_FRAME_LENGTH {
+        return Err(framing_error("frame too long"));
+    }
+    Ok(len)
+}
+
+/// Takes a whole frame, without its length prefix, off the front of a buffer
+///
+/// This returns `None` until the buffer holds the whole frame, so that bytes can be buffered as
+/// they arrive, and a cancelled read never leaves part of a frame consumed.
+fn take_frame(buffer: &mut Vec<u8>) -> Result<Option<Vec<u8>>, io::Error> {
+    let Some(len) = buffer.get(..4) else {
+        return Ok(None);
+    };
+    let len = check_frame_length(u32::from_be_bytes(len.try_into().unwrap()) as usize)?;
+    if buffer.len() < 4 + len {
+        return Ok(None);
+    }
+    let frame = buffer[4..4 + len].to_vec();
+    buffer.drain(..4 + len);
+    Ok(Some(frame))
+}
+
+/// Splits a frame, without its length prefix, into its label and value
+pub(crate) fn decode_frame(frame: &[u8]) -> Result<(Vec<u8>, Vec<u8>), io::Error> {
+    if frame.len() < 4 {
+        return Err(framing_error("frame too short for label length"));
+    }
+    let (label_len, rest) = frame.split_at(4);
+    let label_len = u32::from_be_bytes(label_len.try_into().unwrap()) as usize;
+    if rest.len() < label_len {
+        return Err(framing_error("frame too short for label"));
+    }
+    let (label, value) = rest.split_at(label_len);
+    Ok((label.to_vec(), value.to_vec()))
+}
+
+fn framing_error(msg: &'static str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+#[cfg(test)]
+mod test {
+    use std::assert_matches::assert_matches;
+    use std::{
+        collections::BTreeMap,
+        future::Future as _,
+        pin::{pin, Pin},
+        task::{Context, Poll},
+    };
+
+    use futures::{
+        channel::mpsc::{self, UnboundedReceiver, UnboundedSender},
+        executor::block_on,
+        future::try_join,
+        io::{self, AsyncRead, AsyncWrite, AsyncWriteExt as _},
+        stream::{IntoAsyncRead, TryStreamExt as _},
+        task::noop_waker_ref,
+    };
+    use rand::thread_rng;
+
+    use crate::{
+        transport::{
+            codec::{Bincode, Codec as _, Json},
+            DynTransport, LocalTransport as _,
+        },
+        Error, Org, OrgSecretKey, User, UserSecretKey,
+    };
+
+    use super::{encode_frame, FramedTransport, MAX_FRAME_LENGTH};
+
+    /// One end of an in-memory byte pipe
+    struct Pipe {
+        reader: IntoAsyncRead<UnboundedReceiver<io::Result<Vec<u8>>>>,
+        writer: UnboundedSender<io::Result<Vec<u8>>>,
+    }
+
+    fn pipe() -> (Pipe, Pipe) {
+        let (s1, r2) = mpsc::unbounded();
+        let (s2, r1) = mpsc::unbounded();
+        (
+            Pipe {
+                reader: r1.into_async_read(),
+                writer: s1,
+            },
+            Pipe {
+                reader: r2.into_async_read(),
+                writer: s2,
+            },
+        )
+    }
+
+    impl AsyncRead for Pipe {
+        fn poll_read(
+            mut self: Pin<&mut Self>,
+            cx: &mut Context<'_>,
+            buf: &mut [u8],
+        ) -> Poll<io::Result<usize>> {
+            Pin::new(&mut self.reader).poll_read(cx, buf)
+        }
+    }
+
+    impl AsyncWrite for Pipe {
+        fn poll_write(
+            self: Pin<&mut Self>,
+            _cx: &mut Context<'_>,
+            buf: &[u8],
+        ) -> Poll<io::Result<usize>> {
+            let res = self.writer.unbounded_send(Ok(buf.to_vec()));
+            Poll::Ready(
+                res.map(|_| buf.len())
+                    .map_err(|_| io::ErrorKind::BrokenPipe.into()),
+            )
+        }
+
+        fn poll_flush(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn poll_close(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+            self.writer.close_channel();
+            Poll::Ready(Ok(()))
+        }
+    }
+
+    #[test]
+    fn nym_generation_over_pipe() {
+        let user = User::new(UserSecretKey::random(&mut thread_rng()));
+        let org = Org::new(OrgSecretKey::random(&mut thread_rng()));
+
+        let (u_pipe, o_pipe) = pipe();
+        let mut u_channel = FramedTransport::<_>::new(u_pipe);
+        let mut o_channel = FramedTransport::<_>::new(o_pipe);
+        let (n1, n2) = block_on(try_join(
+            user.generate_nym(&mut u_channel),
+            org.generate_nym(&mut o_channel),
+        ))
+        .unwrap();
+        assert_eq!(n1, n2, "user and org should compute the same nym");
+
+        let (u_pipe, o_pipe) = pipe();
+        let mut u_channel = FramedTransport::<_, Json>::new(u_pipe);
+        let mut o_channel = FramedTransport::<_, Json>::new(o_pipe);
+        let (n1, n2) = block_on(try_join(
+            user.generate_nym(&mut u_channel),
+            org.generate_nym(&mut o_channel),
+        ))
+        .unwrap();
+        assert_eq!(n1, n2, "user and org should compute the same nym");
+    }
+
+    #[test]
+    fn boxed_framed_transport() {
+        let user = User::new(UserSecretKey::random(&mut thread_rng()));
+        let org = Org::new(OrgSecretKey::random(&mut thread_rng()));
+
+        let (u_pipe, o_pipe) = pipe();
+        let mut u_channel: Box<dyn DynTransport> =
+            Box::new(FramedTransport::<_, Json>::new(u_pipe));
+        let mut o_channel = FramedTransport::<_, Json>::new(o_pipe);
+        let (n1, n2) = block_on(try_join(
+            user.generate_nym(&mut u_channel),
+            org.generate_nym(&mut o_channel),
+        ))
+        .unwrap();
+        assert_eq!(n1, n2, "user and org should compute the same nym");
+    }
+
+    #[test]
+    fn bad_frames() {
+        let (mut sender, receiver) = pipe();
+        let mut receiver = FramedTransport::<_>::new(receiver);
+        let len = (MAX_FRAME_LENGTH as u32 + 1).to_be_bytes();
+        block_on(sender.write_all(&len)).unwrap();
+        let res = block_on(receiver.receive::<u32>(b"a")).map_err(Error::from);
+        assert_matches!(res, Err(e) if e.is_malformed());
+
+        let (sender, receiver) = pipe();
+        let mut receiver = FramedTransport::<_>::new(receiver);
+        drop(sender);
+        let res = block_on(receiver.receive::<u32>(b"a")).map_err(Error::from);
+        assert_matches!(res, Err(e) if e.is_disconnect());
+    }
+
+    #[test]
+    fn cancelled_receive() {
+        let (mut sender, receiver) = pipe();
+        let mut receiver = FramedTransport::<_>::new(receiver);
+        let first = encode_frame(b"a", &Bincode::encode(&42u32).unwrap()).unwrap();
+        let second = encode_frame(b"b", &Bincode::encode(&43u32).unwrap()).unwrap();
+
+        block_on(sender.write_all(&first[..6])).unwrap();
+        {
+            let mut receive = pin!(receiver.receive::<u32>(b"a"));
+            let mut cx = Context::from_waker(noop_waker_ref());
+            assert!(receive.as_mut().poll(&mut cx).is_pending());
+        }
+        block_on(sender.write_all(&first[6..])).unwrap();
+        block_on(sender.write_all(&second)).unwrap();
+        assert_eq!(block_on(receiver.receive::<u32>(b"a")).unwrap(), 42);
+        assert_eq!(block_on(receiver.receive::<u32>(b"b")).unwrap(), 43);
+    }
+
+    #[test]
+    fn malformed_values() {
+        let (mut sender, receiver) = pipe();
+        let mut receiver = FramedTransport::<_, Json>::new(receiver);
+        block_on(sender.write_all(&encode_frame(b"a", b"{not json").unwrap())).unwrap();
+        let res = block_on(receiver.receive::<u32>(b"a")).map_err(Error::from);
+        assert_matches!(res, Err(e @ Error::Serialization(_)) if e.is_malformed());
+
+        let (mut sender, receiver) = pipe();
+        let mut receiver = FramedTransport::<_>::new(receiver);
+        block_on(sender.write_all(&encode_frame(b"a", &[1, 2]).unwrap())).unwrap();
+        let res = block_on(receiver.receive::<u32>(b"a")).map_err(Error::from);
+        assert_matches!(res, Err(Error::Serialization(_)));
+    }
+
+    #[test]
+    fn unserializable_values() {
+        let (sender, _receiver) = pipe();
+        let mut sender = FramedTransport::<_, Json>::new(sender);
+        let value = BTreeMap::from([((1, 2), 3)]);
+        let res = block_on(sender.send(b"a", value)).map_err(Error::from);
+        assert_matches!(res, Err(e @ Error::Unserializable(_)) if !e.is_malformed());
+    }
+}
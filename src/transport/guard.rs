@@ -0,0 +1,122 @@
+//! Transport adapter that warns about protocols dropped before finishing
+
+use futures::io;
+use serde::{Deserialize, Serialize};
+
+use super::LocalTransport;
+
+/// A transport adapter that warns if a protocol is dropped before it finishes
+///
+/// A protocol wraps its transport in a guard, and [`finish`](Guarded::finish)es it when it
+/// returns. If its future is dropped before that, e.g. when a timeout fires, the peer may be left
+/// waiting for a message that never comes. With the `tracing` feature, in debug builds, dropping an
+/// unfinished guard emits a warning with the name of the protocol and the label of the last message
+/// it sent, to help find where the peer hangs. Otherwise, this only forwards to the wrapped
+/// transport.
+pub struct Guarded<'a, T> {
+    inner: &'a mut T,
+    tracker: Tracker,
+}
+
+impl<'a, T> Guarded<'a, T> {
+    /// Wraps a transport for a run of a protocol with a given name
+    pub fn new(inner: &'a mut T, protocol: &'static str) -> Self {
+        Self {
+            inner,
+            tracker: Tracker::new(protocol),
+        }
+    }
+
+    /// Marks the protocol as finished, whether it succeeded or not
+    pub fn finish(mut self) {
+        self.tracker.finish();
+    }
+}
+
+impl<T: LocalTransport> LocalTransport for Guarded<'_, T> {
+    async fn receive<V: for<'a> Deserialize<'a>>(
+        &mut self,
+        label: &'static [u8],
+    ) -> Result<V, io::Error> {
+        self.inner.receive(label).await
+    }
+
+    async fn send<V: Serialize>(
+        &mut self,
+        label: &'static [u8],
+        value: V,
+    ) -> Result<(), io::Error> {
+        self.inner.send(label, value).await?;
+        self.tracker.sent(label);
+        Ok(())
+    }
+
+    async fn receive_labeled<V: for<'a> Deserialize<'a>>(
+        &mut self,
+        label: &'static [u8],
+    ) -> Result<(Option<Vec<u8>>, V), io::Error> {
+        self.inner.receive_labeled(label).await
+    }
+
+    async fn flush(&mut self) -> Result<(), io::Error> {
+        self.inner.flush().await
+    }
+}
+
+/// Keeps track of the last label sent by a protocol, to warn if it is dropped unfinished
+#[cfg(all(feature = "tracing", debug_assertions))]
+struct Tracker {
+    protocol: &'static str,
+    last_sent: Option<&'static [u8]>,
+    finished: bool,
+}
+
+#[cfg(all(feature = "tracing", debug_assertions))]
+impl Tracker {
+    fn new(protocol: &'static str) -> Self {
+        Self {
+            protocol,
+            last_sent: None,
+            finished: false,
+        }
+    }
+
+    fn sent(&mut self, label: &'static [u8]) {
+        self.last_sent = Some(label);
+    }
+
+    fn finish(&mut self) {
+        self.finished = true;
+    }
+}
+
+#[cfg(all(feature = "tracing", debug_assertions))]
+impl Drop for Tracker {
+    fn drop(&mut self) {
+        if !self.finished {
+            tracing::warn!(
+                protocol = self.protocol,
+                last_sent = %String::from_utf8_lossy(self.last_sent.unwrap_or_default()),
+                "protocol dropped before finishing"
+            );
+        }
+    }
+}
+
+/// Keeps track of nothing, without the `tracing` feature or in release builds
+#[cfg(not(all(feature = "tracing", debug_assertions)))]
+struct Tracker;
+
+#[cfg(not(all(feature = "tracing", debug_assertions)))]
+impl Tracker {
+    #[inline(always)]
+    fn new(_: &'static str) -> Self {
+        Self
+    }
+
+    #[inline(always)]
+    fn sent(&mut self, _: &'static [u8]) {}
+
+    #[inline(always)]
+    fn finish(&mut self) {}
+}
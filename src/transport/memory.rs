@@ -0,0 +1,191 @@
+//! In-memory transport
+
+use std::{future::Future, marker::PhantomData};
+
+use futures::{
+    channel::mpsc::{self, UnboundedReceiver, UnboundedSender},
+    future::BoxFuture,
+    io,
+    stream::StreamExt as _,
+};
+use serde::{Deserialize, Serialize};
+
+use super::{
+    codec::{Bincode, Canonical, Codec, Json},
+    trace_message, Direction, DynTransport, LabelMismatch, Transport,
+};
+
+/// One end of a pair of connected in-memory transports
+///
+/// Values are serialized with the codec `C`, and received labels are checked against the expected
+/// ones.
+pub struct DuplexTransport<C = Json> {
+    sender: UnboundedSender<(Vec<u8>, Vec<u8>)>,
+    receiver: UnboundedReceiver<(Vec<u8>, Vec<u8>)>,
+    codec: PhantomData<fn() -> C>,
+}
+
+impl<C: Codec> DuplexTransport<C> {
+    /// Creates a pair of transports connected to each other
+    pub fn pair() -> (Self, Self) {
+        let (s1, r2) = mpsc::unbounded();
+        let (s2, r1) = mpsc::unbounded();
+        (
+            Self {
+                sender: s1,
+                receiver: r1,
+                codec: PhantomData,
+            },
+            Self {
+                sender: s2,
+                receiver: r2,
+                codec: PhantomData,
+            },
+        )
+    }
+}
+
+impl<C> DuplexTransport<C> {
+    /// Receives the next message, as its label and encoded value
+    async fn receive_message(&mut self) -> Result<(Vec<u8>, Vec<u8>), io::Error> {
+        let (label, bytes) = self
+            .receiver
+            .next()
+            .await
+            .ok_or(io::ErrorKind::UnexpectedEof)?;
+        trace_message(Direction::Received, &label, &bytes);
+        Ok((label, bytes))
+    }
+
+    /// Receives the next message, checking it has the expected label
+    async fn receive_expected(&mut self, label: &'static [u8]) -> Result<Vec<u8>, io::Error> {
+        match self.receive_message().await? {
+            (got, _) if got != label => Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                LabelMismatch {
+                    expected: label.to_vec(),
+                    got,
+                },
+            )),
+            (_, bytes) => Ok(bytes),
+        }
+    }
+
+    /// Sends a message with an encoded value
+    fn send_message(&mut self, label: &[u8], bytes: Vec<u8>) -> Result<(), io::Error> {
+        trace_message(Direction::Sent, label, &bytes);
+        self.sender
+            .unbounded_send((label.to_vec(), bytes))
+            .map_err(|_| io::Error::from(io::ErrorKind::BrokenPipe))
+    }
+}
+
+impl<C: Codec> Transport for DuplexTransport<C> {
+    async fn receive<V: for<'a> Deserialize<'a>>(
+        &mut self,
+        label: &'static [u8],
+    ) -> Result<V, io::Error> {
+        C::decode(&self.receive_expected(label).await?)
+    }
+
+    fn send<V: Serialize>(
+        &mut self,
+        label: &'static [u8],
+        value: V,
+    ) -> impl Future<Output = Result<(), io::Error>> + Send {
+        let res = C::encode(&value).and_then(|bytes| self.send_message(label, bytes));
+        async move { res }
+    }
+
+    async fn receive_labeled<V: for<'a> Deserialize<'a>>(
+        &mut self,
+        _label: &'static [u8],
+    ) -> Result<(Option<Vec<u8>>, V), io::Error> {
+        let (label, bytes) = self.receive_message().await?;
+        Ok((Some(label), C::decode(&bytes)?))
+    }
+}
+
+/// Moves encoded values as they are, for the codecs that the blanket [`DynTransport`] impl, which
+/// only covers [`Json`], leaves out
+macro_rules! impl_dyn_transport {
+    ($($codec:ty),+) => {
+        $(
+            impl DynTransport<$codec> for DuplexTransport<$codec> {
+                fn recv_bytes(
+                    &mut self,
+                    label: &'static [u8],
+                ) -> BoxFuture<'_, Result<Vec<u8>, io::Error>> {
+                    Box::pin(self.receive_expected(label))
+                }
+
+                fn send_bytes<'a>(
+                    &'a mut self,
+                    label: &'static [u8],
+                    bytes: &'a [u8],
+                ) -> BoxFuture<'a, Result<(), io::Error>> {
+                    let res = self.send_message(label, bytes.to_vec());
+                    Box::pin(async move { res })
+                }
+            }
+        )+
+    };
+}
+
+impl_dyn_transport!(Bincode, Canonical);
+
+#[cfg(test)]
+mod test {
+    use std::assert_matches::assert_matches;
+
+    use futures::executor::block_on;
+
+    use crate::{
+        transport::{
+            codec::{Bincode, Json},
+            LabelMismatch, LocalTransport as _,
+        },
+        Error,
+    };
+
+    use super::DuplexTransport;
+
+    #[test]
+    fn send_and_receive() {
+        let (mut t1, mut t2) = DuplexTransport::<Bincode>::pair();
+        block_on(t1.send(b"x", 42u32)).unwrap();
+        block_on(t2.send(b"y", String::from("hello"))).unwrap();
+        assert_matches!(block_on(t2.receive::<u32>(b"x")), Ok(42));
+        assert_matches!(block_on(t1.receive::<String>(b"y")), Ok(s) if s == "hello");
+    }
+
+    #[test]
+    fn label_mismatch() {
+        let (mut t1, mut t2) = DuplexTransport::<Json>::pair();
+        block_on(t1.send(b"a~", 42u32)).unwrap();
+        let res = block_on(t2.receive::<u32>(b"a")).map_err(Error::from);
+        assert_matches!(
+            res,
+            Err(Error::LabelMismatch(LabelMismatch { expected, got }))
+                if expected == b"a" && got == b"a~"
+        );
+    }
+
+    #[test]
+    fn disconnected() {
+        let (t1, mut t2) = DuplexTransport::<Json>::pair();
+        drop(t1);
+        let res = block_on(t2.receive::<u32>(b"a"));
+        assert_matches!(res, Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof);
+        let res = block_on(t2.send(b"a", 42u32)).map_err(Error::from);
+        assert_matches!(res, Err(e) if e.is_disconnect() && !e.is_malformed());
+    }
+
+    #[test]
+    fn malformed() {
+        let (mut t1, mut t2) = DuplexTransport::<Json>::pair();
+        block_on(t1.send(b"a", 42u32)).unwrap();
+        let res = block_on(t2.receive::<String>(b"a")).map_err(Error::from);
+        assert_matches!(res, Err(e) if e.is_malformed() && !e.is_disconnect());
+    }
+}
@@ -0,0 +1,427 @@
+//! Transport abstraction
+
+use std::future::Future;
+
+use futures::io;
+use serde::{Deserialize, Serialize};
+
+use crate::error::Result;
+
+/// A transport for protocols
+///
+/// Transports report a closed channel as [`io::ErrorKind::UnexpectedEof`] and data that cannot be
+/// deserialized as [`io::ErrorKind::InvalidData`], so that callers can tell them apart with
+/// [`Error::is_disconnect`](crate::Error::is_disconnect) and
+/// [`Error::is_malformed`](crate::Error::is_malformed). Values that fail to deserialize carry a
+/// [`CodecError`](codec::CodecError), which converts into
+/// [`Error::Serialization`](crate::Error::Serialization).
+#[trait_variant::make(Transport: Send)]
+pub trait LocalTransport {
+    /// Receives a value with a given label
+    async fn receive<V: for<'a> Deserialize<'a>>(
+        &mut self,
+        label: &'static [u8],
+    ) -> Result<V, io::Error>;
+
+    /// Sends a value with a given label
+    async fn send<V: Serialize>(&mut self, label: &'static [u8], value: V)
+        -> Result<(), io::Error>;
+
+    /// Receives a value with a given label, along with the label it was sent with
+    ///
+    /// Transports that don't carry labels on the wire keep the default implementation, which
+    /// reports no label.
+    fn receive_labeled<V: for<'a> Deserialize<'a>>(
+        &mut self,
+        label: &'static [u8],
+    ) -> impl Future<Output = Result<(Option<Vec<u8>>, V), io::Error>> {
+        async move { Ok((None, self.receive(label).await?)) }
+    }
+
+    /// Ensures that every value sent so far has been delivered to the peer
+    ///
+    /// Transports may buffer sent values, but they must deliver them before waiting in
+    /// [`receive`](LocalTransport::receive), so that the peer isn't left waiting for a message
+    /// stuck in a buffer. When a party sends and doesn't receive anything after, as at the end of a
+    /// proof, nothing would trigger that delivery, so protocols call this instead. Transports that
+    /// don't buffer, or that already flush on every send, keep the default implementation, which
+    /// does nothing.
+    fn flush(&mut self) -> impl Future<Output = Result<(), io::Error>> {
+        async { Ok(()) }
+    }
+}
+
+/// A transport adapter that checks received labels against the expected ones
+///
+/// A mismatch is reported as [`LabelMismatch`], which converts into
+/// [`Error::LabelMismatch`](crate::Error::LabelMismatch). Values from transports that don't carry
+/// labels on the wire are passed through unchecked.
+pub struct CheckedTransport<T> {
+    inner: T,
+}
+
+/// Wraps a transport so that received labels are checked
+pub fn checked<T: LocalTransport>(inner: T) -> CheckedTransport<T> {
+    CheckedTransport::new(inner)
+}
+
+impl<T> CheckedTransport<T> {
+    /// Wraps a transport so that received labels are checked
+    pub fn new(inner: T) -> Self {
+        Self { inner }
+    }
+
+    /// Gets the wrapped transport back
+    pub fn into_inner(self) -> T {
+        self.inner
+    }
+}
+
+impl<T: LocalTransport> LocalTransport for CheckedTransport<T> {
+    async fn receive<V: for<'a> Deserialize<'a>>(
+        &mut self,
+        label: &'static [u8],
+    ) -> Result<V, io::Error> {
+        match self.inner.receive_labeled(label).await? {
+            (Some(got), _) if got != label => Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                LabelMismatch {
+                    expected: label.to_vec(),
+                    got,
+                },
+            )),
+            (_, value) => Ok(value),
+        }
+    }
+
+    async fn send<V: Serialize>(
+        &mut self,
+        label: &'static [u8],
+        value: V,
+    ) -> Result<(), io::Error> {
+        self.inner.send(label, value).await
+    }
+
+    async fn receive_labeled<V: for<'a> Deserialize<'a>>(
+        &mut self,
+        label: &'static [u8],
+    ) -> Result<(Option<Vec<u8>>, V), io::Error> {
+        self.inner.receive_labeled(label).await
+    }
+
+    async fn flush(&mut self) -> Result<(), io::Error> {
+        self.inner.flush().await
+    }
+}
+
+/// Waits until the peer reaches the same point of a protocol
+///
+/// Both sides send an empty message with a given label, and wait for the peer's. This doesn't
+/// change what the protocols exchange, but when the two sides run as separate tasks and one gets
+/// ahead, e.g. into the wrong protocol, it fails at the sync point, with
+/// [`Error::LabelMismatch`](crate::Error::LabelMismatch) on transports that check labels, rather
+/// than deadlocking or desyncing later. Each phase of a long session should use a different label.
+pub async fn sync_point<T: LocalTransport>(transport: &mut T, label: &'static [u8]) -> Result {
+    transport.send(label, ()).await?;
+    transport.flush().await?;
+    transport.receive::<()>(label).await?;
+    Ok(())
+}
+
+/// Emits a debug event for a message going through a transport
+///
+/// The event carries the label and a short hash of the encoded message, which is enough to match
+/// messages on both ends without logging their contents.
+#[cfg(feature = "tracing")]
+pub(crate) fn trace_message(direction: Direction, label: &[u8], bytes: &[u8]) {
+    let hash = crate::hash::hash_to_scalar(b"nym/trace", bytes);
+    let hash: String = hash.as_bytes()[..8]
+        .iter()
+        .map(|b| format!("{b:02x}"))
+        .collect();
+    tracing::debug!(
+        ?direction,
+        label = %String::from_utf8_lossy(label),
+        %hash,
+        "message"
+    );
+}
+
+/// Emits a debug event for a message going through a transport, with the `tracing` feature
+#[cfg(not(feature = "tracing"))]
+#[inline(always)]
+pub(crate) fn trace_message(_: Direction, _: &[u8], _: &[u8]) {}
+
+/// A value was received with a different label than expected
+#[derive(thiserror::Error, Debug, Clone, PartialEq, Eq)]
+#[error(
+    "expected label `{}`, got `{}`",
+    String::from_utf8_lossy(expected),
+    String::from_utf8_lossy(got)
+)]
+pub struct LabelMismatch {
+    /// The label that was expected
+    pub expected: Vec<u8>,
+    /// The label that was received
+    pub got: Vec<u8>,
+}
+
+pub mod codec;
+mod dynamic;
+pub use dynamic::DynTransport;
+pub mod framed;
+mod guard;
+pub use guard::Guarded;
+pub mod memory;
+mod recording;
+pub use recording::{Direction, RecordedMessage, Recording};
+mod reorder;
+pub use reorder::Reorder;
+mod replay;
+pub use replay::Replay;
+
+#[cfg(feature = "tokio")]
+mod tcp;
+#[cfg(feature = "tokio")]
+pub use tcp::TcpTransport;
+#[cfg(feature = "wasm")]
+pub mod wasm;
+
+#[cfg(test)]
+mod test {
+    use std::assert_matches::assert_matches;
+    use std::{
+        future::Future as _,
+        pin::pin,
+        task::{Context, Poll},
+    };
+
+    use futures::{future::try_join, io, task::noop_waker_ref};
+    use rand::thread_rng;
+    use serde::{Deserialize, Serialize};
+
+    use crate::{Error, Org, OrgSecretKey, User, UserSecretKey};
+
+    use super::{codec::Json, memory::DuplexTransport, sync_point, LocalTransport};
+
+    /// A transport that holds sent values until it flushes or waits for the peer
+    struct Buffered {
+        inner: DuplexTransport<Json>,
+        pending: Vec<(&'static [u8], serde_json::Value)>,
+    }
+
+    impl LocalTransport for Buffered {
+        async fn receive<V: for<'a> Deserialize<'a>>(
+            &mut self,
+            label: &'static [u8],
+        ) -> Result<V, io::Error> {
+            self.flush().await?;
+            self.inner.receive(label).await
+        }
+
+        async fn send<V: Serialize>(
+            &mut self,
+            label: &'static [u8],
+            value: V,
+        ) -> Result<(), io::Error> {
+            let value = serde_json::to_value(value)?;
+            self.pending.push((label, value));
+            Ok(())
+        }
+
+        async fn flush(&mut self) -> Result<(), io::Error> {
+            for (label, value) in self.pending.drain(..) {
+                self.inner.send(label, value).await?;
+            }
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn flush_buffered() {
+        let user = User::new(UserSecretKey::random(&mut thread_rng()));
+        let org = Org::new(OrgSecretKey::random(&mut thread_rng()));
+
+        let (u_channel, mut o_channel) = DuplexTransport::<Json>::pair();
+        let mut u_channel = Buffered {
+            inner: u_channel,
+            pending: Vec::new(),
+        };
+        let mut session = pin!(try_join(
+            user.generate_nym(&mut u_channel),
+            org.generate_nym(&mut o_channel),
+        ));
+        // Everything is in memory, so a stuck message is the only reason not to finish
+        let mut cx = Context::from_waker(noop_waker_ref());
+        let res = (0..16).find_map(|_| match session.as_mut().poll(&mut cx) {
+            Poll::Ready(res) => Some(res),
+            Poll::Pending => None,
+        });
+        assert_matches!(res, Some(Ok(_)));
+    }
+
+    #[test]
+    fn sync_between_phases() {
+        use std::thread;
+
+        use futures::executor::block_on;
+
+        let user = User::new(UserSecretKey::random(&mut thread_rng()));
+        let org = Org::new(OrgSecretKey::random(&mut thread_rng()));
+
+        let (mut u_channel, mut o_channel) = DuplexTransport::<Json>::pair();
+        let (user_res, org_res) = thread::scope(|s| {
+            let user_side = s.spawn(|| {
+                block_on(async {
+                    let nym = user.generate_nym(&mut u_channel).await?;
+                    sync_point(&mut u_channel, b"issuance").await?;
+                    user.issue_credential(&mut u_channel, nym, org.public_key(), 0)
+                        .await
+                })
+            });
+            let org_side = s.spawn(|| {
+                block_on(async {
+                    let nym = org.generate_nym(&mut o_channel).await?;
+                    sync_point(&mut o_channel, b"issuance").await?;
+                    org.issue_credential(&mut o_channel, nym, 0).await
+                })
+            });
+            (user_side.join().unwrap(), org_side.join().unwrap())
+        });
+        assert_matches!(user_res, Ok(_));
+        assert_matches!(org_res, Ok(_));
+
+        // A side that skipped ahead fails at the sync point instead
+        let (mut u_channel, mut o_channel) = DuplexTransport::<Json>::pair();
+        let res = block_on(try_join(
+            sync_point(&mut u_channel, b"issuance"),
+            sync_point(&mut o_channel, b"transfer"),
+        ));
+        assert_matches!(res, Err(Error::LabelMismatch(_)));
+    }
+
+    /// Collects the fields of tracing events
+    #[cfg(feature = "tracing")]
+    mod events {
+        use std::sync::{Arc, Mutex};
+
+        use tracing::{
+            field::{Field, Visit},
+            span, Event, Metadata, Subscriber,
+        };
+
+        /// A subscriber that keeps the name and value of every field of every event
+        #[derive(Clone, Default)]
+        pub struct Events {
+            fields: Arc<Mutex<Vec<(String, String)>>>,
+        }
+
+        impl Visit for &Events {
+            fn record_str(&mut self, field: &Field, value: &str) {
+                let field = field.name().to_owned();
+                self.fields.lock().unwrap().push((field, value.to_owned()));
+            }
+            fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+                self.record_str(field, &format!("{value:?}"));
+            }
+        }
+
+        impl Subscriber for Events {
+            fn enabled(&self, _: &Metadata<'_>) -> bool {
+                true
+            }
+            fn new_span(&self, _: &span::Attributes<'_>) -> span::Id {
+                span::Id::from_u64(1)
+            }
+            fn record(&self, _: &span::Id, _: &span::Record<'_>) {}
+            fn record_follows_from(&self, _: &span::Id, _: &span::Id) {}
+            fn event(&self, event: &Event<'_>) {
+                event.record(&mut &*self);
+            }
+            fn enter(&self, _: &span::Id) {}
+            fn exit(&self, _: &span::Id) {}
+        }
+
+        impl Events {
+            /// Runs a function with this as the subscriber for the current thread
+            pub fn collect<R>(&self, f: impl FnOnce() -> R) -> R {
+                tracing::subscriber::with_default(self.clone(), f)
+            }
+
+            /// Gets the values of the fields with a given name collected so far
+            pub fn values(&self, name: &str) -> Vec<String> {
+                self.fields
+                    .lock()
+                    .unwrap()
+                    .iter()
+                    .filter(|(field, _)| field == name)
+                    .map(|(_, value)| value.clone())
+                    .collect()
+            }
+        }
+    }
+
+    #[cfg(feature = "tracing")]
+    #[test]
+    fn trace_messages() {
+        use futures::executor::block_on;
+
+        let user = User::new(UserSecretKey::random(&mut thread_rng()));
+        let org = Org::new(OrgSecretKey::random(&mut thread_rng()));
+        let events = events::Events::default();
+        let (mut u_channel, mut o_channel) = DuplexTransport::<Json>::pair();
+        events
+            .collect(|| {
+                block_on(try_join(
+                    user.generate_nym(&mut u_channel),
+                    org.generate_nym(&mut o_channel),
+                ))
+            })
+            .unwrap();
+
+        let labels = events.values("label");
+        // Every message is seen once sent and once received
+        for label in ["version", "a~", "b~", "a", "b", "ab", "c", "y"] {
+            let count = labels.iter().filter(|l| *l == label).count();
+            assert_eq!(count, if label == "version" { 4 } else { 2 }, "{label}");
+        }
+        assert_eq!(labels.len(), 18);
+    }
+
+    #[cfg(feature = "tracing")]
+    #[test]
+    fn unfinished_protocol() {
+        use futures::executor::block_on;
+
+        let user = User::new(UserSecretKey::random(&mut thread_rng()));
+        let org = Org::new(OrgSecretKey::random(&mut thread_rng()));
+        let events = events::Events::default();
+        let (mut u_channel, mut o_channel) = DuplexTransport::<Json>::pair();
+        let res = events.collect(|| {
+            block_on(try_join(
+                user.generate_nym(&mut u_channel),
+                org.generate_nym(&mut o_channel),
+            ))
+        });
+        assert_matches!(res, Ok(_));
+        assert_eq!(
+            events.values("protocol"),
+            Vec::<String>::new(),
+            "finished protocols should not warn"
+        );
+
+        // The org never answers, so the user is left waiting for its version
+        let (mut u_channel, _o_channel) = DuplexTransport::<Json>::pair();
+        events.collect(|| {
+            let mut generate = pin!(user.generate_nym(&mut u_channel));
+            let mut cx = Context::from_waker(noop_waker_ref());
+            assert_matches!(generate.as_mut().poll(&mut cx), Poll::Pending);
+        });
+        if cfg!(debug_assertions) {
+            assert_eq!(events.values("protocol"), ["user::generate_nym"]);
+            assert_eq!(events.values("last_sent"), ["version"]);
+        } else {
+            assert_eq!(events.values("protocol"), Vec::<String>::new());
+        }
+    }
+}
@@ -0,0 +1,164 @@
+//! Transport adapter that records the messages exchanged
+
+use futures::io;
+use serde::{Deserialize, Serialize};
+
+use super::{
+    codec::{Codec as _, Json},
+    LocalTransport,
+};
+
+/// A transport adapter that keeps a log of every message sent and received
+///
+/// Each message is logged with its label and its JSON encoding. Received values are captured as
+/// JSON before being deserialized, so the wrapped transport must use a self-describing codec, such
+/// as [`Json`].
+pub struct Recording<T> {
+    inner: T,
+    log: Vec<RecordedMessage>,
+}
+
+/// A message in the log of a [`Recording`] transport
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RecordedMessage {
+    /// The label the message was sent or received with
+    pub label: Vec<u8>,
+    /// The JSON encoding of the value
+    pub bytes: Vec<u8>,
+    /// Whether the message was sent or received
+    pub direction: Direction,
+}
+
+/// The direction of a recorded message
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Direction {
+    /// The message was sent to the peer
+    Sent,
+    /// The message was received from the peer
+    Received,
+}
+
+impl<T> Recording<T> {
+    /// Wraps a transport so that the messages exchanged are recorded
+    pub fn new(inner: T) -> Self {
+        Self {
+            inner,
+            log: Vec::new(),
+        }
+    }
+
+    /// Gets the messages recorded so far
+    pub fn log(&self) -> &[RecordedMessage] {
+        &self.log
+    }
+
+    /// Gets the messages recorded, in the order they were exchanged
+    pub fn into_log(self) -> Vec<RecordedMessage> {
+        self.log
+    }
+
+    /// Gets the wrapped transport back
+    pub fn into_inner(self) -> T {
+        self.inner
+    }
+
+    fn record(&mut self, label: &[u8], bytes: Vec<u8>, direction: Direction) {
+        self.log.push(RecordedMessage {
+            label: label.to_vec(),
+            bytes,
+            direction,
+        });
+    }
+}
+
+impl<T: LocalTransport> LocalTransport for Recording<T> {
+    async fn receive<V: for<'a> Deserialize<'a>>(
+        &mut self,
+        label: &'static [u8],
+    ) -> Result<V, io::Error> {
+        let value: serde_json::Value = self.inner.receive(label).await?;
+        let bytes = Json::encode(&value)?;
+        let value = Json::decode(&bytes)?;
+        self.record(label, bytes, Direction::Received);
+        Ok(value)
+    }
+
+    async fn send<V: Serialize>(
+        &mut self,
+        label: &'static [u8],
+        value: V,
+    ) -> Result<(), io::Error> {
+        let bytes = Json::encode(&value)?;
+        self.inner.send(label, value).await?;
+        self.record(label, bytes, Direction::Sent);
+        Ok(())
+    }
+
+    async fn receive_labeled<V: for<'a> Deserialize<'a>>(
+        &mut self,
+        label: &'static [u8],
+    ) -> Result<(Option<Vec<u8>>, V), io::Error> {
+        let (got, value): (_, serde_json::Value) = self.inner.receive_labeled(label).await?;
+        let bytes = Json::encode(&value)?;
+        let value = Json::decode(&bytes)?;
+        self.record(got.as_deref().unwrap_or(label), bytes, Direction::Received);
+        Ok((got, value))
+    }
+
+    async fn flush(&mut self) -> Result<(), io::Error> {
+        self.inner.flush().await
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use futures::{executor::block_on, future::try_join};
+    use rand::thread_rng;
+
+    use crate::{
+        labels,
+        transport::{codec::Json, memory::DuplexTransport},
+        Org, OrgSecretKey, User, UserSecretKey,
+    };
+
+    use super::{Direction, Recording};
+
+    #[test]
+    fn record_nym_generation() {
+        let user = User::new(UserSecretKey::random(&mut thread_rng()));
+        let org = Org::new(OrgSecretKey::random(&mut thread_rng()));
+
+        let (u_channel, mut o_channel) = DuplexTransport::<Json>::pair();
+        let mut u_channel = Recording::new(u_channel);
+        let (nym, _) = block_on(try_join(
+            user.generate_nym(&mut u_channel),
+            org.generate_nym(&mut o_channel),
+        ))
+        .unwrap();
+
+        let log = u_channel.into_log();
+        let labels: Vec<_> = log.iter().map(|m| m.label.as_slice()).collect();
+        assert_eq!(
+            labels,
+            [
+                b"version" as &[u8],
+                b"version",
+                b"a~",
+                b"b~",
+                b"a",
+                b"b",
+                b"ab",
+                b"c",
+                b"y"
+            ]
+        );
+        let directions: Vec<_> = log.iter().map(|m| m.direction).collect();
+        use Direction::*;
+        assert_eq!(
+            directions,
+            [Sent, Received, Sent, Sent, Received, Sent, Sent, Received, Sent]
+        );
+        assert_eq!(log[4].label, labels::nym::BASE);
+        assert_eq!(log[4].bytes, serde_json::to_vec(&nym.a).unwrap());
+    }
+}
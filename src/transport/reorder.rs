@@ -0,0 +1,126 @@
+//! Transport adapter that tolerates messages arriving out of order
+
+use std::collections::{HashMap, VecDeque};
+
+use futures::io;
+use serde::{Deserialize, Serialize};
+
+use super::{codec::decode_error, LocalTransport};
+
+/// A transport adapter that buffers messages received ahead of the ones expected
+///
+/// When a message arrives with a different label than expected, it is kept until a matching
+/// [`receive`](LocalTransport::receive), and the next one is read, so that sub-protocols can be
+/// interleaved on one channel. Messages with the same label are returned in the order they
+/// arrived.
+///
+/// Buffered values are held as JSON, so the wrapped transport must use a self-describing codec,
+/// such as [`Json`](super::codec::Json), and report labels, as
+/// [`DuplexTransport`](super::memory::DuplexTransport) does. Messages from transports that don't
+/// report labels are never reordered.
+pub struct Reorder<T> {
+    inner: T,
+    pending: HashMap<Vec<u8>, VecDeque<serde_json::Value>>,
+}
+
+impl<T> Reorder<T> {
+    /// Wraps a transport so that messages can be received out of order
+    pub fn new(inner: T) -> Self {
+        Self {
+            inner,
+            pending: HashMap::new(),
+        }
+    }
+
+    /// Gets the wrapped transport back, dropping any messages still buffered
+    pub fn into_inner(self) -> T {
+        self.inner
+    }
+
+    /// Gets the number of messages received but not yet asked for
+    pub fn pending(&self) -> usize {
+        self.pending.values().map(VecDeque::len).sum()
+    }
+}
+
+impl<T: LocalTransport> LocalTransport for Reorder<T> {
+    async fn receive<V: for<'a> Deserialize<'a>>(
+        &mut self,
+        label: &'static [u8],
+    ) -> Result<V, io::Error> {
+        let (_, value) = self.receive_labeled(label).await?;
+        Ok(value)
+    }
+
+    async fn send<V: Serialize>(
+        &mut self,
+        label: &'static [u8],
+        value: V,
+    ) -> Result<(), io::Error> {
+        self.inner.send(label, value).await
+    }
+
+    async fn receive_labeled<V: for<'a> Deserialize<'a>>(
+        &mut self,
+        label: &'static [u8],
+    ) -> Result<(Option<Vec<u8>>, V), io::Error> {
+        let value = match self.pending.get_mut(label).and_then(VecDeque::pop_front) {
+            Some(value) => value,
+            None => loop {
+                let (got, value): (_, serde_json::Value) =
+                    self.inner.receive_labeled(label).await?;
+                match got {
+                    Some(got) if got != label => {
+                        self.pending.entry(got).or_default().push_back(value)
+                    }
+                    _ => break value,
+                }
+            },
+        };
+        let value = V::deserialize(value).map_err(decode_error)?;
+        Ok((Some(label.to_vec()), value))
+    }
+
+    async fn flush(&mut self) -> Result<(), io::Error> {
+        self.inner.flush().await
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::assert_matches::assert_matches;
+
+    use futures::executor::block_on;
+
+    use crate::transport::{codec::Json, memory::DuplexTransport, LocalTransport as _};
+
+    use super::Reorder;
+
+    #[test]
+    fn out_of_order() {
+        let (mut t1, t2) = DuplexTransport::<Json>::pair();
+        let mut t2 = Reorder::new(t2);
+        block_on(t1.send(b"b", 2u32)).unwrap();
+        block_on(t1.send(b"c", String::from("three"))).unwrap();
+        block_on(t1.send(b"a", 1u32)).unwrap();
+        block_on(t1.send(b"b", 4u32)).unwrap();
+
+        assert_matches!(block_on(t2.receive::<u32>(b"a")), Ok(1));
+        assert_eq!(t2.pending(), 2);
+        assert_matches!(block_on(t2.receive::<u32>(b"b")), Ok(2));
+        assert_matches!(block_on(t2.receive::<u32>(b"b")), Ok(4));
+        assert_matches!(block_on(t2.receive::<String>(b"c")), Ok(s) if s == "three");
+        assert_eq!(t2.pending(), 0);
+    }
+
+    #[test]
+    fn disconnected() {
+        let (mut t1, t2) = DuplexTransport::<Json>::pair();
+        let mut t2 = Reorder::new(t2);
+        block_on(t1.send(b"b", 2u32)).unwrap();
+        drop(t1);
+        let res = block_on(t2.receive::<u32>(b"a"));
+        assert_matches!(res, Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof);
+        assert_matches!(block_on(t2.receive::<u32>(b"b")), Ok(2));
+    }
+}
@@ -0,0 +1,144 @@
+//! Transport that replays a recorded session
+
+use std::{collections::VecDeque, future::Future};
+
+use futures::io;
+use serde::{Deserialize, Serialize};
+
+use super::{
+    codec::{Codec as _, Json},
+    Direction, RecordedMessage, Transport,
+};
+
+/// A transport that plays the peer's side of a recorded session
+///
+/// The log is one recorded with [`Recording`](super::Recording) on the same side of the protocol.
+/// Messages that were received are served, in order, to [`receive`](Transport::receive), and
+/// messages that were sent are checked against the values passed to [`send`](Transport::send).
+/// Any divergence from the log, in label, value, or order, fails with
+/// [`io::ErrorKind::InvalidData`], and running past its end with
+/// [`io::ErrorKind::UnexpectedEof`].
+pub struct Replay {
+    log: VecDeque<RecordedMessage>,
+}
+
+impl Replay {
+    /// Creates a transport that replays a log of messages
+    pub fn new(log: impl IntoIterator<Item = RecordedMessage>) -> Self {
+        Self {
+            log: log.into_iter().collect(),
+        }
+    }
+
+    /// Gets the number of messages in the log that were not replayed yet
+    pub fn remaining(&self) -> usize {
+        self.log.len()
+    }
+
+    fn next(&mut self, label: &[u8], direction: Direction) -> Result<Vec<u8>, io::Error> {
+        let message = self.log.pop_front().ok_or(io::ErrorKind::UnexpectedEof)?;
+        if message.direction != direction || message.label != label {
+            return Err(divergence(format!(
+                "expected {:?} `{}`, got {:?} `{}` in the log",
+                direction,
+                String::from_utf8_lossy(label),
+                message.direction,
+                String::from_utf8_lossy(&message.label),
+            )));
+        }
+        Ok(message.bytes)
+    }
+}
+
+impl Transport for Replay {
+    async fn receive<V: for<'a> Deserialize<'a>>(
+        &mut self,
+        label: &'static [u8],
+    ) -> Result<V, io::Error> {
+        Json::decode(&self.next(label, Direction::Received)?)
+    }
+
+    fn send<V: Serialize>(
+        &mut self,
+        label: &'static [u8],
+        value: V,
+    ) -> impl Future<Output = Result<(), io::Error>> + Send {
+        let res = Json::encode(&value).and_then(|bytes| {
+            if self.next(label, Direction::Sent)? == bytes {
+                Ok(())
+            } else {
+                Err(divergence(format!(
+                    "value sent as `{}` differs from the log",
+                    String::from_utf8_lossy(label)
+                )))
+            }
+        });
+        async move { res }
+    }
+}
+
+fn divergence(msg: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+#[cfg(test)]
+mod test {
+    use std::assert_matches::assert_matches;
+
+    use curve25519_dalek::{constants::RISTRETTO_BASEPOINT_POINT, RistrettoPoint, Scalar};
+    use futures::{executor::block_on, future::try_join};
+    use rand::SeedableRng as _;
+    use rand_chacha::ChaCha20Rng;
+
+    use crate::{
+        proof::dlog_eq::{self, Publics, Secrets},
+        transport::{codec::Json, memory::DuplexTransport, Recording},
+        Error, ProofStage,
+    };
+
+    use super::Replay;
+
+    #[test]
+    fn replay_verifier() {
+        let mut rng = ChaCha20Rng::seed_from_u64(0);
+        let x = Scalar::random(&mut rng);
+        let g2 = RistrettoPoint::random(&mut rng);
+        let (h1, h2) = (x * RISTRETTO_BASEPOINT_POINT, x * g2);
+        let publics = Publics {
+            g1: &RISTRETTO_BASEPOINT_POINT,
+            h1: &h1,
+            g2: &g2,
+            h2: &h2,
+        };
+        let verify = |t: &mut Replay, seed| {
+            let mut rng = ChaCha20Rng::seed_from_u64(seed);
+            block_on(dlog_eq::verify_with_rng(t, publics, &mut rng))
+        };
+
+        let (mut p_channel, v_channel) = DuplexTransport::<Json>::pair();
+        let mut v_channel = Recording::new(v_channel);
+        let mut v_rng = ChaCha20Rng::seed_from_u64(1);
+        block_on(try_join(
+            dlog_eq::prove(&mut p_channel, publics, Secrets { x: &x }),
+            dlog_eq::verify_with_rng(&mut v_channel, publics, &mut v_rng),
+        ))
+        .unwrap();
+        let log = v_channel.into_log();
+
+        let mut replay = Replay::new(log.clone());
+        assert_matches!(verify(&mut replay, 1), Ok(_));
+        assert_eq!(replay.remaining(), 0);
+
+        let res = verify(&mut Replay::new(log.clone()), 2);
+        assert_matches!(res, Err(e) if e.is_malformed());
+
+        let mut tampered = log;
+        tampered[2].bytes = serde_json::to_vec(&Scalar::ONE).unwrap();
+        assert_matches!(
+            verify(&mut Replay::new(tampered), 1),
+            Err(Error::BadProof {
+                stage: ProofStage::FirstEquation
+            })
+        );
+    }
+}
@@ -0,0 +1,176 @@
+//! TCP transport
+
+use std::{
+    future::Future,
+    pin::Pin,
+    task::{ready, Context, Poll},
+};
+
+use futures::io::{self, AsyncRead, AsyncWrite};
+use serde::{Deserialize, Serialize};
+use tokio::{
+    io::{AsyncRead as _, AsyncWrite as _, ReadBuf},
+    net::TcpStream,
+};
+
+use super::{framed::FramedTransport, Transport};
+
+/// A transport over a TCP stream, with length-prefixed frames
+///
+/// This is a [`FramedTransport`] over the stream, with values serialized by
+/// [`Bincode`](super::codec::Bincode), so it shares its framing and, e.g. when racing a
+/// [`receive`](Transport::receive) against a timeout, its cancellation-safe receiving.
+pub struct TcpTransport(FramedTransport<Compat>);
+
+impl TcpTransport {
+    /// Creates a new transport over a connected stream
+    pub fn new(stream: TcpStream) -> Self {
+        Self(FramedTransport::new(Compat(stream)))
+    }
+
+    /// Gets the underlying stream back
+    ///
+    /// Bytes already read from the stream but not received as a whole frame are lost.
+    pub fn into_inner(self) -> TcpStream {
+        self.0.into_inner().0
+    }
+}
+
+impl Transport for TcpTransport {
+    async fn receive<V: for<'a> Deserialize<'a>>(
+        &mut self,
+        label: &'static [u8],
+    ) -> Result<V, io::Error> {
+        self.0.receive(label).await
+    }
+
+    fn send<V: Serialize>(
+        &mut self,
+        label: &'static [u8],
+        value: V,
+    ) -> impl Future<Output = Result<(), io::Error>> + Send {
+        self.0.send(label, value)
+    }
+
+    async fn receive_labeled<V: for<'a> Deserialize<'a>>(
+        &mut self,
+        label: &'static [u8],
+    ) -> Result<(Option<Vec<u8>>, V), io::Error> {
+        self.0.receive_labeled(label).await
+    }
+}
+
+/// A Tokio stream seen through the `futures` I/O traits that [`FramedTransport`] works over
+struct Compat(TcpStream);
+
+impl AsyncRead for Compat {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        let mut buf = ReadBuf::new(buf);
+        ready!(Pin::new(&mut self.0).poll_read(cx, &mut buf))?;
+        Poll::Ready(Ok(buf.filled().len()))
+    }
+}
+
+impl AsyncWrite for Compat {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.0).poll_write(cx, buf)
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.0).poll_flush(cx)
+    }
+
+    fn poll_close(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.0).poll_shutdown(cx)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{assert_matches::assert_matches, time::Duration};
+
+    use rand::thread_rng;
+    use tokio::{
+        io::AsyncWriteExt as _,
+        net::{TcpListener, TcpStream},
+        time::timeout,
+    };
+
+    use crate::{
+        transport::{
+            checked,
+            codec::{Bincode, Codec as _},
+            framed::encode_frame,
+            LabelMismatch, LocalTransport as _,
+        },
+        Error, Org, OrgSecretKey, User, UserSecretKey,
+    };
+
+    use super::TcpTransport;
+
+    async fn connected_pair() -> (TcpTransport, TcpTransport) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let (client, server) = tokio::join!(TcpStream::connect(addr), listener.accept());
+        (
+            TcpTransport::new(client.unwrap()),
+            TcpTransport::new(server.unwrap().0),
+        )
+    }
+
+    #[tokio::test]
+    async fn nym_generation_over_tcp() {
+        let user = User::new(UserSecretKey::random(&mut thread_rng()));
+        let org = Org::new(OrgSecretKey::random(&mut thread_rng()));
+
+        let (mut u_channel, mut o_channel) = connected_pair().await;
+
+        let (n1, n2) = tokio::try_join!(
+            user.generate_nym(&mut u_channel),
+            org.generate_nym(&mut o_channel),
+        )
+        .unwrap();
+        assert_eq!(n1, n2, "user and org should compute the same nym");
+    }
+
+    #[tokio::test]
+    async fn label_mismatch() {
+        let (mut sender, receiver) = connected_pair().await;
+        let mut receiver = checked(receiver);
+
+        sender.send(b"a~", 42u32).await.unwrap();
+        sender.send(b"a", 42u32).await.unwrap();
+        let res = receiver.receive::<u32>(b"a").await.map_err(Error::from);
+        assert_matches!(
+            res,
+            Err(Error::LabelMismatch(LabelMismatch { expected, got }))
+                if expected == b"a" && got == b"a~"
+        );
+        let res = receiver.receive::<u32>(b"a").await.map_err(Error::from);
+        assert_matches!(res, Ok(42));
+    }
+
+    #[tokio::test]
+    async fn cancelled_receive() {
+        let (sender, mut receiver) = connected_pair().await;
+        let mut sender = sender.into_inner();
+        let first = encode_frame(b"a", &Bincode::encode(&42u32).unwrap()).unwrap();
+        let second = encode_frame(b"b", &Bincode::encode(&43u32).unwrap()).unwrap();
+
+        sender.write_all(&first[..6]).await.unwrap();
+        let res = timeout(Duration::from_millis(100), receiver.receive::<u32>(b"a")).await;
+        assert!(res.is_err(), "receiving half a frame should time out");
+        sender.write_all(&first[6..]).await.unwrap();
+        sender.write_all(&second).await.unwrap();
+        assert_eq!(receiver.receive::<u32>(b"a").await.unwrap(), 42);
+        assert_eq!(receiver.receive::<u32>(b"b").await.unwrap(), 43);
+    }
+}
@@ -0,0 +1,106 @@
+//! Transport over a web `MessagePort`
+//!
+//! This requires the `wasm` feature, and is meant for `wasm32-unknown-unknown` builds driven by
+//! `wasm-bindgen`, e.g. with the user in a Web Worker and the organization in the main thread,
+//! connected by the two ports of a `MessageChannel`.
+//!
+//! The protocol futures must be driven from the JavaScript event loop (e.g. with
+//! `wasm_bindgen_futures::spawn_local`), since messages are only delivered to the port between
+//! tasks. Both ends of the channel must be [`PortTransport`]s, as each message is expected to be
+//! a `Uint8Array` in the format they post.
+
+use std::future::Future;
+
+use futures::{
+    channel::mpsc::{self, UnboundedReceiver},
+    io,
+    stream::StreamExt as _,
+};
+use js_sys::Uint8Array;
+use serde::{Deserialize, Serialize};
+use wasm_bindgen::{closure::Closure, JsCast as _};
+use web_sys::{MessageEvent, MessagePort};
+
+use super::{
+    codec::{Bincode, Codec as _},
+    trace_message, Direction, LocalTransport,
+};
+
+/// A transport over a web `MessagePort`
+///
+/// Each value is serialized with bincode, along with its label, and posted as a `Uint8Array`.
+///
+/// JavaScript values cannot be sent across threads, so this only implements [`LocalTransport`].
+pub struct PortTransport {
+    port: MessagePort,
+    receiver: UnboundedReceiver<Vec<u8>>,
+    _on_message: Closure<dyn FnMut(MessageEvent)>,
+}
+
+impl PortTransport {
+    /// Creates a new transport over a port, and starts receiving messages from it
+    pub fn new(port: MessagePort) -> Self {
+        let (sender, receiver) = mpsc::unbounded();
+        let on_message = Closure::<dyn FnMut(MessageEvent)>::new(move |event: MessageEvent| {
+            let _ = sender.unbounded_send(Uint8Array::new(&event.data()).to_vec());
+        });
+        port.set_onmessage(Some(on_message.as_ref().unchecked_ref()));
+        port.start();
+        Self {
+            port,
+            receiver,
+            _on_message: on_message,
+        }
+    }
+
+    /// Gets the underlying port back
+    ///
+    /// The port stops being listened to.
+    pub fn into_inner(self) -> MessagePort {
+        self.port.clone()
+    }
+}
+
+impl Drop for PortTransport {
+    fn drop(&mut self) {
+        self.port.set_onmessage(None);
+    }
+}
+
+impl LocalTransport for PortTransport {
+    async fn receive<V: for<'a> Deserialize<'a>>(
+        &mut self,
+        label: &'static [u8],
+    ) -> Result<V, io::Error> {
+        let (_, value) = self.receive_labeled(label).await?;
+        Ok(value)
+    }
+
+    fn send<V: Serialize>(
+        &mut self,
+        label: &'static [u8],
+        value: V,
+    ) -> impl Future<Output = Result<(), io::Error>> {
+        let res = Bincode::encode(&(label, value)).and_then(|bytes| {
+            trace_message(Direction::Sent, label, &bytes);
+            self.port
+                .post_message(&Uint8Array::from(bytes.as_slice()))
+                .map_err(|e| io::Error::new(io::ErrorKind::BrokenPipe, format!("{e:?}")))
+        });
+        async move { res }
+    }
+
+    async fn receive_labeled<V: for<'a> Deserialize<'a>>(
+        &mut self,
+        _label: &'static [u8],
+    ) -> Result<(Option<Vec<u8>>, V), io::Error> {
+        let bytes = self
+            .receiver
+            .next()
+            .await
+            .ok_or(io::ErrorKind::UnexpectedEof)?;
+        let (label, value) = Bincode::decode::<(Vec<u8>, V)>(&bytes)?;
+        trace_message(Direction::Received, &label, &bytes);
+        Ok((Some(label), value))
+    }
+}
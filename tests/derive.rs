@@ -0,0 +1,46 @@
+#![cfg(feature = "derive")]
+
+use curve25519_dalek::{RistrettoPoint, Scalar};
+use merlin::Transcript;
+use nym::hash::{append_length, AppendMessage, Transcribe, TranscriptProtocol as _};
+use rand::thread_rng;
+
+#[derive(Transcribe)]
+struct Derived {
+    a: RistrettoPoint,
+    #[transcribe(skip)]
+    _skipped: u64,
+    #[transcribe(label = "scalar")]
+    b: Scalar,
+}
+
+struct Manual {
+    a: RistrettoPoint,
+    b: Scalar,
+}
+
+impl Transcribe for Manual {
+    fn append_to<S: AppendMessage + ?Sized>(&self, t: &mut S, label: &'static [u8]) {
+        append_length(t, label, 2);
+        self.a.append_to(t, b"a");
+        self.b.append_to(t, b"scalar");
+    }
+}
+
+#[test]
+fn derived_matches_manual() {
+    let a = RistrettoPoint::random(&mut thread_rng());
+    let b = Scalar::random(&mut thread_rng());
+    let mut t1 = Transcript::new(b"test-transcript");
+    t1.commit(b"value", &Derived { a, _skipped: 7, b });
+    let mut t2 = Transcript::new(b"test-transcript");
+    t2.commit(b"value", &Manual { a, b });
+    assert_eq!(t1.challenge::<Scalar>(b"c"), t2.challenge::<Scalar>(b"c"));
+}
+
+#[test]
+fn derive_expansion() {
+    let t = trybuild::TestCases::new();
+    t.pass("tests/ui/pass-*.rs");
+    t.compile_fail("tests/ui/fail-*.rs");
+}
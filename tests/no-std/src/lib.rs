@@ -0,0 +1,33 @@
+//! Checks that the crate builds without the standard library
+
+#![no_std]
+
+use nym::{
+    proof::dlog::{self, Publics, Secrets},
+    OrgSecretKey,
+};
+use rand_core::{CryptoRng, RngCore};
+
+/// Proves ownership of an organization key, and verifies it
+pub fn ownership_round_trip<R: CryptoRng + RngCore>(rng: &mut R) -> nym::Result {
+    let key = OrgSecretKey::random(rng);
+    let proof = key.ownership_proof_with_rng(rng);
+    key.to_public().verify_ownership_proof(&proof)
+}
+
+/// Proves knowledge of a discrete logarithm non-interactively, and verifies it
+pub fn dlog_round_trip<R: CryptoRng + RngCore>(
+    publics: Publics,
+    secrets: Secrets,
+    rng: &mut R,
+) -> nym::Result {
+    dlog::prove_noninteractive_with_rng(publics, secrets, rng).verify(publics)
+}
+
+/// Proves ownership of an organization key with the operating system's randomness, which needs
+/// the `getrandom` feature without `std`
+#[cfg(feature = "getrandom")]
+pub fn ownership_round_trip_os_rng(key: &OrgSecretKey) -> nym::Result {
+    key.to_public()
+        .verify_ownership_proof(&key.ownership_proof())
+}
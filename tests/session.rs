@@ -0,0 +1,7 @@
+#![cfg(feature = "std")]
+
+#[test]
+fn typed_session_order() {
+    let t = trybuild::TestCases::new();
+    t.compile_fail("tests/ui/session/fail-*.rs");
+}
@@ -0,0 +1,9 @@
+use nym::hash::Transcribe;
+
+#[derive(Transcribe)]
+enum Choice {
+    A,
+    B,
+}
+
+fn main() {}
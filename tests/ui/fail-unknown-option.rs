@@ -0,0 +1,9 @@
+use nym::hash::Transcribe;
+
+#[derive(Transcribe)]
+struct Options {
+    #[transcribe(rename = "x")]
+    field: u64,
+}
+
+fn main() {}
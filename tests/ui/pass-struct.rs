@@ -0,0 +1,24 @@
+use curve25519_dalek::{RistrettoPoint, Scalar};
+use nym::hash::Transcribe;
+
+#[derive(Transcribe)]
+struct Named {
+    point: RistrettoPoint,
+    #[transcribe(label = "s")]
+    scalar: Scalar,
+    #[transcribe(skip)]
+    _skipped: std::time::Duration,
+}
+
+#[derive(Transcribe)]
+struct Tuple(u64, String);
+
+#[derive(Transcribe)]
+struct Generic<T> {
+    items: Vec<T>,
+}
+
+#[derive(Transcribe)]
+struct Unit;
+
+fn main() {}
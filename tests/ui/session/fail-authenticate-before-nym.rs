@@ -0,0 +1,17 @@
+use nym::{
+    session::TypedUserSession,
+    transport::{codec::Json, memory::DuplexTransport},
+    User, UserSecretKey,
+};
+use rand::thread_rng;
+
+async fn authenticate_before_nym() {
+    let user = User::new(UserSecretKey::random(&mut thread_rng()));
+    let (channel, _) = DuplexTransport::<Json>::pair();
+    let mut session = TypedUserSession::new(&user, channel);
+    let _ = session.authenticate().await;
+}
+
+fn main() {
+    let _ = authenticate_before_nym();
+}
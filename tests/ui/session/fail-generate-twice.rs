@@ -0,0 +1,18 @@
+use nym::{
+    session::TypedUserSession,
+    transport::{codec::Json, memory::DuplexTransport},
+    User, UserSecretKey,
+};
+use rand::thread_rng;
+
+async fn generate_twice() {
+    let user = User::new(UserSecretKey::random(&mut thread_rng()));
+    let (channel, _) = DuplexTransport::<Json>::pair();
+    let session = TypedUserSession::new(&user, channel);
+    let _ = session.generate_nym().await;
+    let _ = session.generate_nym().await;
+}
+
+fn main() {
+    let _ = generate_twice();
+}
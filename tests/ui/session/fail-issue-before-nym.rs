@@ -0,0 +1,17 @@
+use nym::{
+    session::TypedOrgSession,
+    transport::{codec::Json, memory::DuplexTransport},
+    Org, OrgSecretKey,
+};
+use rand::thread_rng;
+
+async fn issue_before_nym() {
+    let org = Org::new(OrgSecretKey::random(&mut thread_rng()));
+    let (channel, _) = DuplexTransport::<Json>::pair();
+    let mut session = TypedOrgSession::new(&org, channel);
+    let _ = session.issue_credential(0).await;
+}
+
+fn main() {
+    let _ = issue_before_nym();
+}